@@ -0,0 +1,358 @@
+use std::ops::Range;
+use std::time::Duration;
+
+use anyhow::*;
+
+use crate::atlas::Atlas;
+use crate::sprite::{Rect, Sprite, TextureId};
+
+/// What a clip does once it reaches its last frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    /// Starts over from the first frame.
+    Loop,
+    /// Plays backwards to the first frame, then forwards again.
+    PingPong,
+    /// Stays on the last frame.
+    Once,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Frame {
+    pub texture: TextureId,
+    pub source: Rect,
+    pub duration: Duration,
+}
+
+/// A named sequence of frames, with events that fire when playback reaches a frame.
+#[derive(Clone, Debug)]
+pub struct Clip {
+    pub name: String,
+    pub frames: Vec<Frame>,
+    pub mode: PlayMode,
+    /// Frame index and event name.
+    pub events: Vec<(usize, String)>,
+}
+
+impl Clip {
+    pub fn new(name: &str, mode: PlayMode) -> Self {
+        Self {
+            name: name.to_string(),
+            frames: Vec::new(),
+            mode,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_frame(mut self, frame: Frame) -> Self {
+        self.frames.push(frame);
+        self
+    }
+
+    /// Fires `event` every time playback enters `frame`.
+    pub fn with_event(mut self, frame: usize, event: &str) -> Self {
+        self.events.push((frame, event.to_string()));
+        self
+    }
+
+    /// A clip showing the atlas regions in `names` for `duration` each. `pages` are the
+    /// batch's ids for the atlas pages, in the same order.
+    pub fn from_atlas(
+        name: &str,
+        atlas: &Atlas,
+        pages: &[TextureId],
+        names: &[&str],
+        duration: Duration,
+        mode: PlayMode,
+    ) -> Result<Self> {
+        let mut clip = Self::new(name, mode);
+        for frame_name in names {
+            let region = atlas
+                .region(frame_name)
+                .with_context(|| format!("The atlas has no region {:?}", frame_name))?;
+            let page = &atlas.manifest.pages[region.page];
+            clip.frames.push(Frame {
+                texture: *pages
+                    .get(region.page)
+                    .with_context(|| format!("No texture id for atlas page {}", region.page))?,
+                source: region.uv(page),
+                duration,
+            });
+        }
+        Ok(clip)
+    }
+
+    /// How long one pass through the frames takes.
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+/// A texture split into equally sized cells, numbered left to right, top to bottom.
+#[derive(Copy, Clone, Debug)]
+pub struct Grid {
+    pub texture: TextureId,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl Grid {
+    pub fn cell(&self, index: u32) -> Rect {
+        let width = 1.0 / self.columns as f32;
+        let height = 1.0 / self.rows as f32;
+        Rect {
+            x: (index % self.columns) as f32 * width,
+            y: (index / self.columns) as f32 * height,
+            width,
+            height,
+        }
+    }
+
+    /// A clip showing `cells` in order for `duration` each.
+    pub fn clip(&self, name: &str, cells: Range<u32>, duration: Duration, mode: PlayMode) -> Clip {
+        let mut clip = Clip::new(name, mode);
+        clip.frames = cells
+            .map(|index| Frame {
+                texture: self.texture,
+                source: self.cell(index),
+                duration,
+            })
+            .collect();
+        clip
+    }
+}
+
+/// Plays one clip at a time out of a set. Advancing by elapsed time rather than by
+/// frames keeps the playback rate the same however fast the app renders.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    clips: Vec<Clip>,
+    current: usize,
+    frame: usize,
+    /// Time spent on the current frame.
+    elapsed: Duration,
+    backwards: bool,
+    finished: bool,
+    /// Set when a frame was entered outside of `update`, so its events fire on the next one.
+    entered: bool,
+    /// Indices into the current clip's events, fired during the last update.
+    fired: Vec<usize>,
+    /// Multiplies the time passed to `update`.
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Animation {
+    /// Starts out playing the first clip.
+    pub fn new(clips: Vec<Clip>) -> Result<Self> {
+        ensure!(!clips.is_empty(), "An animation needs at least one clip");
+        for clip in &clips {
+            ensure!(
+                !clip.frames.is_empty(),
+                "Clip {:?} has no frames",
+                clip.name
+            );
+        }
+        Ok(Self {
+            clips,
+            current: 0,
+            frame: 0,
+            elapsed: Duration::default(),
+            backwards: false,
+            finished: false,
+            entered: true,
+            fired: Vec::new(),
+            speed: 1.0,
+            paused: false,
+        })
+    }
+
+    /// Switches to the clip called `name`. Playing the clip that is already playing
+    /// carries on where it is, use `restart` to start it over.
+    pub fn play(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .clips
+            .iter()
+            .position(|clip| clip.name == name)
+            .with_context(|| format!("No clip called {:?}", name))?;
+        if index != self.current {
+            self.current = index;
+            self.restart();
+        }
+        Ok(())
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = Duration::default();
+        self.backwards = false;
+        self.finished = false;
+        self.entered = true;
+        // They index into the events of the clip that was playing
+        self.fired.clear();
+    }
+
+    pub fn clip(&self) -> &Clip {
+        &self.clips[self.current]
+    }
+
+    pub fn frame_index(&self) -> usize {
+        self.frame
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.clip().frames[self.frame]
+    }
+
+    /// Whether a `PlayMode::Once` clip has reached its end.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn update(&mut self, dt: Duration) {
+        self.fired.clear();
+        if self.entered {
+            self.entered = false;
+            self.fire_events();
+        }
+        if self.paused || self.finished {
+            return;
+        }
+
+        self.elapsed += dt.mul_f32(self.speed.max(0.0));
+        loop {
+            let duration = self.frame().duration;
+            // Zero length frames would never let the loop end
+            if self.elapsed < duration || self.clip().duration() == Duration::default() {
+                break;
+            }
+            self.elapsed -= duration;
+            if !self.advance() {
+                self.finished = true;
+                self.elapsed = Duration::default();
+                break;
+            }
+            self.fire_events();
+        }
+    }
+
+    /// Moves to the next frame, returning false when a `Once` clip has none left.
+    fn advance(&mut self) -> bool {
+        let clip = &self.clips[self.current];
+        let last = clip.frames.len() - 1;
+        match clip.mode {
+            PlayMode::Loop => self.frame = if self.frame < last { self.frame + 1 } else { 0 },
+            PlayMode::Once if self.frame == last => return false,
+            PlayMode::Once => self.frame += 1,
+            PlayMode::PingPong if last == 0 => {}
+            PlayMode::PingPong => {
+                if self.backwards && self.frame == 0 {
+                    self.backwards = false;
+                } else if !self.backwards && self.frame == last {
+                    self.backwards = true;
+                }
+                if self.backwards {
+                    self.frame -= 1;
+                } else {
+                    self.frame += 1;
+                }
+            }
+        }
+        true
+    }
+
+    fn fire_events(&mut self) {
+        let frame = self.frame;
+        let events = &self.clips[self.current].events;
+        self.fired.extend(
+            events
+                .iter()
+                .enumerate()
+                .filter(|(_, (event_frame, _))| *event_frame == frame)
+                .map(|(index, _)| index),
+        );
+    }
+
+    /// Events of the frames entered during the last `update`, in order.
+    pub fn events(&self) -> impl Iterator<Item = &str> {
+        let events = &self.clips[self.current].events;
+        self.fired
+            .iter()
+            .filter_map(move |index| events.get(*index))
+            .map(|(_, event)| event.as_str())
+    }
+
+    /// Points `sprite` at the current frame.
+    pub fn apply(&self, sprite: &mut Sprite) {
+        let frame = self.frame();
+        sprite.texture = frame.texture;
+        sprite.source = frame.source;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(100);
+
+    fn clip(name: &str, mode: PlayMode, frames: usize) -> Clip {
+        let frame = Frame {
+            texture: TextureId(0),
+            source: Rect::FULL,
+            duration: FRAME,
+        };
+        (0..frames).fold(Clip::new(name, mode), |clip, _| clip.with_frame(frame))
+    }
+
+    fn frames(animation: &mut Animation, steps: usize) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                animation.update(FRAME);
+                animation.frame_index()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn play_modes() {
+        let mut animation = Animation::new(vec![
+            clip("loop", PlayMode::Loop, 3),
+            clip("ping-pong", PlayMode::PingPong, 3),
+            clip("once", PlayMode::Once, 3),
+        ])
+        .unwrap();
+        assert_eq!(frames(&mut animation, 5), [1, 2, 0, 1, 2]);
+        animation.play("ping-pong").unwrap();
+        assert_eq!(frames(&mut animation, 5), [1, 2, 1, 0, 1]);
+        animation.play("once").unwrap();
+        assert_eq!(frames(&mut animation, 4), [1, 2, 2, 2]);
+        assert!(animation.is_finished());
+    }
+
+    #[test]
+    fn events_fire_on_entering_frames() {
+        let walk = clip("walk", PlayMode::Loop, 2)
+            .with_event(0, "step")
+            .with_event(1, "step");
+        let mut animation = Animation::new(vec![walk]).unwrap();
+        animation.update(Duration::default());
+        assert_eq!(animation.events().collect::<Vec<_>>(), ["step"]);
+        // Skipping over two frames fires both of them
+        animation.update(FRAME * 2);
+        assert_eq!(animation.events().collect::<Vec<_>>(), ["step", "step"]);
+    }
+
+    #[test]
+    fn switching_clips_forgets_fired_events() {
+        let attack = clip("attack", PlayMode::Once, 1)
+            .with_event(0, "swing")
+            .with_event(0, "hit");
+        let idle = clip("idle", PlayMode::Loop, 1);
+        let mut animation = Animation::new(vec![attack, idle]).unwrap();
+        animation.update(Duration::default());
+        assert_eq!(animation.events().count(), 2);
+        animation.play("idle").unwrap();
+        assert_eq!(animation.events().count(), 0);
+    }
+}
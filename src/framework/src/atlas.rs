@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::*;
+
+use crate::json::{self, json, Fields};
+use crate::sprite::Rect;
+use crate::texture::Texture;
+
+/// One atlas image as listed in a manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+    /// File name of the image, relative to the manifest.
+    pub image: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Where an image ended up in the atlas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub name: String,
+    /// Index into the manifest's pages.
+    pub page: usize,
+    /// Position and size in pixels, without the padding and extruded edges.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// The region in texture coordinates, ready for `Sprite::source`.
+    pub fn uv(&self, page: &Page) -> Rect {
+        Rect::from_pixels(
+            self.x,
+            self.y,
+            self.width,
+            self.height,
+            page.width,
+            page.height,
+        )
+    }
+}
+
+/// The JSON file written next to the atlas images. Each region is stored with its pixel
+/// rectangle and, for tools that don't want to do the division, its UV rectangle.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Manifest {
+    pub pages: Vec<Page>,
+    pub regions: Vec<Region>,
+}
+
+impl Manifest {
+    pub fn to_json(&self) -> String {
+        let pages = self
+            .pages
+            .iter()
+            .map(|page| {
+                json!({
+                    "image": page.image,
+                    "width": page.width,
+                    "height": page.height,
+                })
+            })
+            .collect::<Vec<_>>();
+        let regions = self
+            .regions
+            .iter()
+            .map(|region| {
+                let uv = region.uv(&self.pages[region.page]);
+                json!({
+                    "name": region.name,
+                    "page": region.page,
+                    "rect": [region.x, region.y, region.width, region.height],
+                    "uv": [uv.x, uv.y, uv.width, uv.height],
+                })
+            })
+            .collect::<Vec<_>>();
+        let manifest = json!({ "pages": pages, "regions": regions });
+        format!("{:#}\n", manifest)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        let root = json::parse(text)?;
+        let pages = root
+            .field_array("pages")?
+            .iter()
+            .enumerate()
+            .map(|(index, page)| {
+                let parse = || -> Result<Page> {
+                    Ok(Page {
+                        image: page.field_str("image")?.to_string(),
+                        width: page.field_u32("width")?,
+                        height: page.field_u32("height")?,
+                    })
+                };
+                parse().with_context(|| format!("Invalid page {}", index))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let regions = root
+            .field_array("regions")?
+            .iter()
+            .enumerate()
+            .map(|(index, region)| {
+                let parse = || -> Result<Region> {
+                    let name = region.field_str("name")?.to_string();
+                    let page = region.field_u32("page")? as usize;
+                    ensure!(page < pages.len(), "Page {} doesn't exist", page);
+                    let rect = region
+                        .field_array("rect")?
+                        .iter()
+                        .map(|value| value.as_u64().map(|value| value as u32))
+                        .collect::<Option<Vec<_>>>()
+                        .filter(|rect| rect.len() == 4)
+                        .context("rect should hold x, y, width and height in pixels")?;
+                    Ok(Region {
+                        name,
+                        page,
+                        x: rect[0],
+                        y: rect[1],
+                        width: rect[2],
+                        height: rect[3],
+                    })
+                };
+                parse().with_context(|| format!("Invalid region {}", index))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { pages, regions })
+    }
+}
+
+/// Atlas pages loaded onto the GPU with a table from image names to where they are.
+pub struct Atlas {
+    pub manifest: Manifest,
+    pub pages: Vec<Texture>,
+    lookup: HashMap<String, usize>,
+}
+
+impl Atlas {
+    /// Loads a manifest written by the atlas-packer tool and the pages next to it.
+    pub fn load<P: AsRef<Path>>(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        manifest_path: P,
+    ) -> Result<Self> {
+        let manifest_path = manifest_path.as_ref();
+        let text = std::fs::read_to_string(manifest_path)
+            .with_context(|| format!("Couldn't read {:?}", manifest_path))?;
+        let manifest = Manifest::from_json(&text)
+            .with_context(|| format!("Couldn't parse {:?}", manifest_path))?;
+        let directory = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+
+        let pages = manifest
+            .pages
+            .iter()
+            .map(|page| {
+                let path = directory.join(&page.image);
+                let img =
+                    image::open(&path).with_context(|| format!("Couldn't open {:?}", path))?;
+                Texture::from_image(device, queue, &img, Some(&page.image), false)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::new(manifest, pages))
+    }
+
+    pub fn new(manifest: Manifest, pages: Vec<Texture>) -> Self {
+        let lookup = manifest
+            .regions
+            .iter()
+            .enumerate()
+            .map(|(index, region)| (region.name.clone(), index))
+            .collect();
+        Self {
+            manifest,
+            pages,
+            lookup,
+        }
+    }
+
+    pub fn region(&self, name: &str) -> Option<&Region> {
+        self.lookup
+            .get(name)
+            .map(|index| &self.manifest.regions[*index])
+    }
+
+    /// The page texture and texture coordinates for `name`.
+    pub fn get(&self, name: &str) -> Option<(&Texture, Rect)> {
+        let region = self.region(name)?;
+        let page = &self.manifest.pages[region.page];
+        Some((&self.pages[region.page], region.uv(page)))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.manifest
+            .regions
+            .iter()
+            .map(|region| region.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> Manifest {
+        Manifest {
+            pages: vec![Page {
+                image: "atlas0.png".to_string(),
+                width: 256,
+                height: 128,
+            }],
+            regions: vec![Region {
+                name: "happy-tree".to_string(),
+                page: 0,
+                x: 2,
+                y: 4,
+                width: 64,
+                height: 32,
+            }],
+        }
+    }
+
+    #[test]
+    fn manifest_round_trip() {
+        let manifest = manifest();
+        assert_eq!(Manifest::from_json(&manifest.to_json()).unwrap(), manifest);
+    }
+
+    #[test]
+    fn region_on_missing_page() {
+        let text = manifest().to_json().replace("\"page\": 0", "\"page\": 1");
+        assert!(Manifest::from_json(&text).is_err());
+    }
+}
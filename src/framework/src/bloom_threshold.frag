@@ -0,0 +1,36 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_source;
+layout(set=0, binding=2) uniform Bloom {
+    float u_threshold;
+    float u_knee;
+    float u_radius;
+    float _padding;
+};
+
+vec3 sample_box(vec2 uv) {
+    vec2 texel = 1.0 / vec2(textureSize(sampler2D(t_source, s_source), 0));
+    // Four bilinear taps average a 4x4 block of the source
+    vec4 offset = texel.xyxy * vec4(-1.0, -1.0, 1.0, 1.0);
+    return (texture(sampler2D(t_source, s_source), uv + offset.xy).rgb
+        + texture(sampler2D(t_source, s_source), uv + offset.zy).rgb
+        + texture(sampler2D(t_source, s_source), uv + offset.xw).rgb
+        + texture(sampler2D(t_source, s_source), uv + offset.zw).rgb) * 0.25;
+}
+
+void main() {
+    vec3 color = sample_box(v_tex_coords);
+
+    // Soft knee so pixels just under the threshold fade in instead of popping
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - u_threshold + u_knee, 0.0, 2.0 * u_knee);
+    soft = soft * soft / (4.0 * u_knee + 0.00001);
+    float contribution = max(soft, brightness - u_threshold) / max(brightness, 0.00001);
+
+    f_color = vec4(color * contribution, 1.0);
+}
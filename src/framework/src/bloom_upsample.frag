@@ -0,0 +1,31 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_source;
+layout(set=0, binding=2) uniform Bloom {
+    float u_threshold;
+    float u_knee;
+    float u_radius;
+    float _padding;
+};
+
+void main() {
+    vec2 texel = u_radius / vec2(textureSize(sampler2D(t_source, s_source), 0));
+
+    // 3x3 tent filter, the result is added on top of the next larger level
+    vec3 color = texture(sampler2D(t_source, s_source), v_tex_coords).rgb * 4.0;
+    color += texture(sampler2D(t_source, s_source), v_tex_coords + vec2(-texel.x, 0.0)).rgb * 2.0;
+    color += texture(sampler2D(t_source, s_source), v_tex_coords + vec2(texel.x, 0.0)).rgb * 2.0;
+    color += texture(sampler2D(t_source, s_source), v_tex_coords + vec2(0.0, -texel.y)).rgb * 2.0;
+    color += texture(sampler2D(t_source, s_source), v_tex_coords + vec2(0.0, texel.y)).rgb * 2.0;
+    color += texture(sampler2D(t_source, s_source), v_tex_coords + vec2(-texel.x, -texel.y)).rgb;
+    color += texture(sampler2D(t_source, s_source), v_tex_coords + vec2(texel.x, -texel.y)).rgb;
+    color += texture(sampler2D(t_source, s_source), v_tex_coords + vec2(-texel.x, texel.y)).rgb;
+    color += texture(sampler2D(t_source, s_source), v_tex_coords + vec2(texel.x, texel.y)).rgb;
+
+    f_color = vec4(color / 16.0, 1.0);
+}
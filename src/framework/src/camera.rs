@@ -0,0 +1,275 @@
+use cgmath::prelude::*;
+use wgpu::util::DeviceExt;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+// wgpu's clip space has z in 0..1, cgmath builds OpenGL style -1..1 projections.
+#[rustfmt::skip]
+pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+pub struct Camera {
+    pub eye: cgmath::Point3<f32>,
+    pub target: cgmath::Point3<f32>,
+    pub up: cgmath::Vector3<f32>,
+    pub aspect: f32,
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl Camera {
+    pub fn new(eye: cgmath::Point3<f32>, target: cgmath::Point3<f32>, aspect: f32) -> Self {
+        Self {
+            eye,
+            target,
+            up: cgmath::Vector3::unit_y(),
+            aspect,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        }
+    }
+
+    pub fn view_matrix(&self) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    pub fn projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        OPENGL_TO_WGPU_MATRIX
+            * cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar)
+    }
+
+    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        self.projection_matrix() * self.view_matrix()
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    view_position: [f32; 4],
+    view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    pub fn new() -> Self {
+        Self {
+            view_position: [0.0; 4],
+            view_proj: cgmath::Matrix4::identity().into(),
+        }
+    }
+
+    pub fn update_view_proj(&mut self, camera: &Camera) {
+        self.view_position = camera.eye.to_homogeneous().into();
+        self.view_proj = camera.build_view_projection_matrix().into();
+    }
+}
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The camera uniform buffer together with the bind group that exposes it to the
+/// vertex and fragment stages at binding 0.
+pub struct CameraBinding {
+    pub uniform: CameraUniform,
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl CameraBinding {
+    pub fn new(device: &wgpu::Device, camera: &Camera) -> Self {
+        let mut uniform = CameraUniform::new();
+        uniform.update_view_proj(camera);
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("camera_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("camera_bind_group"),
+        });
+
+        Self {
+            uniform,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, camera: &Camera) {
+        self.uniform.update_view_proj(camera);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+}
+
+/// Orbits the camera around its target. W/S move toward and away from the target,
+/// A/D circle around it and Space/LShift raise and lower the eye.
+pub struct CameraController {
+    speed: f32,
+    is_forward_pressed: bool,
+    is_backward_pressed: bool,
+    is_left_pressed: bool,
+    is_right_pressed: bool,
+    is_up_pressed: bool,
+    is_down_pressed: bool,
+}
+
+impl CameraController {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            is_forward_pressed: false,
+            is_backward_pressed: false,
+            is_left_pressed: false,
+            is_right_pressed: false,
+            is_up_pressed: false,
+            is_down_pressed: false,
+        }
+    }
+
+    pub fn process_events(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => {
+                let is_pressed = *state == ElementState::Pressed;
+                match keycode {
+                    VirtualKeyCode::W | VirtualKeyCode::Up => {
+                        self.is_forward_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::S | VirtualKeyCode::Down => {
+                        self.is_backward_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::A | VirtualKeyCode::Left => {
+                        self.is_left_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::D | VirtualKeyCode::Right => {
+                        self.is_right_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::Space => {
+                        self.is_up_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::LShift => {
+                        self.is_down_pressed = is_pressed;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    pub fn update_camera(&self, camera: &mut Camera, dt: std::time::Duration) {
+        let step = self.speed * dt.as_secs_f32();
+        let forward = camera.target - camera.eye;
+        let forward_norm = forward.normalize();
+        let forward_mag = forward.magnitude();
+
+        // Stop short of the target so the view matrix never degenerates.
+        if self.is_forward_pressed && forward_mag > step + 0.5 {
+            camera.eye += forward_norm * step;
+        }
+        if self.is_backward_pressed {
+            camera.eye -= forward_norm * step;
+        }
+        if self.is_up_pressed {
+            camera.eye += camera.up * step;
+        }
+        if self.is_down_pressed {
+            camera.eye -= camera.up * step;
+        }
+
+        let right = forward_norm.cross(camera.up);
+
+        // Redo the radius calculation in case forward/backward moved the eye.
+        let forward = camera.target - camera.eye;
+        let forward_mag = forward.magnitude();
+
+        if self.is_right_pressed {
+            camera.eye = camera.target - (forward + right * step).normalize() * forward_mag;
+        }
+        if self.is_left_pressed {
+            camera.eye = camera.target - (forward - right * step).normalize() * forward_mag;
+        }
+    }
+}
+
+/// Looks at a flat world measured in pixels with y pointing down, the way sprites and
+/// tilemaps are laid out.
+#[derive(Copy, Clone, Debug)]
+pub struct Camera2d {
+    /// The world point at the center of the screen.
+    pub position: cgmath::Vector2<f32>,
+    /// Screen pixels per world pixel.
+    pub zoom: f32,
+    /// Size of the render target in pixels.
+    pub viewport: cgmath::Vector2<f32>,
+}
+
+impl Camera2d {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            position: cgmath::Vector2::new(width / 2.0, height / 2.0),
+            zoom: 1.0,
+            viewport: cgmath::Vector2::new(width, height),
+        }
+    }
+
+    /// The top left and bottom right corners of what's on screen, in world pixels.
+    pub fn visible_bounds(&self) -> (cgmath::Vector2<f32>, cgmath::Vector2<f32>) {
+        let half = self.viewport / (2.0 * self.zoom);
+        (self.position - half, self.position + half)
+    }
+
+    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        let (min, max) = self.visible_bounds();
+        OPENGL_TO_WGPU_MATRIX * cgmath::ortho(min.x, max.x, max.y, min.y, -1.0, 1.0)
+    }
+
+    /// The world point under a position in window pixels.
+    pub fn screen_to_world(&self, screen: cgmath::Vector2<f32>) -> cgmath::Vector2<f32> {
+        self.position + (screen - self.viewport / 2.0) / self.zoom
+    }
+}
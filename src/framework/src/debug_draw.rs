@@ -0,0 +1,342 @@
+//! Immediate mode debug lines. Any code running on the main thread can call
+//! `line`, `aabb`, `circle`, `arrow` or `axes` during `update()`, and a
+//! `DebugDraw` renderer draws everything queued so far at the end of `render()`.
+//!
+//! ```ignore
+//! debug_draw::arrow(from, to, debug_draw::YELLOW).lifetime(2.0);
+//! debug_draw::axes(model).depth_test(false);
+//! ```
+use std::cell::RefCell;
+use std::ops::Range;
+use std::time::Instant;
+
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Vector3};
+
+use crate::light::{perpendicular_axes, GizmoVertex};
+
+pub const RED: Vector3<f32> = Vector3::new(1.0, 0.0, 0.0);
+pub const GREEN: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
+pub const BLUE: Vector3<f32> = Vector3::new(0.0, 0.0, 1.0);
+pub const YELLOW: Vector3<f32> = Vector3::new(1.0, 1.0, 0.0);
+pub const CYAN: Vector3<f32> = Vector3::new(0.0, 1.0, 1.0);
+pub const MAGENTA: Vector3<f32> = Vector3::new(1.0, 0.0, 1.0);
+pub const WHITE: Vector3<f32> = Vector3::new(1.0, 1.0, 1.0);
+
+const CIRCLE_SEGMENTS: usize = 32;
+/// Length of an arrow head relative to the whole arrow.
+const ARROW_HEAD: f32 = 0.2;
+const INITIAL_CAPACITY: usize = 1024;
+
+#[derive(Copy, Clone, Debug)]
+struct Segment {
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    color: Vector3<f32>,
+    depth_test: bool,
+    /// Seconds left before the segment disappears, zero draws it for a single frame.
+    lifetime: f32,
+}
+
+thread_local! {
+    static QUEUE: RefCell<Vec<Segment>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The segments of a shape that was just queued. Dropping it keeps the defaults:
+/// depth tested and drawn for a single frame.
+pub struct Shape {
+    segments: Range<usize>,
+}
+
+impl Shape {
+    /// Keeps the shape on screen for `seconds` instead of a single frame.
+    pub fn lifetime(self, seconds: f32) -> Self {
+        self.modify(|segment| segment.lifetime = seconds.max(0.0));
+        self
+    }
+
+    /// Whether the shape is hidden behind closer geometry, `false` draws it on top of everything.
+    pub fn depth_test(self, enabled: bool) -> Self {
+        self.modify(|segment| segment.depth_test = enabled);
+        self
+    }
+
+    fn modify(&self, f: impl Fn(&mut Segment)) {
+        QUEUE.with(|queue| {
+            let mut queue = queue.borrow_mut();
+            // A flush between queueing and modifying leaves nothing to change
+            if let Some(segments) = queue.get_mut(self.segments.clone()) {
+                segments.iter_mut().for_each(f);
+            }
+        });
+    }
+}
+
+fn push_lines(
+    lines: impl IntoIterator<Item = (Vector3<f32>, Vector3<f32>)>,
+    color: Vector3<f32>,
+) -> Shape {
+    QUEUE.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        let start = queue.len();
+        queue.extend(lines.into_iter().map(|(a, b)| Segment {
+            a,
+            b,
+            color,
+            depth_test: true,
+            lifetime: 0.0,
+        }));
+        Shape {
+            segments: start..queue.len(),
+        }
+    })
+}
+
+pub fn line(a: Vector3<f32>, b: Vector3<f32>, color: Vector3<f32>) -> Shape {
+    push_lines(Some((a, b)), color)
+}
+
+/// The 12 edges of an axis aligned box.
+pub fn aabb(min: Vector3<f32>, max: Vector3<f32>, color: Vector3<f32>) -> Shape {
+    let corner = |i: usize| {
+        Vector3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )
+    };
+    // Every pair of corners whose indices differ in exactly one bit shares an edge
+    let edges = (0..8).flat_map(|i| {
+        [1, 2, 4]
+            .iter()
+            .filter(move |bit| i & **bit == 0)
+            .map(move |bit| (corner(i), corner(i | bit)))
+    });
+    push_lines(edges, color)
+}
+
+/// A circle around `center` in the plane facing `normal`.
+pub fn circle(
+    center: Vector3<f32>,
+    normal: Vector3<f32>,
+    radius: f32,
+    color: Vector3<f32>,
+) -> Shape {
+    let (right, up) = perpendicular_axes(normal);
+    let point = |i: usize| {
+        let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+        center + (right * angle.cos() + up * angle.sin()) * radius
+    };
+    push_lines(
+        (0..CIRCLE_SEGMENTS).map(|i| (point(i), point(i + 1))),
+        color,
+    )
+}
+
+/// A line from `from` to `to` with a four pronged head at `to`.
+pub fn arrow(from: Vector3<f32>, to: Vector3<f32>, color: Vector3<f32>) -> Shape {
+    let shaft = to - from;
+    let length = shaft.magnitude();
+    if length <= f32::EPSILON {
+        return push_lines(None, color);
+    }
+
+    let (right, up) = perpendicular_axes(shaft);
+    let base = to - shaft * ARROW_HEAD;
+    let width = length * ARROW_HEAD * 0.5;
+    let head = [right, up, -right, -up]
+        .iter()
+        .map(|side| (to, base + side * width))
+        .collect::<Vec<_>>();
+    push_lines(Some((from, to)).into_iter().chain(head), color)
+}
+
+/// Red, green and blue lines along the X, Y and Z axes of `transform`, so they
+/// follow its translation, rotation and scale.
+pub fn axes(transform: Matrix4<f32>) -> Shape {
+    let origin = transform.w.truncate();
+    let start = QUEUE.with(|queue| queue.borrow().len());
+    line(origin, origin + transform.x.truncate(), RED);
+    line(origin, origin + transform.y.truncate(), GREEN);
+    let end = line(origin, origin + transform.z.truncate(), BLUE)
+        .segments
+        .end;
+    Shape {
+        segments: start..end,
+    }
+}
+
+/// Draws the queued debug shapes as a single line list, reusing the light gizmo shaders.
+pub struct DebugDraw {
+    depth_tested_pipeline: Option<wgpu::RenderPipeline>,
+    overlay_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    capacity: usize,
+    /// Segments from earlier frames that have not run out of lifetime yet.
+    segments: Vec<Segment>,
+    num_depth_tested: u32,
+    num_vertices: u32,
+    last_prepare: Instant,
+}
+
+impl DebugDraw {
+    /// Without a `depth_format` every shape is drawn on top, as if it had
+    /// `depth_test(false)`.
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        sample_count: u32,
+    ) -> Self {
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("light_gizmo.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("light_gizmo.frag.spv"));
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Draw Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label, depth_compare| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &vs_module,
+                    entry_point: "main",
+                    buffers: &[GizmoVertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &fs_module,
+                    entry_point: "main",
+                    targets: &[wgpu::ColorTargetState {
+                        format: color_format,
+                        alpha_blend: wgpu::BlendState::REPLACE,
+                        color_blend: wgpu::BlendState::REPLACE,
+                        write_mask: wgpu::ColorWrite::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: wgpu::CullMode::None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                },
+                depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                    format,
+                    depth_write_enabled: false,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                    clamp_depth: false,
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+            })
+        };
+
+        let depth_tested_pipeline = depth_format.map(|_| {
+            create_pipeline(
+                "Debug Draw Depth Tested Pipeline",
+                wgpu::CompareFunction::LessEqual,
+            )
+        });
+        let overlay_pipeline =
+            create_pipeline("Debug Draw Overlay Pipeline", wgpu::CompareFunction::Always);
+
+        let capacity = INITIAL_CAPACITY;
+        let vertex_buffer = create_vertex_buffer(device, capacity);
+
+        Self {
+            depth_tested_pipeline,
+            overlay_pipeline,
+            vertex_buffer,
+            capacity,
+            segments: Vec::new(),
+            num_depth_tested: 0,
+            num_vertices: 0,
+            last_prepare: Instant::now(),
+        }
+    }
+
+    /// Takes everything queued since the last call, drops shapes whose lifetime
+    /// ran out and uploads the rest. Call it once per frame before `draw`.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let now = Instant::now();
+        let dt = (now - self.last_prepare).as_secs_f32();
+        self.last_prepare = now;
+
+        // Segments kept from earlier frames age by the time since they were last drawn
+        self.segments.retain(|segment| segment.lifetime > dt);
+        for segment in &mut self.segments {
+            segment.lifetime -= dt;
+        }
+        QUEUE.with(|queued| self.segments.append(&mut queued.borrow_mut()));
+
+        let depth_tested = self.depth_tested_pipeline.is_some();
+        let (tested, overlay): (Vec<&Segment>, Vec<&Segment>) = self
+            .segments
+            .iter()
+            .partition(|segment| depth_tested && segment.depth_test);
+        let mut vertices = Vec::with_capacity(self.segments.len() * 2);
+        for segment in tested.iter().chain(overlay.iter()) {
+            let color = segment.color.into();
+            vertices.push(GizmoVertex {
+                position: segment.a.into(),
+                color,
+            });
+            vertices.push(GizmoVertex {
+                position: segment.b.into(),
+                color,
+            });
+        }
+
+        if vertices.len() > self.capacity {
+            self.capacity = vertices.len().next_power_of_two();
+            self.vertex_buffer = create_vertex_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.num_depth_tested = tested.len() as u32 * 2;
+        self.num_vertices = vertices.len() as u32;
+    }
+
+    /// Forgets every queued shape, including ones with time left.
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        QUEUE.with(|queued| queued.borrow_mut().clear());
+    }
+
+    /// Meant to go last in the pass, after the scene has filled the depth buffer.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        if self.num_vertices == 0 {
+            return;
+        }
+
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        if let Some(pipeline) = &self.depth_tested_pipeline {
+            render_pass.set_pipeline(pipeline);
+            render_pass.draw(0..self.num_depth_tested, 0..1);
+        }
+        render_pass.set_pipeline(&self.overlay_pipeline);
+        render_pass.draw(self.num_depth_tested..self.num_vertices, 0..1);
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Debug Draw Vertex Buffer"),
+        size: (capacity * std::mem::size_of::<GizmoVertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
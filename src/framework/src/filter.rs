@@ -0,0 +1,488 @@
+use std::str::FromStr;
+
+use anyhow::*;
+use wgpu::util::DeviceExt;
+
+use crate::texture::Texture;
+
+/// Largest radius a blur accepts. Each pass reads 2 * radius + 1 texels per pixel.
+pub const MAX_RADIUS: u32 = 64;
+const WORKGROUP_SIZE: u32 = 8;
+
+/// A square convolution kernel, stored row by row from the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    size: u32,
+    weights: Vec<f32>,
+}
+
+impl Kernel {
+    /// `size` must be 3 or 5 and `weights` must hold `size * size` values.
+    pub fn new(size: u32, weights: Vec<f32>) -> Result<Self> {
+        ensure!(
+            size == 3 || size == 5,
+            "Kernels must be 3x3 or 5x5, not {}x{}",
+            size,
+            size
+        );
+        ensure!(
+            weights.len() == (size * size) as usize,
+            "A {}x{} kernel needs {} weights, got {}",
+            size,
+            size,
+            size * size,
+            weights.len()
+        );
+        Ok(Self { size, weights })
+    }
+
+    /// Like `new`, but scales the weights to sum to one so the image keeps its brightness.
+    /// Kernels summing to zero, like most edge detectors, are left alone.
+    pub fn normalized(size: u32, weights: Vec<f32>) -> Result<Self> {
+        let mut kernel = Self::new(size, weights)?;
+        let sum: f32 = kernel.weights.iter().sum();
+        if sum.abs() > f32::EPSILON {
+            kernel.weights.iter_mut().for_each(|weight| *weight /= sum);
+        }
+        Ok(kernel)
+    }
+
+    pub fn sharpen() -> Self {
+        Self::new(3, vec![0.0, -1.0, 0.0, -1.0, 5.0, -1.0, 0.0, -1.0, 0.0]).unwrap()
+    }
+
+    pub fn emboss() -> Self {
+        Self::new(3, vec![-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0]).unwrap()
+    }
+
+    pub fn outline() -> Self {
+        Self::new(3, vec![-1.0, -1.0, -1.0, -1.0, 8.0, -1.0, -1.0, -1.0, -1.0]).unwrap()
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+}
+
+/// Parses 9 or 25 weights separated by commas or whitespace.
+impl FromStr for Kernel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let weights = s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|weight| !weight.is_empty())
+            .map(|weight| {
+                weight
+                    .parse()
+                    .with_context(|| format!("Invalid kernel weight {:?}", weight))
+            })
+            .collect::<Result<Vec<f32>>>()?;
+        let size = match weights.len() {
+            9 => 3,
+            25 => 5,
+            count => bail!("Kernels need 9 or 25 weights, got {}", count),
+        };
+        Self::new(size, weights)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// Gaussian blur reaching `radius` pixels out, with sigma at a third of the radius.
+    GaussianBlur {
+        radius: u32,
+    },
+    /// Averages the (2 * radius + 1)² pixels around each pixel.
+    BoxBlur {
+        radius: u32,
+    },
+    /// Magnitude of the luminance gradient, in grayscale.
+    Sobel,
+    Kernel(Kernel),
+}
+
+/// Parses the forms the image-filter tool takes: `gaussian:4`, `box:2`, `sobel`,
+/// `sharpen`, `emboss`, `outline` and `kernel:` followed by 9 or 25 weights.
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, argument) = match s.find(':') {
+            Some(colon) => (&s[..colon], Some(&s[colon + 1..])),
+            None => (s, None),
+        };
+        let radius = || -> Result<u32> {
+            let argument = argument.with_context(|| format!("{} needs a radius", name))?;
+            let radius = argument
+                .parse()
+                .with_context(|| format!("Invalid radius {:?}", argument))?;
+            ensure!(
+                radius <= MAX_RADIUS,
+                "Radius {} is larger than the maximum of {}",
+                radius,
+                MAX_RADIUS
+            );
+            Ok(radius)
+        };
+        Ok(match name {
+            "gaussian" => Filter::GaussianBlur { radius: radius()? },
+            "box" => Filter::BoxBlur { radius: radius()? },
+            "sobel" => Filter::Sobel,
+            "sharpen" => Filter::Kernel(Kernel::sharpen()),
+            "emboss" => Filter::Kernel(Kernel::emboss()),
+            "outline" => Filter::Kernel(Kernel::outline()),
+            "kernel" => Filter::Kernel(
+                argument
+                    .context("kernel needs its weights, like kernel:0,-1,0,-1,5,-1,0,-1,0")?
+                    .parse()?,
+            ),
+            _ => bail!("Unknown filter {:?}", name),
+        })
+    }
+}
+
+/// Normalized weights for a Gaussian blur, from -radius to radius.
+pub fn gaussian_weights(radius: u32) -> Vec<f32> {
+    let sigma = (radius as f32 / 3.0).max(0.5);
+    let radius = radius as i32;
+    let weights = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / sum).collect()
+}
+
+pub fn box_weights(radius: u32) -> Vec<f32> {
+    let count = 2 * radius + 1;
+    vec![1.0 / count as f32; count as usize]
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SeparableUniform {
+    direction: [i32; 2],
+    radius: i32,
+    _padding: i32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct KernelUniform {
+    size: i32,
+    gradient: i32,
+    _padding: [i32; 2],
+}
+
+/// Compute based image filters. Each filter reads any filterable 2D texture and writes a
+/// new `FORMAT` texture, which can be sampled by later passes, filtered again or read back
+/// with `texture::read_to_image`.
+pub struct ImageFilters {
+    bind_group_layout: wgpu::BindGroupLayout,
+    separable_pipeline: wgpu::ComputePipeline,
+    kernel_pipeline: wgpu::ComputePipeline,
+}
+
+impl ImageFilters {
+    /// Linear and wide enough that chained filters don't band.
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                        filtering: true,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: Self::FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                buffer_entry(3, wgpu::BufferBindingType::Storage { read_only: true }),
+                buffer_entry(4, wgpu::BufferBindingType::Uniform),
+            ],
+            label: Some("filter_bind_group_layout"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Filter Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let separable_module =
+            device.create_shader_module(&wgpu::include_spirv!("filter_separable.comp.spv"));
+        let separable_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Separable Filter Pipeline"),
+            layout: Some(&layout),
+            module: &separable_module,
+            entry_point: "main",
+        });
+        let kernel_module =
+            device.create_shader_module(&wgpu::include_spirv!("filter_kernel.comp.spv"));
+        let kernel_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Kernel Filter Pipeline"),
+            layout: Some(&layout),
+            module: &kernel_module,
+            entry_point: "main",
+        });
+
+        Self {
+            bind_group_layout,
+            separable_pipeline,
+            kernel_pipeline,
+        }
+    }
+
+    /// A texture filters can write into. Create these up front to reuse them every frame.
+    pub fn create_target(device: &wgpu::Device, width: u32, height: u32, label: &str) -> Texture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsage::STORAGE
+                | wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        Texture {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Filters `input` into a newly created texture of the same size.
+    pub fn apply(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        width: u32,
+        height: u32,
+        filter: &Filter,
+    ) -> Texture {
+        let output = Self::create_target(device, width, height, "Filter Output");
+        self.apply_to(device, encoder, input, &output, width, height, filter);
+        output
+    }
+
+    /// Filters `input` into `output`, which must come from `create_target` with the same
+    /// size. Separable filters allocate an intermediate texture for their first pass.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_to(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        output: &Texture,
+        width: u32,
+        height: u32,
+        filter: &Filter,
+    ) {
+        match filter {
+            Filter::GaussianBlur { radius } => {
+                let weights = gaussian_weights((*radius).min(MAX_RADIUS));
+                self.separable(device, encoder, input, output, width, height, &weights);
+            }
+            Filter::BoxBlur { radius } => {
+                let weights = box_weights((*radius).min(MAX_RADIUS));
+                self.separable(device, encoder, input, output, width, height, &weights);
+            }
+            Filter::Sobel => {
+                #[rustfmt::skip]
+                let weights = [
+                    -1.0, 0.0, 1.0,
+                    -2.0, 0.0, 2.0,
+                    -1.0, 0.0, 1.0,
+
+                    -1.0, -2.0, -1.0,
+                     0.0,  0.0,  0.0,
+                     1.0,  2.0,  1.0,
+                ];
+                let uniform = KernelUniform {
+                    size: 3,
+                    gradient: 1,
+                    _padding: [0; 2],
+                };
+                self.dispatch(
+                    device,
+                    encoder,
+                    &self.kernel_pipeline,
+                    input,
+                    output,
+                    (width, height),
+                    &weights,
+                    bytemuck::bytes_of(&uniform),
+                );
+            }
+            Filter::Kernel(kernel) => {
+                let uniform = KernelUniform {
+                    size: kernel.size as i32,
+                    gradient: 0,
+                    _padding: [0; 2],
+                };
+                self.dispatch(
+                    device,
+                    encoder,
+                    &self.kernel_pipeline,
+                    input,
+                    output,
+                    (width, height),
+                    &kernel.weights,
+                    bytemuck::bytes_of(&uniform),
+                );
+            }
+        }
+    }
+
+    /// Runs `weights` horizontally into an intermediate texture, then vertically into
+    /// `output`.
+    #[allow(clippy::too_many_arguments)]
+    fn separable(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        output: &Texture,
+        width: u32,
+        height: u32,
+        weights: &[f32],
+    ) {
+        let intermediate = Self::create_target(device, width, height, "Filter Intermediate");
+        let radius = (weights.len() / 2) as i32;
+        for (direction, source, target) in [
+            ([1, 0], input, &intermediate),
+            ([0, 1], &intermediate, output),
+        ]
+        .iter()
+        {
+            let uniform = SeparableUniform {
+                direction: *direction,
+                radius,
+                _padding: 0,
+            };
+            self.dispatch(
+                device,
+                encoder,
+                &self.separable_pipeline,
+                source,
+                target,
+                (width, height),
+                weights,
+                bytemuck::bytes_of(&uniform),
+            );
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn dispatch(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        input: &Texture,
+        output: &Texture,
+        (width, height): (u32, u32),
+        weights: &[f32],
+        uniform: &[u8],
+    ) {
+        let weight_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Filter Weight Buffer"),
+            contents: bytemuck::cast_slice(weights),
+            usage: wgpu::BufferUsage::STORAGE,
+        });
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Filter Uniform Buffer"),
+            contents: uniform,
+            usage: wgpu::BufferUsage::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&input.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&input.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&output.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: weight_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("filter_bind_group"),
+        });
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Filter Pass"),
+        });
+        compute_pass.set_pipeline(pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.dispatch(
+            width.div_ceil(WORKGROUP_SIZE),
+            height.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    }
+}
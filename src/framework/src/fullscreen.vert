@@ -0,0 +1,11 @@
+#version 450
+
+layout(location=0) out vec2 v_tex_coords;
+
+// A single triangle that covers the whole target, no vertex buffer needed.
+// Draw it with `render_pass.draw(0..3, 0..1)`.
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    v_tex_coords = uv;
+    gl_Position = vec4(uv * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.0, 1.0);
+}
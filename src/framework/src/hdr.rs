@@ -0,0 +1,462 @@
+use wgpu::util::DeviceExt;
+
+use crate::fullscreen::{
+    create_fullscreen_pipeline, fullscreen_pass, sampler_entry, texture_entry, uniform_entry,
+    vertex_module,
+};
+
+/// Format of the HDR render target, scene pipelines drawn through `HdrPipeline` must use it.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Number of times the bloom halves its resolution, more levels spread the glow further.
+const BLOOM_LEVELS: usize = 5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// Clamps at 1.0, handy for seeing what the other operators do.
+    None,
+    Reinhard,
+    Aces,
+}
+
+impl Tonemapper {
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::Reinhard,
+            Self::Reinhard => Self::Aces,
+            Self::Aces => Self::None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct HdrSettings {
+    pub tonemapper: Tonemapper,
+    /// Scene color is multiplied by this before tonemapping.
+    pub exposure: f32,
+    pub bloom: bool,
+    /// Brightness above which pixels start to bloom.
+    pub bloom_threshold: f32,
+    /// Width of the soft transition below the threshold.
+    pub bloom_knee: f32,
+    /// How much of the blurred highlights is added back onto the scene.
+    pub bloom_intensity: f32,
+    /// Spread of the upsampling filter in texels.
+    pub bloom_radius: f32,
+}
+
+impl Default for HdrSettings {
+    fn default() -> Self {
+        Self {
+            tonemapper: Tonemapper::Aces,
+            exposure: 1.0,
+            bloom: true,
+            bloom_threshold: 1.0,
+            bloom_knee: 0.5,
+            bloom_intensity: 0.3,
+            bloom_radius: 1.0,
+        }
+    }
+}
+
+/// Mirrors the `Bloom` block in the bloom shaders.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BloomUniform {
+    threshold: f32,
+    knee: f32,
+    radius: f32,
+    _padding: f32,
+}
+
+/// Mirrors the `Tonemap` block in tonemap.frag.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TonemapUniform {
+    exposure: f32,
+    tonemapper: u32,
+    bloom_intensity: f32,
+    _padding: f32,
+}
+
+impl From<&HdrSettings> for BloomUniform {
+    fn from(settings: &HdrSettings) -> Self {
+        Self {
+            threshold: settings.bloom_threshold,
+            knee: settings.bloom_knee,
+            radius: settings.bloom_radius,
+            _padding: 0.0,
+        }
+    }
+}
+
+impl From<&HdrSettings> for TonemapUniform {
+    fn from(settings: &HdrSettings) -> Self {
+        Self {
+            exposure: settings.exposure,
+            tonemapper: settings.tonemapper as u32,
+            bloom_intensity: if settings.bloom {
+                settings.bloom_intensity
+            } else {
+                0.0
+            },
+            _padding: 0.0,
+        }
+    }
+}
+
+/// Render target textures that change with the window size.
+struct Targets {
+    #[allow(dead_code)]
+    hdr_texture: wgpu::Texture,
+    hdr_view: wgpu::TextureView,
+    /// Each level is half the size of the one before, level 0 is half the window.
+    #[allow(dead_code)]
+    bloom_textures: Vec<wgpu::Texture>,
+    bloom_views: Vec<wgpu::TextureView>,
+    /// Samples the HDR target, written into bloom level 0.
+    threshold_bind_group: wgpu::BindGroup,
+    /// `level_bind_groups[i]` samples bloom level `i`.
+    level_bind_groups: Vec<wgpu::BindGroup>,
+    tonemap_bind_group: wgpu::BindGroup,
+}
+
+/// Renders the scene into an `Rgba16Float` target so lighting can exceed 1.0, then adds
+/// bloom and tonemaps the result into the swap chain.
+///
+/// Draw the scene into `view()` with pipelines targeting `HDR_FORMAT`, then call `process`
+/// with the swap chain frame. Call `resize` whenever the swap chain is recreated.
+pub struct HdrPipeline {
+    settings: HdrSettings,
+    sampler: wgpu::Sampler,
+    bloom_buffer: wgpu::Buffer,
+    tonemap_buffer: wgpu::Buffer,
+    bloom_bind_group_layout: wgpu::BindGroupLayout,
+    tonemap_bind_group_layout: wgpu::BindGroupLayout,
+    threshold_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    upsample_pipeline: wgpu::RenderPipeline,
+    tonemap_pipeline: wgpu::RenderPipeline,
+    targets: Targets,
+}
+
+impl HdrPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        settings: HdrSettings,
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("hdr_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bloom_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bloom Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[BloomUniform::from(&settings)]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let tonemap_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tonemap Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[TonemapUniform::from(&settings)]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bloom_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[texture_entry(0), sampler_entry(1), uniform_entry(2)],
+                label: Some("bloom_bind_group_layout"),
+            });
+        let tonemap_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    texture_entry(0),
+                    sampler_entry(1),
+                    texture_entry(2),
+                    uniform_entry(3),
+                ],
+                label: Some("tonemap_bind_group_layout"),
+            });
+
+        let vs_module = vertex_module(device);
+        let threshold_module =
+            device.create_shader_module(&wgpu::include_spirv!("bloom_threshold.frag.spv"));
+        let downsample_module =
+            device.create_shader_module(&wgpu::include_spirv!("bloom_downsample.frag.spv"));
+        let upsample_module =
+            device.create_shader_module(&wgpu::include_spirv!("bloom_upsample.frag.spv"));
+        let tonemap_module = device.create_shader_module(&wgpu::include_spirv!("tonemap.frag.spv"));
+
+        let bloom_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[&bloom_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let tonemap_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tonemap Pipeline Layout"),
+            bind_group_layouts: &[&tonemap_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let threshold_pipeline = create_fullscreen_pipeline(
+            device,
+            "Bloom Threshold Pipeline",
+            &bloom_layout,
+            &vs_module,
+            &threshold_module,
+            HDR_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+        let downsample_pipeline = create_fullscreen_pipeline(
+            device,
+            "Bloom Downsample Pipeline",
+            &bloom_layout,
+            &vs_module,
+            &downsample_module,
+            HDR_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+        // Each upsampled level is added on top of the next larger one.
+        let upsample_pipeline = create_fullscreen_pipeline(
+            device,
+            "Bloom Upsample Pipeline",
+            &bloom_layout,
+            &vs_module,
+            &upsample_module,
+            HDR_FORMAT,
+            wgpu::BlendState {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        );
+        let tonemap_pipeline = create_fullscreen_pipeline(
+            device,
+            "Tonemap Pipeline",
+            &tonemap_layout,
+            &vs_module,
+            &tonemap_module,
+            sc_desc.format,
+            wgpu::BlendState::REPLACE,
+        );
+
+        let targets = Targets::new(
+            device,
+            sc_desc.width,
+            sc_desc.height,
+            &sampler,
+            &bloom_buffer,
+            &tonemap_buffer,
+            &bloom_bind_group_layout,
+            &tonemap_bind_group_layout,
+        );
+
+        Self {
+            settings,
+            sampler,
+            bloom_buffer,
+            tonemap_buffer,
+            bloom_bind_group_layout,
+            tonemap_bind_group_layout,
+            threshold_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            tonemap_pipeline,
+            targets,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.targets = Targets::new(
+            device,
+            width,
+            height,
+            &self.sampler,
+            &self.bloom_buffer,
+            &self.tonemap_buffer,
+            &self.bloom_bind_group_layout,
+            &self.tonemap_bind_group_layout,
+        );
+    }
+
+    /// The HDR target to draw the scene into.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.targets.hdr_view
+    }
+
+    pub fn settings(&self) -> &HdrSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: HdrSettings) {
+        self.settings = settings;
+        queue.write_buffer(
+            &self.bloom_buffer,
+            0,
+            bytemuck::cast_slice(&[BloomUniform::from(&settings)]),
+        );
+        queue.write_buffer(
+            &self.tonemap_buffer,
+            0,
+            bytemuck::cast_slice(&[TonemapUniform::from(&settings)]),
+        );
+    }
+
+    /// Records the bloom passes and the tonemapping pass that writes to `output`.
+    pub fn process(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        if self.settings.bloom {
+            let views = &self.targets.bloom_views;
+
+            fullscreen_pass(
+                encoder,
+                "Bloom Threshold Pass",
+                &views[0],
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                &self.threshold_pipeline,
+                &self.targets.threshold_bind_group,
+            );
+            // Each level reads the one before it.
+            for (view, source) in views[1..].iter().zip(&self.targets.level_bind_groups) {
+                fullscreen_pass(
+                    encoder,
+                    "Bloom Downsample Pass",
+                    view,
+                    wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    &self.downsample_pipeline,
+                    source,
+                );
+            }
+            for level in (0..BLOOM_LEVELS - 1).rev() {
+                fullscreen_pass(
+                    encoder,
+                    "Bloom Upsample Pass",
+                    &views[level],
+                    wgpu::LoadOp::Load,
+                    &self.upsample_pipeline,
+                    &self.targets.level_bind_groups[level + 1],
+                );
+            }
+        }
+
+        fullscreen_pass(
+            encoder,
+            "Tonemap Pass",
+            output,
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            &self.tonemap_pipeline,
+            &self.targets.tonemap_bind_group,
+        );
+    }
+}
+
+impl Targets {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sampler: &wgpu::Sampler,
+        bloom_buffer: &wgpu::Buffer,
+        tonemap_buffer: &wgpu::Buffer,
+        bloom_bind_group_layout: &wgpu::BindGroupLayout,
+        tonemap_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let (hdr_texture, hdr_view) = create_target(device, "hdr_texture", width, height);
+
+        let (bloom_textures, bloom_views): (Vec<_>, Vec<_>) = (0..BLOOM_LEVELS)
+            .map(|level| {
+                create_target(
+                    device,
+                    "bloom_texture",
+                    (width >> (level + 1)).max(1),
+                    (height >> (level + 1)).max(1),
+                )
+            })
+            .unzip();
+
+        let bloom_bind_group = |view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: bloom_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: bloom_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("bloom_bind_group"),
+            })
+        };
+        let threshold_bind_group = bloom_bind_group(&hdr_view);
+        let level_bind_groups = bloom_views.iter().map(bloom_bind_group).collect();
+
+        let tonemap_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: tonemap_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&hdr_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&bloom_views[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: tonemap_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("tonemap_bind_group"),
+        });
+
+        Self {
+            hdr_texture,
+            hdr_view,
+            bloom_textures,
+            bloom_views,
+            threshold_bind_group,
+            level_bind_groups,
+            tonemap_bind_group,
+        }
+    }
+}
+
+fn create_target(
+    device: &wgpu::Device,
+    label: &str,
+    width: u32,
+    height: u32,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: HDR_FORMAT,
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
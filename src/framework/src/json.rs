@@ -0,0 +1,87 @@
+//! Reading the manifests and level files the framework loads with serde_json, plus
+//! lookups that fail with the name of the missing or mistyped field.
+
+use anyhow::*;
+
+pub use serde_json::{json, Value};
+
+/// Parses a whole document. serde_json limits nesting, so hostile files get an error
+/// rather than overflowing the stack.
+pub fn parse(text: &str) -> Result<Value> {
+    serde_json::from_str(text).context("Invalid JSON")
+}
+
+/// Required fields of an object, where a missing or mistyped key is an error naming it.
+pub trait Fields {
+    fn field(&self, key: &str) -> Result<&Value>;
+
+    fn field_f64(&self, key: &str) -> Result<f64> {
+        self.field(key)?
+            .as_f64()
+            .with_context(|| format!("Field {:?} should be a number", key))
+    }
+
+    fn field_u32(&self, key: &str) -> Result<u32> {
+        self.field(key)?
+            .as_u64()
+            .filter(|value| *value <= u32::MAX as u64)
+            .map(|value| value as u32)
+            .with_context(|| format!("Field {:?} should be a whole number", key))
+    }
+
+    fn field_str(&self, key: &str) -> Result<&str> {
+        self.field(key)?
+            .as_str()
+            .with_context(|| format!("Field {:?} should be a string", key))
+    }
+
+    fn field_array(&self, key: &str) -> Result<&[Value]> {
+        self.field(key)?
+            .as_array()
+            .map(Vec::as_slice)
+            .with_context(|| format!("Field {:?} should be an array", key))
+    }
+}
+
+impl Fields for Value {
+    fn field(&self, key: &str) -> Result<&Value> {
+        self.get(key)
+            .with_context(|| format!("Missing field {:?}", key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let value = parse(r#"{"name": "grass", "size": 16, "scale": 0.5, "tags": []}"#).unwrap();
+        assert_eq!(value.field_str("name").unwrap(), "grass");
+        assert_eq!(value.field_u32("size").unwrap(), 16);
+        assert_eq!(value.field_f64("scale").unwrap(), 0.5);
+        assert!(value.field_array("tags").unwrap().is_empty());
+
+        let error = value.field_u32("scale").unwrap_err().to_string();
+        assert_eq!(error, "Field \"scale\" should be a whole number");
+        let error = value.field("missing").unwrap_err().to_string();
+        assert_eq!(error, "Missing field \"missing\"");
+    }
+
+    #[test]
+    fn malformed() {
+        for text in &[
+            "",
+            "{",
+            r#"{"a": 1,}"#,
+            r#""\x""#,
+            r#""\ud83dA""#,
+            r#""\ud83d\u0041""#,
+            "[".repeat(100_000).as_str(),
+        ] {
+            assert!(parse(text).is_err(), "{:?} parsed", text);
+        }
+        // Surrogate pairs still make one character
+        assert_eq!(parse(r#""\ud83d\ude00""#).unwrap(), "\u{1f600}");
+    }
+}
@@ -0,0 +1,28 @@
+pub mod animation;
+pub mod atlas;
+pub mod camera;
+pub mod debug_draw;
+pub mod filter;
+pub mod fullscreen;
+pub mod hdr;
+pub mod instance;
+pub mod json;
+pub mod light;
+pub mod material;
+pub mod mesh;
+pub mod msaa;
+pub mod particles;
+pub mod post;
+pub mod render_target;
+pub mod shadow;
+pub mod skybox;
+pub mod sprite;
+pub mod svg;
+pub mod text;
+pub mod texture;
+pub mod tiled;
+pub mod tilemap;
+pub mod ui;
+pub mod vector;
+pub mod wireframe;
+pub mod xml;
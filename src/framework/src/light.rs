@@ -0,0 +1,424 @@
+use cgmath::prelude::*;
+use cgmath::Vector3;
+use wgpu::util::DeviceExt;
+
+pub const MAX_POINT_LIGHTS: usize = 8;
+pub const MAX_DIRECTIONAL_LIGHTS: usize = 4;
+pub const MAX_SPOT_LIGHTS: usize = 8;
+
+#[derive(Copy, Clone, Debug)]
+pub struct PointLight {
+    pub position: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    /// Distance at which the light has faded out completely.
+    pub range: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DirectionalLight {
+    /// Direction the light travels in, it does not need to be normalized.
+    pub direction: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SpotLight {
+    pub position: Vector3<f32>,
+    pub direction: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    pub range: f32,
+    /// Half angle of the fully lit part of the cone.
+    pub inner_angle: cgmath::Rad<f32>,
+    /// Half angle at which the cone has faded out completely.
+    pub outer_angle: cgmath::Rad<f32>,
+}
+
+/// Every light in a scene. Lights past the `MAX_*` limits are ignored by the shaders.
+#[derive(Clone, Debug)]
+pub struct Lights {
+    pub point_lights: Vec<PointLight>,
+    pub directional_lights: Vec<DirectionalLight>,
+    pub spot_lights: Vec<SpotLight>,
+    pub ambient_color: Vector3<f32>,
+    pub ambient_strength: f32,
+}
+
+impl Lights {
+    pub fn new() -> Self {
+        Self {
+            point_lights: Vec::new(),
+            directional_lights: Vec::new(),
+            spot_lights: Vec::new(),
+            ambient_color: Vector3::new(1.0, 1.0, 1.0),
+            ambient_strength: 0.05,
+        }
+    }
+
+    pub fn to_uniform(&self) -> LightsUniform {
+        let mut uniform: LightsUniform = bytemuck::Zeroable::zeroed();
+
+        for (raw, light) in uniform.point_lights.iter_mut().zip(&self.point_lights) {
+            *raw = PointLightRaw {
+                position: light.position.into(),
+                range: light.range,
+                color: light.color.into(),
+                intensity: light.intensity,
+            };
+        }
+        for (raw, light) in uniform
+            .directional_lights
+            .iter_mut()
+            .zip(&self.directional_lights)
+        {
+            *raw = DirectionalLightRaw {
+                direction: light.direction.normalize().into(),
+                intensity: light.intensity,
+                color: light.color.into(),
+                _padding: 0.0,
+            };
+        }
+        for (raw, light) in uniform.spot_lights.iter_mut().zip(&self.spot_lights) {
+            *raw = SpotLightRaw {
+                position: light.position.into(),
+                range: light.range,
+                direction: light.direction.normalize().into(),
+                intensity: light.intensity,
+                color: light.color.into(),
+                cos_inner: light.inner_angle.cos(),
+                cos_outer: light.outer_angle.cos(),
+                _padding: [0.0; 3],
+            };
+        }
+
+        uniform.ambient_color = self.ambient_color.into();
+        uniform.ambient_strength = self.ambient_strength;
+        uniform.counts = [
+            self.point_lights.len().min(MAX_POINT_LIGHTS) as u32,
+            self.directional_lights.len().min(MAX_DIRECTIONAL_LIGHTS) as u32,
+            self.spot_lights.len().min(MAX_SPOT_LIGHTS) as u32,
+            0,
+        ];
+        uniform
+    }
+}
+
+impl Default for Lights {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The raw structs mirror the std140 layout of the `Lights` block in the shaders,
+// every member is packed so that each struct is a multiple of 16 bytes.
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLightRaw {
+    position: [f32; 3],
+    range: f32,
+    color: [f32; 3],
+    intensity: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DirectionalLightRaw {
+    direction: [f32; 3],
+    intensity: f32,
+    color: [f32; 3],
+    _padding: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpotLightRaw {
+    position: [f32; 3],
+    range: f32,
+    direction: [f32; 3],
+    intensity: f32,
+    color: [f32; 3],
+    cos_inner: f32,
+    cos_outer: f32,
+    _padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightsUniform {
+    point_lights: [PointLightRaw; MAX_POINT_LIGHTS],
+    directional_lights: [DirectionalLightRaw; MAX_DIRECTIONAL_LIGHTS],
+    spot_lights: [SpotLightRaw; MAX_SPOT_LIGHTS],
+    ambient_color: [f32; 3],
+    ambient_strength: f32,
+    /// Number of point, directional and spot lights in use, the last slot is padding.
+    counts: [u32; 4],
+}
+
+/// The light uniform buffer and a bind group exposing it to the fragment stage at binding 0.
+pub struct LightBinding {
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl LightBinding {
+    pub fn new(device: &wgpu::Device, lights: &Lights) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::cast_slice(&[lights.to_uniform()]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("light_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("light_bind_group"),
+        });
+
+        Self {
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, lights: &Lights) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[lights.to_uniform()]),
+        );
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct GizmoVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl GizmoVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GizmoVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float3,
+                },
+            ],
+        }
+    }
+}
+
+const GIZMO_SIZE: f32 = 0.15;
+const OCTAHEDRON_VERTICES: usize = 24;
+const MAX_GIZMO_VERTICES: usize = MAX_POINT_LIGHTS * OCTAHEDRON_VERTICES
+    + MAX_SPOT_LIGHTS * (OCTAHEDRON_VERTICES + 16)
+    + MAX_DIRECTIONAL_LIGHTS * (OCTAHEDRON_VERTICES + 2);
+
+/// Debug view of a `Lights` set drawn as unlit lines: a small octahedron at every
+/// light, the outer cone of spot lights and an arrow for each directional light.
+pub struct LightGizmos {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+}
+
+impl LightGizmos {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        sample_count: u32,
+    ) -> Self {
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("light_gizmo.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("light_gizmo.frag.spv"));
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Light Gizmo Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Light Gizmo Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vs_module,
+                entry_point: "main",
+                buffers: &[GizmoVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fs_module,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format: color_format,
+                    alpha_blend: wgpu::BlendState::REPLACE,
+                    color_blend: wgpu::BlendState::REPLACE,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+                clamp_depth: false,
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Gizmo Vertex Buffer"),
+            size: (MAX_GIZMO_VERTICES * std::mem::size_of::<GizmoVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            vertex_buffer,
+            num_vertices: 0,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, lights: &Lights) {
+        let mut vertices = Vec::with_capacity(MAX_GIZMO_VERTICES);
+
+        for light in lights.point_lights.iter().take(MAX_POINT_LIGHTS) {
+            push_octahedron(&mut vertices, light.position, light.color);
+        }
+
+        for light in lights.spot_lights.iter().take(MAX_SPOT_LIGHTS) {
+            push_octahedron(&mut vertices, light.position, light.color);
+
+            let (right, up) = perpendicular_axes(light.direction);
+            let length = light.range.min(2.0);
+            let radius = length * light.outer_angle.tan();
+            let center = light.position + light.direction.normalize() * length;
+            let rim = [
+                center + right * radius,
+                center + up * radius,
+                center - right * radius,
+                center - up * radius,
+            ];
+            for (i, point) in rim.iter().enumerate() {
+                push_line(&mut vertices, light.position, *point, light.color);
+                push_line(&mut vertices, *point, rim[(i + 1) % rim.len()], light.color);
+            }
+        }
+
+        for light in lights
+            .directional_lights
+            .iter()
+            .take(MAX_DIRECTIONAL_LIGHTS)
+        {
+            // Directional lights have no position, so anchor the gizmo upstream of the origin.
+            let direction = light.direction.normalize();
+            let anchor = -direction * 8.0;
+            push_octahedron(&mut vertices, anchor, light.color);
+            push_line(&mut vertices, anchor, anchor + direction * 2.0, light.color);
+        }
+
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.num_vertices = vertices.len() as u32;
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, 0..1);
+    }
+}
+
+pub(crate) fn perpendicular_axes(direction: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let direction = direction.normalize();
+    let reference = if direction.y.abs() < 0.99 {
+        Vector3::unit_y()
+    } else {
+        Vector3::unit_x()
+    };
+    let right = direction.cross(reference).normalize();
+    let up = right.cross(direction);
+    (right, up)
+}
+
+fn push_line(
+    vertices: &mut Vec<GizmoVertex>,
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    color: Vector3<f32>,
+) {
+    let color = color.into();
+    vertices.push(GizmoVertex {
+        position: a.into(),
+        color,
+    });
+    vertices.push(GizmoVertex {
+        position: b.into(),
+        color,
+    });
+}
+
+fn push_octahedron(vertices: &mut Vec<GizmoVertex>, center: Vector3<f32>, color: Vector3<f32>) {
+    let top = center + Vector3::unit_y() * GIZMO_SIZE;
+    let bottom = center - Vector3::unit_y() * GIZMO_SIZE;
+    let ring = [
+        center + Vector3::unit_x() * GIZMO_SIZE,
+        center + Vector3::unit_z() * GIZMO_SIZE,
+        center - Vector3::unit_x() * GIZMO_SIZE,
+        center - Vector3::unit_z() * GIZMO_SIZE,
+    ];
+    for (i, point) in ring.iter().enumerate() {
+        push_line(vertices, *point, ring[(i + 1) % ring.len()], color);
+        push_line(vertices, *point, top, color);
+        push_line(vertices, *point, bottom, color);
+    }
+}
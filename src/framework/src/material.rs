@@ -0,0 +1,214 @@
+use wgpu::util::DeviceExt;
+
+use crate::texture::Texture;
+
+/// Scalar factors of a glTF metallic-roughness material. Each one is multiplied with the
+/// matching texture, so a material without maps is described by its factors alone.
+#[derive(Copy, Clone, Debug)]
+pub struct MaterialFactors {
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    /// Scales the X and Y of the tangent space normal map sample.
+    pub normal_scale: f32,
+    /// How much of the occlusion map is applied, 0 disables it.
+    pub occlusion_strength: f32,
+}
+
+impl Default for MaterialFactors {
+    /// The defaults glTF specifies for a material that omits a factor.
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            metallic: 1.0,
+            roughness: 1.0,
+            emissive: [0.0; 3],
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaterialUniform {
+    base_color_factor: [f32; 4],
+    emissive_factor: [f32; 3],
+    metallic_factor: f32,
+    roughness_factor: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    _padding: f32,
+}
+
+impl From<&MaterialFactors> for MaterialUniform {
+    fn from(factors: &MaterialFactors) -> Self {
+        Self {
+            base_color_factor: factors.base_color,
+            emissive_factor: factors.emissive,
+            metallic_factor: factors.metallic,
+            roughness_factor: factors.roughness,
+            normal_scale: factors.normal_scale,
+            occlusion_strength: factors.occlusion_strength,
+            _padding: 0.0,
+        }
+    }
+}
+
+/// The texture maps of a material. Color maps (base color, emissive) should be loaded as
+/// sRGB, the rest with `is_normal_map` set so they stay linear. Metallic-roughness follows
+/// glTF and reads roughness from green and metallic from blue; occlusion reads red.
+#[derive(Default)]
+pub struct MaterialMaps {
+    pub base_color: Option<Texture>,
+    pub metallic_roughness: Option<Texture>,
+    pub normal: Option<Texture>,
+    pub occlusion: Option<Texture>,
+    pub emissive: Option<Texture>,
+}
+
+/// 1x1 textures bound in place of any map a material doesn't have. Each one is the
+/// identity for its map, leaving the factor as the only input.
+pub struct DefaultTextures {
+    pub white: Texture,
+    pub white_linear: Texture,
+    pub flat_normal: Texture,
+}
+
+impl DefaultTextures {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self {
+            white: Texture::from_color(device, queue, [255; 4], "default_white", false),
+            white_linear: Texture::from_color(
+                device,
+                queue,
+                [255; 4],
+                "default_white_linear",
+                true,
+            ),
+            flat_normal: Texture::from_color(
+                device,
+                queue,
+                [128, 128, 255, 255],
+                "default_flat_normal",
+                true,
+            ),
+        }
+    }
+}
+
+pub struct Material {
+    pub name: String,
+    pub factors: MaterialFactors,
+    pub maps: MaterialMaps,
+    uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Material {
+    /// Binding 0 holds the factors, followed by a texture and sampler pair for base color,
+    /// metallic-roughness, normal, occlusion and emissive in that order.
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let mut entries = vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }];
+        for map in 0..5 {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 1 + map * 2,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 + map * 2,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Sampler {
+                    comparison: false,
+                    filtering: true,
+                },
+                count: None,
+            });
+        }
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("material_bind_group_layout"),
+        })
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        defaults: &DefaultTextures,
+        name: &str,
+        factors: MaterialFactors,
+        maps: MaterialMaps,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Material Buffer", name)),
+            contents: bytemuck::cast_slice(&[MaterialUniform::from(&factors)]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let textures = [
+            maps.base_color.as_ref().unwrap_or(&defaults.white),
+            maps.metallic_roughness
+                .as_ref()
+                .unwrap_or(&defaults.white_linear),
+            maps.normal.as_ref().unwrap_or(&defaults.flat_normal),
+            maps.occlusion.as_ref().unwrap_or(&defaults.white_linear),
+            maps.emissive.as_ref().unwrap_or(&defaults.white),
+        ];
+
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniform_buffer.as_entire_binding(),
+        }];
+        for (map, texture) in textures.iter().enumerate() {
+            let map = map as u32;
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1 + map * 2,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 + map * 2,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            });
+        }
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some(&format!("{} Material Bind Group", name)),
+        });
+
+        Self {
+            name: name.to_string(),
+            factors,
+            maps,
+            uniform_buffer,
+            bind_group,
+        }
+    }
+
+    /// Uploads `factors` after they have been changed.
+    pub fn write_factors(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[MaterialUniform::from(&self.factors)]),
+        );
+    }
+}
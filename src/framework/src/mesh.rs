@@ -0,0 +1,484 @@
+use std::collections::HashSet;
+
+use anyhow::*;
+use cgmath::prelude::*;
+use cgmath::{Vector2, Vector3};
+use wgpu::util::DeviceExt;
+
+/// Vertex layout for lit meshes: tutorial5's `Vertex` plus a per-vertex normal and tangent.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ModelVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    /// MikkTSpace style tangent, `w` holds the handedness of the tangent frame so the
+    /// bitangent is `cross(normal, tangent.xyz) * tangent.w`.
+    pub tangent: [f32; 4],
+}
+
+impl ModelVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float4,
+                },
+            ],
+        }
+    }
+}
+
+/// The tutorial5 pentagon, counter-clockwise from the top: A, B, C, D, E.
+const PENTAGON: [([f32; 2], [f32; 2]); 5] = [
+    ([-0.0868241, 0.49240386], [0.4131759, 0.00759614]),
+    ([-0.49513406, 0.06958647], [0.0048659444, 0.43041354]),
+    ([-0.21918549, -0.44939706], [0.28081453, 0.949_397]),
+    ([0.35966998, -0.3473291], [0.85967, 0.847_329_14]),
+    ([0.44147372, 0.2347359], [0.9414737, 0.2652641]),
+];
+
+/// CPU side geometry. Procedural constructors produce counter-clockwise front faces.
+pub struct Mesh {
+    pub name: String,
+    pub vertices: Vec<ModelVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    fn empty(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    /// Appends a quad, flipping the winding if needed so it faces along `normal`.
+    fn push_quad(
+        &mut self,
+        corners: [Vector3<f32>; 4],
+        normal: Vector3<f32>,
+        tex_coords: [[f32; 2]; 4],
+    ) {
+        let base = self.vertices.len() as u32;
+        for (corner, uv) in corners.iter().zip(tex_coords.iter()) {
+            self.vertices.push(ModelVertex {
+                position: (*corner).into(),
+                tex_coords: *uv,
+                normal: normal.into(),
+                tangent: [0.0; 4],
+            });
+        }
+
+        let face_normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+        if face_normal.dot(normal) >= 0.0 {
+            self.indices
+                .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        } else {
+            self.indices
+                .extend_from_slice(&[base, base + 2, base + 1, base, base + 3, base + 2]);
+        }
+    }
+
+    pub fn cube(size: f32) -> Self {
+        let half = size * 0.5;
+        // (normal, u axis, v axis) with u x v == normal
+        let faces = [
+            (Vector3::unit_x(), -Vector3::unit_z(), Vector3::unit_y()),
+            (-Vector3::unit_x(), Vector3::unit_z(), Vector3::unit_y()),
+            (Vector3::unit_y(), Vector3::unit_x(), -Vector3::unit_z()),
+            (-Vector3::unit_y(), Vector3::unit_x(), Vector3::unit_z()),
+            (Vector3::unit_z(), Vector3::unit_x(), Vector3::unit_y()),
+            (-Vector3::unit_z(), -Vector3::unit_x(), Vector3::unit_y()),
+        ];
+
+        let mut mesh = Self::empty("cube");
+        for (normal, u, v) in faces.iter() {
+            let center = normal * half;
+            let (u, v) = (u * half, v * half);
+            mesh.push_quad(
+                [
+                    center - u - v,
+                    center + u - v,
+                    center + u + v,
+                    center - u + v,
+                ],
+                *normal,
+                [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+            );
+        }
+        mesh.generate_tangents();
+        mesh
+    }
+
+    /// A square on the XZ plane facing +Y. `uv_repeat` tiles the texture across it.
+    pub fn plane(size: f32, uv_repeat: f32) -> Self {
+        let half = size * 0.5;
+        let (u, v) = (Vector3::unit_x() * half, -Vector3::unit_z() * half);
+        let mut mesh = Self::empty("plane");
+        mesh.push_quad(
+            [-u - v, u - v, u + v, -u + v],
+            Vector3::unit_y(),
+            [
+                [0.0, uv_repeat],
+                [uv_repeat, uv_repeat],
+                [uv_repeat, 0.0],
+                [0.0, 0.0],
+            ],
+        );
+        mesh.generate_tangents();
+        mesh
+    }
+
+    pub fn uv_sphere(radius: f32, sectors: u32, stacks: u32) -> Self {
+        use std::f32::consts::PI;
+
+        let mut mesh = Self::empty("uv_sphere");
+        for i in 0..=stacks {
+            let phi = PI / 2.0 - i as f32 * PI / stacks as f32;
+            let (y, ring) = (phi.sin(), phi.cos());
+            for j in 0..=sectors {
+                let theta = j as f32 * 2.0 * PI / sectors as f32;
+                let normal = [ring * theta.sin(), y, ring * theta.cos()];
+                mesh.vertices.push(ModelVertex {
+                    position: [normal[0] * radius, normal[1] * radius, normal[2] * radius],
+                    tex_coords: [j as f32 / sectors as f32, i as f32 / stacks as f32],
+                    normal,
+                    tangent: [0.0; 4],
+                });
+            }
+        }
+
+        for i in 0..stacks {
+            for j in 0..sectors {
+                let k1 = i * (sectors + 1) + j;
+                let k2 = k1 + sectors + 1;
+                // The first and last stacks collapse to a point, so each only needs one triangle.
+                if i != 0 {
+                    mesh.indices.extend_from_slice(&[k1, k2, k1 + 1]);
+                }
+                if i != stacks - 1 {
+                    mesh.indices.extend_from_slice(&[k1 + 1, k2, k2 + 1]);
+                }
+            }
+        }
+        mesh.generate_tangents();
+        mesh
+    }
+
+    /// The tutorial5 pentagon extruded along Z so it has sides to light.
+    pub fn pentagon_prism(depth: f32) -> Self {
+        let half = depth * 0.5;
+        let mut mesh = Self::empty("pentagon_prism");
+
+        for (z, normal) in [(half, 1.0), (-half, -1.0)].iter() {
+            let base = mesh.vertices.len() as u32;
+            for (position, tex_coords) in PENTAGON.iter() {
+                mesh.vertices.push(ModelVertex {
+                    position: [position[0], position[1], *z],
+                    tex_coords: *tex_coords,
+                    normal: [0.0, 0.0, *normal],
+                    tangent: [0.0; 4],
+                });
+            }
+            let fan: [u32; 9] = if *normal > 0.0 {
+                [0, 1, 4, 1, 2, 4, 2, 3, 4]
+            } else {
+                [0, 4, 1, 1, 4, 2, 2, 4, 3]
+            };
+            mesh.indices.extend(fan.iter().map(|i| base + i));
+        }
+
+        for i in 0..PENTAGON.len() {
+            let (a, _) = PENTAGON[i];
+            let (b, _) = PENTAGON[(i + 1) % PENTAGON.len()];
+            // Outward normal of a counter-clockwise edge.
+            let normal = Vector3::new(b[1] - a[1], a[0] - b[0], 0.0).normalize();
+            mesh.push_quad(
+                [
+                    Vector3::new(a[0], a[1], half),
+                    Vector3::new(a[0], a[1], -half),
+                    Vector3::new(b[0], b[1], -half),
+                    Vector3::new(b[0], b[1], half),
+                ],
+                normal,
+                [[0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0]],
+            );
+        }
+        mesh.generate_tangents();
+        mesh
+    }
+
+    /// Loads every model in an OBJ file. Missing normals are generated from the faces and
+    /// tangents are always generated from the texture coordinates.
+    pub fn load_obj<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<Self>> {
+        let (models, _materials) = tobj::load_obj(
+            path.as_ref(),
+            &tobj::LoadOptions {
+                triangulate: true,
+                single_index: true,
+                ..Default::default()
+            },
+        )?;
+
+        let meshes = models
+            .into_iter()
+            .map(|model| {
+                let obj = model.mesh;
+                let has_normals = !obj.normals.is_empty();
+                let vertices = (0..obj.positions.len() / 3)
+                    .map(|i| ModelVertex {
+                        position: [
+                            obj.positions[i * 3],
+                            obj.positions[i * 3 + 1],
+                            obj.positions[i * 3 + 2],
+                        ],
+                        // OBJ puts the texture origin in the bottom left, wgpu in the top left.
+                        tex_coords: if obj.texcoords.is_empty() {
+                            [0.0, 0.0]
+                        } else {
+                            [obj.texcoords[i * 2], 1.0 - obj.texcoords[i * 2 + 1]]
+                        },
+                        normal: if has_normals {
+                            [
+                                obj.normals[i * 3],
+                                obj.normals[i * 3 + 1],
+                                obj.normals[i * 3 + 2],
+                            ]
+                        } else {
+                            [0.0; 3]
+                        },
+                        tangent: [0.0; 4],
+                    })
+                    .collect();
+
+                let mut mesh = Self {
+                    name: model.name,
+                    vertices,
+                    indices: obj.indices,
+                };
+                if !has_normals {
+                    mesh.generate_normals();
+                }
+                mesh.generate_tangents();
+                mesh
+            })
+            .collect();
+
+        Ok(meshes)
+    }
+
+    /// Smooth normals from the area weighted average of the faces around each vertex.
+    pub fn generate_normals(&mut self) {
+        let mut normals = vec![Vector3::zero(); self.vertices.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [p0, p1, p2] = self.triangle_positions(triangle);
+            // The cross product's length is twice the triangle's area.
+            let face_normal = (p1 - p0).cross(p2 - p0);
+            for &index in triangle {
+                normals[index as usize] += face_normal;
+            }
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            if normal.magnitude2() > f32::EPSILON {
+                vertex.normal = normal.normalize().into();
+            }
+        }
+    }
+
+    /// Generates per-vertex tangents following the MikkTSpace conventions: triangle
+    /// tangents are weighted by the corner angle, orthogonalized against the vertex
+    /// normal and the handedness is stored in `w`.
+    ///
+    /// The V coordinate is flipped first, as glTF exporters do, so normal maps authored
+    /// with green pointing up (Blender, glTF, OpenGL) come out right. Vertices are never
+    /// split, so results can differ from the reference implementation along UV seams.
+    pub fn generate_tangents(&mut self) {
+        let mut tangents = vec![Vector3::zero(); self.vertices.len()];
+        let mut bitangents = vec![Vector3::zero(); self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let positions = self.triangle_positions(triangle);
+            let flipped_uv = |i: u32| {
+                let uv = self.vertices[i as usize].tex_coords;
+                Vector2::new(uv[0], 1.0 - uv[1])
+            };
+            let uvs = [
+                flipped_uv(triangle[0]),
+                flipped_uv(triangle[1]),
+                flipped_uv(triangle[2]),
+            ];
+
+            let edge1 = positions[1] - positions[0];
+            let edge2 = positions[2] - positions[0];
+            let delta_uv1 = uvs[1] - uvs[0];
+            let delta_uv2 = uvs[2] - uvs[0];
+
+            let determinant = delta_uv1.x * delta_uv2.y - delta_uv2.x * delta_uv1.y;
+            if determinant.abs() <= f32::EPSILON {
+                // Degenerate texture mapping, the triangle has no usable tangent frame.
+                continue;
+            }
+            let r = 1.0 / determinant;
+            let tangent = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) * r;
+            let bitangent = (edge2 * delta_uv1.x - edge1 * delta_uv2.x) * r;
+
+            for corner in 0..3 {
+                let to_next = positions[(corner + 1) % 3] - positions[corner];
+                let to_prev = positions[(corner + 2) % 3] - positions[corner];
+                if to_next.magnitude2() <= f32::EPSILON || to_prev.magnitude2() <= f32::EPSILON {
+                    continue;
+                }
+                let angle = to_next.angle(to_prev).0;
+                let index = triangle[corner] as usize;
+                tangents[index] += tangent * angle;
+                bitangents[index] += bitangent * angle;
+            }
+        }
+
+        for (i, vertex) in self.vertices.iter_mut().enumerate() {
+            let normal = Vector3::from(vertex.normal);
+            // Gram-Schmidt so the frame stays orthonormal after interpolation.
+            let mut tangent = tangents[i] - normal * normal.dot(tangents[i]);
+            if tangent.magnitude2() <= f32::EPSILON {
+                tangent = any_perpendicular(normal);
+            }
+            let tangent = tangent.normalize();
+            let handedness = if normal.cross(tangent).dot(bitangents[i]) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            vertex.tangent = [tangent.x, tangent.y, tangent.z, handedness];
+        }
+    }
+
+    /// Line list indices with every triangle edge once, for drawing a wireframe on
+    /// devices without `Features::NON_FILL_POLYGON_MODE`.
+    pub fn edge_indices(&self) -> Vec<u32> {
+        let mut seen = HashSet::new();
+        let mut edges = Vec::new();
+        for triangle in self.indices.chunks_exact(3) {
+            for corner in 0..3 {
+                let a = triangle[corner];
+                let b = triangle[(corner + 1) % 3];
+                if seen.insert((a.min(b), a.max(b))) {
+                    edges.extend_from_slice(&[a, b]);
+                }
+            }
+        }
+        edges
+    }
+
+    fn triangle_positions(&self, triangle: &[u32]) -> [Vector3<f32>; 3] {
+        [
+            self.vertices[triangle[0] as usize].position.into(),
+            self.vertices[triangle[1] as usize].position.into(),
+            self.vertices[triangle[2] as usize].position.into(),
+        ]
+    }
+
+    pub fn upload(&self, device: &wgpu::Device) -> GpuMesh {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Vertex Buffer", self.name)),
+            contents: bytemuck::cast_slice(&self.vertices),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Index Buffer", self.name)),
+            contents: bytemuck::cast_slice(&self.indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        let edge_indices = self.edge_indices();
+        let edge_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Edge Index Buffer", self.name)),
+            contents: bytemuck::cast_slice(&edge_indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+
+        GpuMesh {
+            name: self.name.clone(),
+            vertex_buffer,
+            index_buffer,
+            num_indices: self.indices.len() as u32,
+            edge_index_buffer,
+            num_edge_indices: edge_indices.len() as u32,
+            num_vertices: self.vertices.len() as u32,
+        }
+    }
+}
+
+fn any_perpendicular(normal: Vector3<f32>) -> Vector3<f32> {
+    let reference = if normal.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    normal.cross(reference)
+}
+
+pub struct GpuMesh {
+    pub name: String,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub num_indices: u32,
+    /// Line list from `Mesh::edge_indices`.
+    pub edge_index_buffer: wgpu::Buffer,
+    pub num_edge_indices: u32,
+    pub num_vertices: u32,
+}
+
+impl GpuMesh {
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        instances: std::ops::Range<u32>,
+    ) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, instances);
+    }
+
+    /// Draws the edges, the pipeline must use `PrimitiveTopology::LineList`.
+    pub fn draw_edges<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        instances: std::ops::Range<u32>,
+    ) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.edge_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_edge_indices, 0, instances);
+    }
+
+    /// Draws every vertex once, the pipeline must use `PrimitiveTopology::PointList`.
+    pub fn draw_points<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        instances: std::ops::Range<u32>,
+    ) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, instances);
+    }
+}
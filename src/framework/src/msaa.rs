@@ -0,0 +1,176 @@
+use anyhow::*;
+
+use crate::texture;
+
+/// Sample counts that can be picked at runtime. wgpu 0.7 has no way to ask the adapter
+/// which counts it supports, so this sticks to 1 and 4, which are available everywhere.
+/// 2 and 8 fail pipeline creation on adapters that lack them.
+pub const SAMPLE_COUNTS: [u32; 2] = [1, 4];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MsaaSettings {
+    pub sample_count: u32,
+    /// Turns the fragment alpha into a coverage mask, giving cut-out textures soft
+    /// edges without blending or sorting. Has no effect with a single sample.
+    pub alpha_to_coverage: bool,
+}
+
+impl Default for MsaaSettings {
+    fn default() -> Self {
+        Self {
+            sample_count: 4,
+            alpha_to_coverage: false,
+        }
+    }
+}
+
+impl MsaaSettings {
+    /// Reads `--samples <count>` and `--alpha-to-coverage` from the command line
+    /// arguments, anything not given keeps its default.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut settings = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--samples" => {
+                    let value = args.next().context("--samples needs a value")?;
+                    let count = value
+                        .parse()
+                        .with_context(|| format!("Invalid sample count {:?}", value))?;
+                    if !SAMPLE_COUNTS.contains(&count) {
+                        bail!("Sample count must be one of {:?}", SAMPLE_COUNTS);
+                    }
+                    settings.sample_count = count;
+                }
+                "--alpha-to-coverage" => settings.alpha_to_coverage = true,
+                _ => bail!("Unknown argument {:?}", arg),
+            }
+        }
+        Ok(settings)
+    }
+
+    /// The sample count after this one in `SAMPLE_COUNTS`, wrapping back to 1.
+    pub fn next_sample_count(&self) -> u32 {
+        let index = SAMPLE_COUNTS
+            .iter()
+            .position(|count| *count == self.sample_count)
+            .map_or(0, |i| (i + 1) % SAMPLE_COUNTS.len());
+        SAMPLE_COUNTS[index]
+    }
+
+    pub fn multisample_state(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: self.alpha_to_coverage && self.sample_count > 1,
+        }
+    }
+}
+
+/// Multisampled color and depth targets matching the swap chain. The color target is
+/// resolved into the frame at the end of the pass, with a single sample the frame is
+/// drawn to directly.
+pub struct MsaaTarget {
+    sample_count: u32,
+    color: Option<wgpu::TextureView>,
+    depth: texture::Texture,
+}
+
+impl MsaaTarget {
+    pub fn new(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        sample_count: u32,
+    ) -> Self {
+        let color = if sample_count > 1 {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Multisampled Color Texture"),
+                size: wgpu::Extent3d {
+                    width: sc_desc.width,
+                    height: sc_desc.height,
+                    depth: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: sc_desc.format,
+                usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            });
+            Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+        } else {
+            None
+        };
+        let depth = texture::Texture::create_multisampled_depth_texture(
+            device,
+            sc_desc,
+            sample_count,
+            "Multisampled Depth Texture",
+        );
+
+        Self {
+            sample_count,
+            color,
+            depth,
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Color attachment that resolves into `frame_view`.
+    pub fn color_attachment<'a>(
+        &'a self,
+        frame_view: &'a wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachmentDescriptor<'a> {
+        match &self.color {
+            Some(view) => wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: view,
+                resolve_target: Some(frame_view),
+                // Only the resolved image is needed once the pass ends
+                ops: wgpu::Operations { load, store: false },
+            },
+            None => wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: true },
+            },
+        }
+    }
+
+    pub fn depth_attachment(&self) -> wgpu::RenderPassDepthStencilAttachmentDescriptor<'_> {
+        wgpu::RenderPassDepthStencilAttachmentDescriptor {
+            attachment: &self.depth.view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: false,
+            }),
+            stencil_ops: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<MsaaSettings> {
+        MsaaSettings::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn sample_counts() {
+        assert_eq!(args(&["--samples", "1"]).unwrap().sample_count, 1);
+        for count in &["2", "8", "x"] {
+            assert!(args(&["--samples", count]).is_err());
+        }
+        let mut settings = MsaaSettings::default();
+        let mut seen = Vec::new();
+        for _ in 0..SAMPLE_COUNTS.len() {
+            settings.sample_count = settings.next_sample_count();
+            seen.push(settings.sample_count);
+        }
+        assert_eq!(seen, vec![1, 4]);
+    }
+}
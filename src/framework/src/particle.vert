@@ -0,0 +1,48 @@
+#version 450
+
+// Per-instance attributes read straight from the particle storage buffer
+layout(location=0) in vec3 a_position;
+layout(location=1) in float a_age;
+layout(location=2) in float a_lifetime;
+layout(location=3) in vec4 a_color;
+layout(location=4) in float a_size;
+
+layout(location=0) out vec2 v_corner;
+layout(location=1) out vec4 v_color;
+
+layout(set=0, binding=0) uniform Camera {
+    vec4 u_view_position;
+    mat4 u_view_proj;
+};
+
+layout(set=1, binding=0) uniform Billboard {
+    vec4 u_camera_right;
+    vec4 u_camera_up;
+};
+
+const vec2 corners[6] = vec2[6](
+    vec2(-1.0, -1.0),
+    vec2(1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, 1.0)
+);
+
+void main() {
+    vec2 corner = corners[gl_VertexIndex];
+    v_corner = corner;
+
+    if (a_age >= a_lifetime) {
+        // Outside the clip volume, so dead particles produce no fragments
+        gl_Position = vec4(0.0, 0.0, 2.0, 1.0);
+        v_color = vec4(0.0);
+        return;
+    }
+
+    float t = a_age / a_lifetime;
+    v_color = vec4(a_color.rgb, a_color.a * (1.0 - t));
+
+    vec3 offset = (u_camera_right.xyz * corner.x + u_camera_up.xyz * corner.y) * a_size;
+    gl_Position = u_view_proj * vec4(a_position + offset, 1.0);
+}
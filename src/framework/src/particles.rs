@@ -0,0 +1,487 @@
+use std::time::Duration;
+
+use bytemuck::Zeroable;
+use cgmath::prelude::*;
+use cgmath::{Point3, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::camera::Camera;
+
+/// Invocations per workgroup, must match `local_size_x` in particles.comp.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// One particle as stored on the GPU. Mirrors the `Particle` struct in particles.comp,
+/// the same buffer is bound as instance data for drawing.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Particle {
+    pub position: [f32; 3],
+    pub age: f32,
+    pub velocity: [f32; 3],
+    pub lifetime: f32,
+    pub color: [f32; 4],
+    pub size: f32,
+    _padding: [f32; 3],
+}
+
+impl Particle {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Particle>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float,
+                },
+            ],
+        }
+    }
+}
+
+/// Mirrors the `Simulation` block in particles.comp.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimulationUniform {
+    gravity: [f32; 3],
+    dt: f32,
+    drag: f32,
+    particle_count: u32,
+    _padding: [u32; 2],
+}
+
+/// Mirrors the `Billboard` block in particle.vert.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BillboardUniform {
+    camera_right: [f32; 4],
+    camera_up: [f32; 4],
+}
+
+/// Spawns particles in a cone around `direction`.
+#[derive(Clone, Debug)]
+pub struct Emitter {
+    pub position: Point3<f32>,
+    pub direction: Vector3<f32>,
+    /// Half angle of the cone in radians, PI sprays in every direction.
+    pub spread: f32,
+    pub speed: f32,
+    /// Particles per second, leave at 0 for emitters only used with `burst`.
+    pub rate: f32,
+    /// Seconds a particle lives, it fades out over that time.
+    pub lifetime: f32,
+    pub size: f32,
+    pub color: [f32; 4],
+    /// Random variation of speed, lifetime and size as a fraction of their value.
+    pub variance: f32,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self {
+            position: Point3::origin(),
+            direction: Vector3::unit_y(),
+            spread: 0.3,
+            speed: 3.0,
+            rate: 0.0,
+            lifetime: 2.0,
+            size: 0.05,
+            color: [1.0, 1.0, 1.0, 1.0],
+            variance: 0.2,
+        }
+    }
+}
+
+/// Particles simulated by a compute shader and drawn as camera facing, additively
+/// blended quads.
+///
+/// The buffer is used as a ring: new particles overwrite the oldest slots, so the
+/// capacity should cover the emission rate times the longest lifetime.
+pub struct ParticleSystem {
+    pub emitters: Vec<Emitter>,
+    pub gravity: Vector3<f32>,
+    /// Fraction of the velocity lost per second.
+    pub drag: f32,
+
+    capacity: u32,
+    next_slot: u32,
+    // Fractional particles owed by each emitter, carried between frames.
+    spawn_accumulators: Vec<f32>,
+    pending: Vec<Particle>,
+    rng: Rng,
+
+    particle_buffer: wgpu::Buffer,
+    simulation_buffer: wgpu::Buffer,
+    compute_pipeline: wgpu::ComputePipeline,
+    compute_bind_group: wgpu::BindGroup,
+    billboard_buffer: wgpu::Buffer,
+    billboard_bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl ParticleSystem {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        capacity: u32,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        let particles = vec![Particle::zeroed(); capacity as usize];
+        let particle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Buffer"),
+            contents: bytemuck::cast_slice(&particles),
+            usage: wgpu::BufferUsage::STORAGE
+                | wgpu::BufferUsage::VERTEX
+                | wgpu::BufferUsage::COPY_DST,
+        });
+        let simulation_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Simulation Buffer"),
+            size: std::mem::size_of::<SimulationUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let billboard_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Billboard Buffer"),
+            size: std::mem::size_of::<BillboardUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("particle_compute_bind_group_layout"),
+            });
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &compute_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: particle_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: simulation_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("particle_compute_bind_group"),
+        });
+
+        let cs_module = device.create_shader_module(&wgpu::include_spirv!("particles.comp.spv"));
+        let compute_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Compute Pipeline Layout"),
+            bind_group_layouts: &[&compute_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Particle Compute Pipeline"),
+            layout: Some(&compute_layout),
+            module: &cs_module,
+            entry_point: "main",
+        });
+
+        let billboard_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("particle_billboard_bind_group_layout"),
+            });
+        let billboard_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &billboard_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: billboard_buffer.as_entire_binding(),
+            }],
+            label: Some("particle_billboard_bind_group"),
+        });
+
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("particle.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("particle.frag.spv"));
+        let render_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Render Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &billboard_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let additive = wgpu::BlendState {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Particle Render Pipeline"),
+            layout: Some(&render_layout),
+            vertex: wgpu::VertexState {
+                module: &vs_module,
+                entry_point: "main",
+                buffers: &[Particle::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fs_module,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format: color_format,
+                    alpha_blend: additive.clone(),
+                    color_blend: additive,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            // Additive blending doesn't care about order, so particles are tested against
+            // the scene but never occlude each other.
+            depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+                clamp_depth: false,
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        Self {
+            emitters: Vec::new(),
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            drag: 0.2,
+            capacity,
+            next_slot: 0,
+            spawn_accumulators: Vec::new(),
+            pending: Vec::new(),
+            rng: Rng::new(0x2545_f491),
+            particle_buffer,
+            simulation_buffer,
+            compute_pipeline,
+            compute_bind_group,
+            billboard_buffer,
+            billboard_bind_group,
+            render_pipeline,
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Spawns `count` particles from `emitter` at once, ignoring its rate.
+    pub fn burst(&mut self, emitter: &Emitter, count: u32) {
+        for _ in 0..count {
+            let particle = self.rng.particle(emitter);
+            self.pending.push(particle);
+        }
+    }
+
+    /// Kills every particle, including any spawned this frame.
+    pub fn clear(&mut self, queue: &wgpu::Queue) {
+        let particles = vec![Particle::zeroed(); self.capacity as usize];
+        queue.write_buffer(&self.particle_buffer, 0, bytemuck::cast_slice(&particles));
+        self.pending.clear();
+        self.next_slot = 0;
+    }
+
+    /// Runs the emitters and uploads the new particles and this frame's uniforms. Call
+    /// once per frame before `simulate`.
+    pub fn update(&mut self, queue: &wgpu::Queue, camera: &Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        self.spawn_accumulators.resize(self.emitters.len(), 0.0);
+        for (emitter, accumulator) in self.emitters.iter().zip(&mut self.spawn_accumulators) {
+            *accumulator += emitter.rate * dt;
+            let count = accumulator.floor();
+            *accumulator -= count;
+            for _ in 0..count as u32 {
+                self.pending.push(self.rng.particle(emitter));
+            }
+        }
+        self.upload_pending(queue);
+
+        queue.write_buffer(
+            &self.simulation_buffer,
+            0,
+            bytemuck::cast_slice(&[SimulationUniform {
+                gravity: self.gravity.into(),
+                dt,
+                drag: self.drag,
+                particle_count: self.capacity,
+                _padding: [0; 2],
+            }]),
+        );
+
+        let forward = (camera.target - camera.eye).normalize();
+        let right = forward.cross(camera.up).normalize();
+        let up = right.cross(forward);
+        queue.write_buffer(
+            &self.billboard_buffer,
+            0,
+            bytemuck::cast_slice(&[BillboardUniform {
+                camera_right: right.extend(0.0).into(),
+                camera_up: up.extend(0.0).into(),
+            }]),
+        );
+    }
+
+    /// Writes the spawned particles into the ring, in two parts when they wrap around.
+    fn upload_pending(&mut self, queue: &wgpu::Queue) {
+        let capacity = self.capacity as usize;
+        if self.pending.len() > capacity {
+            // Only the newest particles would survive the wrap anyway.
+            let excess = self.pending.len() - capacity;
+            self.pending.drain(..excess);
+        }
+
+        let mut spawned = &self.pending[..];
+        while !spawned.is_empty() {
+            let slot = self.next_slot as usize;
+            let count = spawned.len().min(capacity - slot);
+            queue.write_buffer(
+                &self.particle_buffer,
+                (slot * std::mem::size_of::<Particle>()) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&spawned[..count]),
+            );
+            self.next_slot = ((slot + count) % capacity) as u32;
+            spawned = &spawned[count..];
+        }
+        self.pending.clear();
+    }
+
+    /// Advances every live particle by the time passed to `update`.
+    pub fn simulate(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Particle Compute Pass"),
+        });
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+        let workgroups = self.capacity.div_ceil(WORKGROUP_SIZE);
+        compute_pass.dispatch(workgroups, 1, 1);
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.billboard_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.particle_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.capacity);
+    }
+}
+
+/// Xorshift generator, plenty for scattering particles.
+struct Rng(u32);
+
+impl Rng {
+    fn new(seed: u32) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    /// Uniform in 0..1.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// `value` scaled by a random factor in 1 - variance..1 + variance.
+    fn vary(&mut self, value: f32, variance: f32) -> f32 {
+        value * (1.0 + variance * (self.next_f32() * 2.0 - 1.0))
+    }
+
+    fn particle(&mut self, emitter: &Emitter) -> Particle {
+        // Uniform over the spherical cap, not bunched up around the axis.
+        let cos_theta = 1.0 - self.next_f32() * (1.0 - emitter.spread.cos());
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = self.next_f32() * std::f32::consts::PI * 2.0;
+
+        let axis = emitter.direction.normalize();
+        let reference = if axis.x.abs() < 0.9 {
+            Vector3::unit_x()
+        } else {
+            Vector3::unit_y()
+        };
+        let tangent = axis.cross(reference).normalize();
+        let bitangent = axis.cross(tangent);
+        let direction =
+            axis * cos_theta + (tangent * phi.cos() + bitangent * phi.sin()) * sin_theta;
+
+        let speed = self.vary(emitter.speed, emitter.variance);
+        Particle {
+            position: emitter.position.into(),
+            age: 0.0,
+            velocity: (direction * speed).into(),
+            lifetime: self.vary(emitter.lifetime, emitter.variance),
+            color: emitter.color,
+            size: self.vary(emitter.size, emitter.variance),
+            _padding: [0.0; 3],
+        }
+    }
+}
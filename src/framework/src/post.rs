@@ -0,0 +1,494 @@
+use std::path::Path;
+
+use anyhow::*;
+use wgpu::util::DeviceExt;
+
+use crate::fullscreen::{
+    create_fullscreen_pipeline, fullscreen_pass, sampler_entry, texture_entry, uniform_entry,
+    vertex_module,
+};
+
+const MAX_PARAMS: usize = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Effect {
+    Vignette,
+    ChromaticAberration,
+    Grayscale,
+    Crt,
+    Pixelate,
+    Sharpen,
+    ColorBlindness,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 7] = [
+        Effect::Vignette,
+        Effect::ChromaticAberration,
+        Effect::Grayscale,
+        Effect::Crt,
+        Effect::Pixelate,
+        Effect::Sharpen,
+        Effect::ColorBlindness,
+    ];
+
+    /// The name used for `type` in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Vignette => "vignette",
+            Effect::ChromaticAberration => "chromatic_aberration",
+            Effect::Grayscale => "grayscale",
+            Effect::Crt => "crt",
+            Effect::Pixelate => "pixelate",
+            Effect::Sharpen => "sharpen",
+            Effect::ColorBlindness => "color_blindness",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|effect| effect.name() == name)
+    }
+
+    /// Parameter names and defaults, in the order the shader's uniform block declares them.
+    pub fn params(self) -> &'static [(&'static str, f32)] {
+        match self {
+            Effect::Vignette => &[("strength", 0.6), ("radius", 1.4), ("softness", 0.8)],
+            Effect::ChromaticAberration => &[("amount", 0.005)],
+            Effect::Grayscale => &[("amount", 1.0)],
+            Effect::Crt => &[
+                ("curvature", 0.15),
+                ("scanline_strength", 0.3),
+                ("scanline_count", 240.0),
+                ("scroll_speed", 0.02),
+            ],
+            Effect::Pixelate => &[("pixel_size", 6.0)],
+            Effect::Sharpen => &[("amount", 0.5)],
+            Effect::ColorBlindness => &[("mode", 1.0), ("amount", 1.0)],
+        }
+    }
+
+    fn shader(self) -> wgpu::ShaderModuleDescriptor<'static> {
+        match self {
+            Effect::Vignette => wgpu::include_spirv!("post_vignette.frag.spv"),
+            Effect::ChromaticAberration => {
+                wgpu::include_spirv!("post_chromatic_aberration.frag.spv")
+            }
+            Effect::Grayscale => wgpu::include_spirv!("post_grayscale.frag.spv"),
+            Effect::Crt => wgpu::include_spirv!("post_crt.frag.spv"),
+            Effect::Pixelate => wgpu::include_spirv!("post_pixelate.frag.spv"),
+            Effect::Sharpen => wgpu::include_spirv!("post_sharpen.frag.spv"),
+            Effect::ColorBlindness => wgpu::include_spirv!("post_color_blindness.frag.spv"),
+        }
+    }
+}
+
+/// One entry of a post-processing chain.
+#[derive(Clone, Debug)]
+pub struct EffectConfig {
+    pub effect: Effect,
+    pub enabled: bool,
+    /// Values for `effect.params()`, any unused slots are zero.
+    pub params: [f32; MAX_PARAMS],
+}
+
+impl EffectConfig {
+    pub fn new(effect: Effect) -> Self {
+        let mut params = [0.0; MAX_PARAMS];
+        for (param, (_, default)) in params.iter_mut().zip(effect.params()) {
+            *param = *default;
+        }
+        Self {
+            effect,
+            enabled: true,
+            params,
+        }
+    }
+
+    pub fn set_param(&mut self, name: &str, value: f32) -> Result<()> {
+        let index = self
+            .effect
+            .params()
+            .iter()
+            .position(|(param, _)| *param == name)
+            .with_context(|| format!("{} has no parameter `{}`", self.effect.name(), name))?;
+        self.params[index] = value;
+        Ok(())
+    }
+}
+
+/// Parses a chain from TOML. Effects run in the order they are listed:
+///
+/// ```toml
+/// [[effect]]
+/// type = "vignette"
+/// strength = 0.5
+///
+/// [[effect]]
+/// type = "color_blindness"
+/// enabled = false
+/// mode = "protanopia"
+/// ```
+///
+/// Parameters that are left out keep their defaults from `Effect::params`.
+pub fn parse_chain(source: &str) -> Result<Vec<EffectConfig>> {
+    let value: toml::Value = source.parse()?;
+    let entries = match value.get("effect") {
+        Some(toml::Value::Array(entries)) => entries.as_slice(),
+        Some(_) => bail!("`effect` must be an array of tables, use [[effect]]"),
+        None => &[],
+    };
+
+    let mut chain = Vec::with_capacity(entries.len());
+    for (i, entry) in entries.iter().enumerate() {
+        let table = entry
+            .as_table()
+            .with_context(|| format!("effect {} is not a table", i))?;
+        let name = table
+            .get("type")
+            .and_then(toml::Value::as_str)
+            .with_context(|| format!("effect {} has no `type`", i))?;
+        let effect =
+            Effect::from_name(name).with_context(|| format!("unknown effect `{}`", name))?;
+
+        let mut config = EffectConfig::new(effect);
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("type", _) => {}
+                ("enabled", toml::Value::Boolean(enabled)) => config.enabled = *enabled,
+                ("mode", toml::Value::String(mode)) if effect == Effect::ColorBlindness => {
+                    let mode = match mode.as_str() {
+                        "protanopia" => 0.0,
+                        "deuteranopia" => 1.0,
+                        "tritanopia" => 2.0,
+                        _ => bail!("unknown color blindness mode `{}`", mode),
+                    };
+                    config.set_param("mode", mode)?;
+                }
+                (_, toml::Value::Float(number)) => config.set_param(key, *number as f32)?,
+                (_, toml::Value::Integer(number)) => config.set_param(key, *number as f32)?,
+                _ => bail!("unexpected value for `{}` in {}", key, name),
+            }
+        }
+        chain.push(config);
+    }
+    Ok(chain)
+}
+
+pub fn load_chain<P: AsRef<Path>>(path: P) -> Result<Vec<EffectConfig>> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("couldn't read {}", path.display()))?;
+    parse_chain(&source).with_context(|| format!("couldn't parse {}", path.display()))
+}
+
+/// Mirrors the start of every effect's uniform block, the named parameters follow.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct EffectUniform {
+    resolution: [f32; 2],
+    time: f32,
+    _padding: f32,
+    params: [f32; MAX_PARAMS],
+}
+
+struct Pass {
+    config: EffectConfig,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    /// `bind_groups[i]` reads ping-pong texture `i`.
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+struct Targets {
+    #[allow(dead_code)]
+    textures: [wgpu::Texture; 2],
+    views: [wgpu::TextureView; 2],
+}
+
+/// A chain of fullscreen effects. Draw the scene into `input_view()`, then `process`
+/// runs every enabled effect in turn, bouncing between two offscreen textures, and the
+/// last one writes to the output.
+pub struct PostChain {
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    vs_module: wgpu::ShaderModule,
+    copy_pipeline: wgpu::RenderPipeline,
+    copy_uniform_buffer: wgpu::Buffer,
+    copy_bind_group: wgpu::BindGroup,
+    targets: Targets,
+    passes: Vec<Pass>,
+}
+
+impl PostChain {
+    /// `format` is used for the offscreen textures and the output.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        chain: Vec<EffectConfig>,
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[texture_entry(0), sampler_entry(1), uniform_entry(2)],
+            label: Some("post_bind_group_layout"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let vs_module = vertex_module(device);
+
+        // Used when every effect is switched off.
+        let copy_module = device.create_shader_module(&wgpu::include_spirv!("post_copy.frag.spv"));
+        let copy_pipeline = create_fullscreen_pipeline(
+            device,
+            "Post Copy Pipeline",
+            &pipeline_layout,
+            &vs_module,
+            &copy_module,
+            format,
+            wgpu::BlendState::REPLACE,
+        );
+        let copy_uniform_buffer = create_uniform_buffer(device, "copy");
+
+        let targets = Targets::new(device, format, width, height);
+        let copy_bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &targets.views[0],
+            &sampler,
+            &copy_uniform_buffer,
+        );
+
+        let mut post = Self {
+            format,
+            width,
+            height,
+            sampler,
+            bind_group_layout,
+            pipeline_layout,
+            vs_module,
+            copy_pipeline,
+            copy_uniform_buffer,
+            copy_bind_group,
+            targets,
+            passes: Vec::new(),
+        };
+        post.set_chain(device, chain);
+        post
+    }
+
+    /// Replaces every pass, for example after reloading the config file.
+    pub fn set_chain(&mut self, device: &wgpu::Device, chain: Vec<EffectConfig>) {
+        self.passes = chain
+            .into_iter()
+            .map(|config| {
+                let module = device.create_shader_module(&config.effect.shader());
+                let pipeline = create_fullscreen_pipeline(
+                    device,
+                    config.effect.name(),
+                    &self.pipeline_layout,
+                    &self.vs_module,
+                    &module,
+                    self.format,
+                    wgpu::BlendState::REPLACE,
+                );
+                let uniform_buffer = create_uniform_buffer(device, config.effect.name());
+                let bind_groups = self.create_bind_groups(device, &uniform_buffer);
+                Pass {
+                    config,
+                    pipeline,
+                    uniform_buffer,
+                    bind_groups,
+                }
+            })
+            .collect();
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.targets = Targets::new(device, self.format, width, height);
+        self.copy_bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.targets.views[0],
+            &self.sampler,
+            &self.copy_uniform_buffer,
+        );
+        let mut passes = std::mem::take(&mut self.passes);
+        for pass in &mut passes {
+            pass.bind_groups = self.create_bind_groups(device, &pass.uniform_buffer);
+        }
+        self.passes = passes;
+    }
+
+    /// The texture to draw the scene into.
+    pub fn input_view(&self) -> &wgpu::TextureView {
+        &self.targets.views[0]
+    }
+
+    pub fn effects(&self) -> impl Iterator<Item = &EffectConfig> {
+        self.passes.iter().map(|pass| &pass.config)
+    }
+
+    /// Gives access to a pass's config, changes are uploaded by the next `update`.
+    pub fn effect_mut(&mut self, index: usize) -> Option<&mut EffectConfig> {
+        self.passes.get_mut(index).map(|pass| &mut pass.config)
+    }
+
+    /// Uploads the parameters of every pass along with the current time in seconds.
+    pub fn update(&self, queue: &wgpu::Queue, time: f32) {
+        for pass in &self.passes {
+            let uniform = EffectUniform {
+                resolution: [self.width as f32, self.height as f32],
+                time,
+                _padding: 0.0,
+                params: pass.config.params,
+            };
+            queue.write_buffer(&pass.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        }
+    }
+
+    pub fn process(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let enabled = self
+            .passes
+            .iter()
+            .filter(|pass| pass.config.enabled)
+            .collect::<Vec<_>>();
+
+        if enabled.is_empty() {
+            fullscreen_pass(
+                encoder,
+                "Post Copy Pass",
+                output,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                &self.copy_pipeline,
+                &self.copy_bind_group,
+            );
+            return;
+        }
+
+        let mut source = 0;
+        for (i, pass) in enabled.iter().enumerate() {
+            let target = if i + 1 == enabled.len() {
+                output
+            } else {
+                &self.targets.views[1 - source]
+            };
+            fullscreen_pass(
+                encoder,
+                pass.config.effect.name(),
+                target,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                &pass.pipeline,
+                &pass.bind_groups[source],
+            );
+            source = 1 - source;
+        }
+    }
+
+    fn create_bind_groups(
+        &self,
+        device: &wgpu::Device,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> [wgpu::BindGroup; 2] {
+        let create = |view| {
+            create_bind_group(
+                device,
+                &self.bind_group_layout,
+                view,
+                &self.sampler,
+                uniform_buffer,
+            )
+        };
+        [
+            create(&self.targets.views[0]),
+            create(&self.targets.views[1]),
+        ]
+    }
+}
+
+impl Targets {
+    fn new(device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+        let create = || {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("post_texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            (texture, view)
+        };
+        let (ping, ping_view) = create();
+        let (pong, pong_view) = create();
+
+        Self {
+            textures: [ping, pong],
+            views: [ping_view, pong_view],
+        }
+    }
+}
+
+fn create_uniform_buffer(device: &wgpu::Device, name: &str) -> wgpu::Buffer {
+    let uniform: EffectUniform = bytemuck::Zeroable::zeroed();
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(&format!("{} Uniform Buffer", name)),
+        contents: bytemuck::cast_slice(&[uniform]),
+        usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("post_bind_group"),
+    })
+}
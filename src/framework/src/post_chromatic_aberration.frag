@@ -0,0 +1,23 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_source;
+layout(set=0, binding=2) uniform ChromaticAberration {
+    vec2 u_resolution;
+    float u_time;
+    float _padding;
+    // Offset of the red and blue channels at the corners, in UV units
+    float u_amount;
+};
+
+void main() {
+    vec2 offset = (v_tex_coords - 0.5) * u_amount * 2.0;
+    vec4 center = texture(sampler2D(t_source, s_source), v_tex_coords);
+    float red = texture(sampler2D(t_source, s_source), v_tex_coords + offset).r;
+    float blue = texture(sampler2D(t_source, s_source), v_tex_coords - offset).b;
+    f_color = vec4(red, center.g, blue, center.a);
+}
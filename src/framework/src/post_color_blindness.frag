@@ -0,0 +1,33 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_source;
+layout(set=0, binding=2) uniform ColorBlindness {
+    vec2 u_resolution;
+    float u_time;
+    float _padding;
+    // 0 protanopia, 1 deuteranopia, 2 tritanopia
+    float u_mode;
+    float u_amount;
+};
+
+void main() {
+    vec4 color = texture(sampler2D(t_source, s_source), v_tex_coords);
+
+    // GLSL fills matrices column by column, so every three values say where one input channel ends up
+    mat3 simulation;
+    int mode = int(u_mode + 0.5);
+    if (mode == 0) {
+        simulation = mat3(0.567, 0.558, 0.0, 0.433, 0.442, 0.242, 0.0, 0.0, 0.758);
+    } else if (mode == 1) {
+        simulation = mat3(0.625, 0.7, 0.0, 0.375, 0.3, 0.3, 0.0, 0.0, 0.7);
+    } else {
+        simulation = mat3(0.95, 0.0, 0.0, 0.05, 0.433, 0.475, 0.0, 0.567, 0.525);
+    }
+
+    f_color = vec4(mix(color.rgb, simulation * color.rgb, u_amount), color.a);
+}
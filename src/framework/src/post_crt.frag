@@ -0,0 +1,40 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_source;
+layout(set=0, binding=2) uniform Crt {
+    vec2 u_resolution;
+    float u_time;
+    float _padding;
+    // How strongly the screen bulges outward
+    float u_curvature;
+    float u_scanline_strength;
+    float u_scanline_count;
+    // Speed the scanlines roll down the screen
+    float u_scroll_speed;
+};
+
+void main() {
+    // Barrel distortion
+    vec2 centered = v_tex_coords * 2.0 - 1.0;
+    centered *= 1.0 + u_curvature * dot(centered, centered) * 0.25;
+    vec2 uv = centered * 0.5 + 0.5;
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        f_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec4 color = texture(sampler2D(t_source, s_source), uv);
+    float scanline = sin((uv.y + u_time * u_scroll_speed) * u_scanline_count * 3.14159265);
+    color.rgb *= 1.0 - u_scanline_strength * (0.5 - 0.5 * scanline);
+
+    // Faint RGB phosphor mask every three pixels
+    int column = int(gl_FragCoord.x) % 3;
+    vec3 mask = vec3(0.9);
+    mask[column] = 1.1;
+    f_color = vec4(color.rgb * mask, color.a);
+}
@@ -0,0 +1,21 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_source;
+layout(set=0, binding=2) uniform Grayscale {
+    vec2 u_resolution;
+    float u_time;
+    float _padding;
+    float u_amount;
+};
+
+void main() {
+    vec4 color = texture(sampler2D(t_source, s_source), v_tex_coords);
+    // Rec. 709 luminance
+    float luminance = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    f_color = vec4(mix(color.rgb, vec3(luminance), u_amount), color.a);
+}
@@ -0,0 +1,21 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_source;
+layout(set=0, binding=2) uniform Pixelate {
+    vec2 u_resolution;
+    float u_time;
+    float _padding;
+    // Size of each block in screen pixels
+    float u_pixel_size;
+};
+
+void main() {
+    vec2 blocks = u_resolution / max(u_pixel_size, 1.0);
+    vec2 uv = (floor(v_tex_coords * blocks) + 0.5) / blocks;
+    f_color = texture(sampler2D(t_source, s_source), uv);
+}
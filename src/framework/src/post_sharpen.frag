@@ -0,0 +1,26 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_source;
+layout(set=0, binding=2) uniform Sharpen {
+    vec2 u_resolution;
+    float u_time;
+    float _padding;
+    float u_amount;
+};
+
+void main() {
+    vec2 texel = 1.0 / u_resolution;
+    vec4 center = texture(sampler2D(t_source, s_source), v_tex_coords);
+    vec3 neighbours = texture(sampler2D(t_source, s_source), v_tex_coords + vec2(texel.x, 0.0)).rgb
+        + texture(sampler2D(t_source, s_source), v_tex_coords - vec2(texel.x, 0.0)).rgb
+        + texture(sampler2D(t_source, s_source), v_tex_coords + vec2(0.0, texel.y)).rgb
+        + texture(sampler2D(t_source, s_source), v_tex_coords - vec2(0.0, texel.y)).rgb;
+    // Push the pixel away from the average of its neighbours
+    vec3 sharpened = center.rgb + (center.rgb * 4.0 - neighbours) * u_amount;
+    f_color = vec4(max(sharpened, vec3(0.0)), center.a);
+}
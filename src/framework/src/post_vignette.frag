@@ -0,0 +1,25 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_source;
+layout(set=0, binding=2) uniform Vignette {
+    vec2 u_resolution;
+    float u_time;
+    float _padding;
+    float u_strength;
+    // Distance from the center, in half screen heights, where the image is fully darkened
+    float u_radius;
+    float u_softness;
+};
+
+void main() {
+    vec4 color = texture(sampler2D(t_source, s_source), v_tex_coords);
+    // Keep the vignette round on wide windows
+    vec2 centered = (v_tex_coords - 0.5) * vec2(u_resolution.x / u_resolution.y, 1.0) * 2.0;
+    float vignette = 1.0 - smoothstep(u_radius - u_softness, u_radius, length(centered));
+    f_color = vec4(color.rgb * mix(1.0, vignette, u_strength), color.a);
+}
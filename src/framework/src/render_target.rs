@@ -0,0 +1,165 @@
+use crate::texture;
+
+/// An offscreen color texture, with an optional depth texture, that can be drawn into
+/// like a swap chain frame and then sampled through `bind_group()`.
+pub struct RenderTarget {
+    label: String,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    color: texture::Texture,
+    depth: Option<texture::Texture>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl RenderTarget {
+    /// `layout` must be compatible with `Texture::bind_group_layout`.
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        with_depth: bool,
+        label: &str,
+    ) -> Self {
+        let color = create_color_texture(device, width, height, format, label);
+        let depth = if with_depth {
+            Some(create_depth_texture(device, width, height, format, label))
+        } else {
+            None
+        };
+        let bind_group = color.bind_group(device, layout, Some(label));
+
+        Self {
+            label: label.to_string(),
+            format,
+            width,
+            height,
+            color,
+            depth,
+            bind_group,
+        }
+    }
+
+    /// Recreates the textures, the old contents are lost.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+    ) {
+        *self = Self::new(
+            device,
+            layout,
+            width,
+            height,
+            self.format,
+            self.depth.is_some(),
+            &self.label,
+        );
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.color.view
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Starts a render pass drawing into the target, clearing it to `clear_color` and
+    /// the depth, if any, to 1.0.
+    pub fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        clear_color: wgpu::Color,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&self.label),
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: &self.color.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: self.depth.as_ref().map(|depth| {
+                wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                    attachment: &depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }
+            }),
+        })
+    }
+}
+
+fn create_color_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    label: &str,
+) -> texture::Texture {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    });
+
+    texture::Texture {
+        texture,
+        view,
+        sampler,
+    }
+}
+
+fn create_depth_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    label: &str,
+) -> texture::Texture {
+    // create_depth_texture only looks at the size of the descriptor.
+    let sc_desc = wgpu::SwapChainDescriptor {
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+        format,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+    };
+    texture::Texture::create_depth_texture(device, &sc_desc, &format!("{} Depth", label))
+}
@@ -0,0 +1,609 @@
+use std::num::NonZeroU32;
+
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::camera::OPENGL_TO_WGPU_MATRIX;
+use crate::instance::InstanceRaw;
+use crate::light::{DirectionalLight, Lights, SpotLight};
+use crate::mesh::ModelVertex;
+
+/// Number of layers in the shadow map array. Directional lights claim layers first,
+/// in order, then spot lights; any light past the last layer is left unshadowed.
+pub const MAX_SHADOW_MAPS: usize = 4;
+
+pub const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+#[derive(Copy, Clone, Debug)]
+pub struct ShadowConfig {
+    /// Width and height of every shadow map in texels.
+    pub resolution: u32,
+    /// Constant depth bias in units of the depth format, pushes depth away from the light
+    /// to stop surfaces from shadowing themselves.
+    pub depth_bias: i32,
+    /// Extra bias scaled by the slope of the surface as seen from the light.
+    pub slope_bias: f32,
+    /// The main pass averages a (2r + 1) x (2r + 1) grid of comparisons, 0 is a single tap.
+    pub pcf_radius: u32,
+    /// Center of the box directional lights cast shadows in.
+    pub directional_center: Point3<f32>,
+    /// Half the width of the box directional lights cast shadows in.
+    pub directional_extent: f32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            resolution: 2048,
+            depth_bias: 2,
+            slope_bias: 2.0,
+            pcf_radius: 1,
+            directional_center: Point3::new(0.0, 0.0, 0.0),
+            directional_extent: 10.0,
+        }
+    }
+}
+
+impl ShadowConfig {
+    pub fn directional_view_proj(&self, light: &DirectionalLight) -> Matrix4<f32> {
+        let direction = light.direction.normalize();
+        let distance = self.directional_extent * 2.0;
+        let eye = self.directional_center - direction * distance;
+        let view = Matrix4::look_at_rh(eye, self.directional_center, up_for(direction));
+        let e = self.directional_extent;
+        let proj = cgmath::ortho(-e, e, -e, e, 0.1, distance * 2.0);
+        OPENGL_TO_WGPU_MATRIX * proj * view
+    }
+
+    pub fn spot_view_proj(light: &SpotLight) -> Matrix4<f32> {
+        let direction = light.direction.normalize();
+        let eye = Point3::from_vec(light.position);
+        let view = Matrix4::look_at_rh(eye, eye + direction, up_for(direction));
+        let proj = cgmath::perspective(light.outer_angle * 2.0, 1.0, 0.1, light.range);
+        OPENGL_TO_WGPU_MATRIX * proj * view
+    }
+}
+
+fn up_for(direction: Vector3<f32>) -> Vector3<f32> {
+    if direction.y.abs() < 0.99 {
+        Vector3::unit_y()
+    } else {
+        Vector3::unit_x()
+    }
+}
+
+/// Mirrors the `Shadows` block in the shaders.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowUniform {
+    light_view_proj: [[[f32; 4]; 4]; MAX_SHADOW_MAPS],
+    texel_size: f32,
+    pcf_radius: i32,
+    /// Number of directional lights with a layer, spot light `i` uses layer `directional_count + i`.
+    directional_count: u32,
+    /// Number of layers in use.
+    count: u32,
+}
+
+/// Depth rendered from each shadow casting light into one layer of a texture array.
+///
+/// Each frame call `update` with the scene's lights, then for every layer below `count()`
+/// record a pass with `begin_pass` and draw the shadow casters into it using
+/// `ModelVertex` at slot 0 and `InstanceRaw` at slot 1. The main pass reads the result
+/// through `bind_group`: the `Shadows` uniform at binding 0, the depth array at binding 1
+/// and a comparison sampler at binding 2.
+pub struct ShadowMaps {
+    config: ShadowConfig,
+    pipeline: wgpu::RenderPipeline,
+    pass_buffers: Vec<wgpu::Buffer>,
+    pass_bind_group_layout: wgpu::BindGroupLayout,
+    pass_bind_groups: Vec<wgpu::BindGroup>,
+    #[allow(dead_code)]
+    texture: wgpu::Texture,
+    layer_views: Vec<wgpu::TextureView>,
+    view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    uniform: ShadowUniform,
+    uniform_buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    debug_pipeline: wgpu::RenderPipeline,
+    debug_bind_group_layout: wgpu::BindGroupLayout,
+    debug_bind_group: wgpu::BindGroup,
+    debug_sampler: wgpu::Sampler,
+}
+
+impl ShadowMaps {
+    /// `color_format` and `depth_format` describe the pass the debug view is drawn in.
+    pub fn new(
+        device: &wgpu::Device,
+        config: ShadowConfig,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        let pass_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("shadow_pass_bind_group_layout"),
+            });
+        let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+        let pass_buffers = (0..MAX_SHADOW_MAPS)
+            .map(|i| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("Shadow Pass Buffer {}", i)),
+                    contents: bytemuck::cast_slice(&[identity]),
+                    usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                })
+            })
+            .collect::<Vec<_>>();
+        let pass_bind_groups = pass_buffers
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &pass_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                    label: Some(&format!("shadow_pass_bind_group_{}", i)),
+                })
+            })
+            .collect();
+
+        let pipeline = Self::create_pipeline(device, &pass_bind_group_layout, &config);
+        let (texture, view, layer_views) = Self::create_texture(device, config.resolution);
+
+        // Unlike a color sampler this one returns how much of the filtered footprint
+        // passes the depth comparison, which gives a 2x2 PCF for free with linear filtering.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let uniform = ShadowUniform {
+            light_view_proj: [identity; MAX_SHADOW_MAPS],
+            texel_size: 1.0 / config.resolution as f32,
+            pcf_radius: config.pcf_radius as i32,
+            directional_count: 0,
+            count: 0,
+        };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shadow Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: true,
+                        filtering: true,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("shadow_bind_group_layout"),
+        });
+        let bind_group =
+            Self::create_bind_group(device, &bind_group_layout, &uniform_buffer, &view, &sampler);
+
+        let debug_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                            sample_type: wgpu::TextureSampleType::Depth,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler {
+                            comparison: false,
+                            filtering: false,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("shadow_debug_bind_group_layout"),
+            });
+        let debug_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow_debug_sampler"),
+            ..Default::default()
+        });
+        let debug_bind_group =
+            Self::create_debug_bind_group(device, &debug_bind_group_layout, &view, &debug_sampler);
+        let debug_pipeline = Self::create_debug_pipeline(
+            device,
+            &debug_bind_group_layout,
+            color_format,
+            depth_format,
+        );
+
+        Self {
+            config,
+            pipeline,
+            pass_buffers,
+            pass_bind_group_layout,
+            pass_bind_groups,
+            texture,
+            layer_views,
+            view,
+            sampler,
+            uniform,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            debug_pipeline,
+            debug_bind_group_layout,
+            debug_bind_group,
+            debug_sampler,
+        }
+    }
+
+    pub fn config(&self) -> &ShadowConfig {
+        &self.config
+    }
+
+    /// Applies a new config, rebuilding the pipeline for the new bias and the shadow maps
+    /// when the resolution changed. `bind_group` is replaced, so fetch it again afterwards.
+    pub fn set_config(&mut self, device: &wgpu::Device, config: ShadowConfig) {
+        if config.depth_bias != self.config.depth_bias
+            || config.slope_bias != self.config.slope_bias
+        {
+            self.pipeline = Self::create_pipeline(device, &self.pass_bind_group_layout, &config);
+        }
+
+        if config.resolution != self.config.resolution {
+            let (texture, view, layer_views) = Self::create_texture(device, config.resolution);
+            self.bind_group = Self::create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.uniform_buffer,
+                &view,
+                &self.sampler,
+            );
+            self.debug_bind_group = Self::create_debug_bind_group(
+                device,
+                &self.debug_bind_group_layout,
+                &view,
+                &self.debug_sampler,
+            );
+            self.texture = texture;
+            self.view = view;
+            self.layer_views = layer_views;
+        }
+
+        self.config = config;
+        self.uniform.texel_size = 1.0 / config.resolution as f32;
+        self.uniform.pcf_radius = config.pcf_radius as i32;
+    }
+
+    /// Number of shadow maps filled by the last `update`.
+    pub fn count(&self) -> usize {
+        self.uniform.count as usize
+    }
+
+    /// Works out the light's view of the scene for every shadow casting light.
+    pub fn update(&mut self, queue: &wgpu::Queue, lights: &Lights) {
+        let mut matrices = Vec::with_capacity(MAX_SHADOW_MAPS);
+        for light in &lights.directional_lights {
+            matrices.push(self.config.directional_view_proj(light));
+        }
+        let directional_count = matrices.len().min(MAX_SHADOW_MAPS);
+        for light in &lights.spot_lights {
+            matrices.push(ShadowConfig::spot_view_proj(light));
+        }
+        matrices.truncate(MAX_SHADOW_MAPS);
+
+        for (i, matrix) in matrices.iter().enumerate() {
+            let raw: [[f32; 4]; 4] = (*matrix).into();
+            self.uniform.light_view_proj[i] = raw;
+            queue.write_buffer(&self.pass_buffers[i], 0, bytemuck::cast_slice(&[raw]));
+        }
+        self.uniform.directional_count = directional_count as u32;
+        self.uniform.count = matrices.len() as u32;
+
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniform]),
+        );
+    }
+
+    /// Starts a depth only pass into shadow map `layer` with the shadow pipeline set.
+    pub fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        layer: usize,
+    ) -> wgpu::RenderPass<'a> {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &self.layer_views[layer],
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.pass_bind_groups[layer], &[]);
+        render_pass
+    }
+
+    /// Draws every shadow map in use as a row of squares along the bottom of a
+    /// `target_width` x `target_height` render target.
+    pub fn draw_debug<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        target_width: u32,
+        target_height: u32,
+    ) {
+        let size = (target_width.min(target_height) as f32 / 4.0).floor();
+        render_pass.set_pipeline(&self.debug_pipeline);
+        render_pass.set_bind_group(0, &self.debug_bind_group, &[]);
+        for layer in 0..self.count() as u32 {
+            render_pass.set_viewport(
+                layer as f32 * size,
+                target_height as f32 - size,
+                size,
+                size,
+                0.0,
+                1.0,
+            );
+            // The instance index tells the shader which layer to show.
+            render_pass.draw(0..3, layer..layer + 1);
+        }
+        render_pass.set_viewport(
+            0.0,
+            0.0,
+            target_width as f32,
+            target_height as f32,
+            0.0,
+            1.0,
+        );
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        pass_bind_group_layout: &wgpu::BindGroupLayout,
+        config: &ShadowConfig,
+    ) -> wgpu::RenderPipeline {
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("shadow.vert.spv"));
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[pass_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vs_module,
+                entry_point: "main",
+                buffers: &[ModelVertex::desc(), InstanceRaw::desc()],
+            },
+            // Only depth is written
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: config.depth_bias,
+                    slope_scale: config.slope_bias,
+                    clamp: 0.0,
+                },
+                clamp_depth: false,
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        })
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        resolution: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView, Vec<wgpu::TextureView>) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow_maps"),
+            size: wgpu::Extent3d {
+                width: resolution,
+                height: resolution,
+                depth: MAX_SHADOW_MAPS as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("shadow_maps_view"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let layer_views = (0..MAX_SHADOW_MAPS as u32)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("shadow_map_layer_view"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        (texture, view, layer_views)
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("shadow_bind_group"),
+        })
+    }
+
+    fn create_debug_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("shadow_debug_bind_group"),
+        })
+    }
+
+    fn create_debug_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> wgpu::RenderPipeline {
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("shadow_debug.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("shadow_debug.frag.spv"));
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Debug Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Debug Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vs_module,
+                entry_point: "main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fs_module,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format: color_format,
+                    alpha_blend: wgpu::BlendState::REPLACE,
+                    color_blend: wgpu::BlendState::REPLACE,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            // Drawn on top of everything
+            depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+                clamp_depth: false,
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        })
+    }
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) flat in uint v_layer;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2DArray t_shadow;
+layout(set=0, binding=1) uniform sampler s_shadow;
+
+void main() {
+    float depth = texture(sampler2DArray(t_shadow, s_shadow), vec3(v_tex_coords, float(v_layer))).r;
+    // Perspective depth bunches up near 1.0, spread it out so spot light maps are readable.
+    f_color = vec4(vec3(pow(depth, 16.0)), 1.0);
+}
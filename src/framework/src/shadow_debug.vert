@@ -0,0 +1,12 @@
+#version 450
+
+layout(location=0) out vec2 v_tex_coords;
+layout(location=1) flat out uint v_layer;
+
+// A single triangle covering the viewport, no vertex buffer needed.
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    v_tex_coords = uv;
+    v_layer = gl_InstanceIndex;
+    gl_Position = vec4(uv * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.0, 1.0);
+}
@@ -0,0 +1,139 @@
+use wgpu::util::DeviceExt;
+
+use crate::texture::Texture;
+
+#[rustfmt::skip]
+const CUBE_VERTICES: &[[f32; 3]] = &[
+    [-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [-1.0, 1.0, -1.0],
+    [-1.0, -1.0, 1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0],
+];
+
+#[rustfmt::skip]
+const CUBE_INDICES: &[u16] = &[
+    0, 1, 2, 2, 3, 0, // -z
+    4, 6, 5, 6, 4, 7, // +z
+    0, 3, 7, 7, 4, 0, // -x
+    1, 5, 6, 6, 2, 1, // +x
+    3, 2, 6, 6, 7, 3, // +y
+    0, 4, 5, 5, 1, 0, // -y
+];
+
+/// Draws a cubemap behind everything else. Draw it after the opaque geometry, in a pass
+/// whose depth buffer was cleared to 1.0, so only the uncovered pixels get shaded.
+pub struct Skybox {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Skybox {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        cubemap: &Texture,
+        color_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+    ) -> Self {
+        let bind_group_layout = Texture::cube_bind_group_layout(device);
+        let bind_group = cubemap.bind_group(device, &bind_group_layout, Some("skybox_bind_group"));
+
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("skybox.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("skybox.frag.spv"));
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skybox Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vs_module,
+                entry_point: "main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttribute {
+                        offset: 0,
+                        shader_location: 0,
+                        format: wgpu::VertexFormat::Float3,
+                    }],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fs_module,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format: color_format,
+                    alpha_blend: wgpu::BlendState::REPLACE,
+                    color_blend: wgpu::BlendState::REPLACE,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // The camera is inside the cube
+                cull_mode: wgpu::CullMode::None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                // The sky sits exactly on the far plane, which Less would reject
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+                clamp_depth: false,
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Skybox Vertex Buffer"),
+            contents: bytemuck::cast_slice(CUBE_VERTICES),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Skybox Index Buffer"),
+            contents: bytemuck::cast_slice(CUBE_INDICES),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+
+        Self {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    /// Shows a different cubemap.
+    pub fn set_cubemap(&mut self, device: &wgpu::Device, cubemap: &Texture) {
+        self.bind_group =
+            cubemap.bind_group(device, &self.bind_group_layout, Some("skybox_bind_group"));
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..CUBE_INDICES.len() as u32, 0, 0..1);
+    }
+}
@@ -0,0 +1,18 @@
+#version 450
+
+layout(location=0) in vec3 a_position;
+
+layout(location=0) out vec3 v_direction;
+
+layout(set=0, binding=0) uniform Camera {
+    vec4 u_view_position;
+    mat4 u_view_proj;
+};
+
+void main() {
+    v_direction = a_position;
+    // Keep the cube centered on the camera so it never gets any closer
+    vec4 clip_position = u_view_proj * vec4(u_view_position.xyz + a_position, 1.0);
+    // z = w puts every fragment on the far plane, behind the rest of the scene
+    gl_Position = clip_position.xyww;
+}
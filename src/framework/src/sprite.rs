@@ -0,0 +1,418 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Rad, Vector2};
+use wgpu::util::DeviceExt;
+
+use crate::camera::OPENGL_TO_WGPU_MATRIX;
+use crate::texture::Texture;
+
+/// tutorial5's `Vertex` with a tint color.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpriteVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl SpriteVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<SpriteVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float4,
+                },
+            ],
+        }
+    }
+}
+
+/// A rectangle in texture coordinates, from 0 to 1 with (0, 0) in the top left corner.
+/// A negative width or height flips the sprite.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub const FULL: Rect = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    /// The rectangle covering the given pixels of a `texture_width` x `texture_height`
+    /// texture.
+    pub fn from_pixels(
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        texture_width: u32,
+        texture_height: u32,
+    ) -> Self {
+        Self {
+            x: x as f32 / texture_width as f32,
+            y: y as f32 / texture_height as f32,
+            width: width as f32 / texture_width as f32,
+            height: height as f32 / texture_height as f32,
+        }
+    }
+
+    pub fn flipped(self, horizontal: bool, vertical: bool) -> Self {
+        let mut rect = self;
+        if horizontal {
+            rect.x += rect.width;
+            rect.width = -rect.width;
+        }
+        if vertical {
+            rect.y += rect.height;
+            rect.height = -rect.height;
+        }
+        rect
+    }
+}
+
+/// Which texture a sprite samples, returned by `SpriteBatch::add_texture`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextureId(pub(crate) usize);
+
+#[derive(Copy, Clone, Debug)]
+pub struct Sprite {
+    pub texture: TextureId,
+    /// Where `origin` ends up, in the units of the batch's projection.
+    pub position: Vector2<f32>,
+    pub size: Vector2<f32>,
+    /// The point the sprite is placed and rotated around, from (0, 0) in its top left
+    /// corner to (1, 1) in the bottom right.
+    pub origin: Vector2<f32>,
+    /// Clockwise with the default screen projection, which has y pointing down.
+    pub rotation: Rad<f32>,
+    pub source: Rect,
+    pub color: [f32; 4],
+    /// Sprites in lower layers are drawn first. Within a layer sprites are grouped by
+    /// texture, so overlapping sprites only keep their order when they share one.
+    pub layer: i32,
+}
+
+impl Sprite {
+    pub fn new(texture: TextureId) -> Self {
+        Self {
+            texture,
+            position: Vector2::zero(),
+            size: Vector2::new(1.0, 1.0),
+            origin: Vector2::new(0.5, 0.5),
+            rotation: Rad(0.0),
+            source: Rect::FULL,
+            color: [1.0; 4],
+            layer: 0,
+        }
+    }
+
+    fn vertices(&self) -> [SpriteVertex; 4] {
+        let (sin, cos) = self.rotation.0.sin_cos();
+        let rect = self.source;
+        let corner = |x: f32, y: f32| {
+            let local = Vector2::new(
+                (x - self.origin.x) * self.size.x,
+                (y - self.origin.y) * self.size.y,
+            );
+            let rotated =
+                Vector2::new(local.x * cos - local.y * sin, local.x * sin + local.y * cos);
+            SpriteVertex {
+                position: [
+                    self.position.x + rotated.x,
+                    self.position.y + rotated.y,
+                    0.0,
+                ],
+                tex_coords: [rect.x + rect.width * x, rect.y + rect.height * y],
+                color: self.color,
+            }
+        };
+        [
+            corner(0.0, 0.0),
+            corner(0.0, 1.0),
+            corner(1.0, 1.0),
+            corner(1.0, 0.0),
+        ]
+    }
+}
+
+/// Pixel coordinates for a `width` x `height` target, with (0, 0) in the top left corner.
+pub fn screen_projection(width: f32, height: f32) -> Matrix4<f32> {
+    OPENGL_TO_WGPU_MATRIX * cgmath::ortho(0.0, width, height, 0.0, -1.0, 1.0)
+}
+
+/// The uniform holding a sprite pipeline's projection matrix, bind group 1.
+pub(crate) fn projection_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStage::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+        label: Some("sprite_projection_bind_group_layout"),
+    })
+}
+
+/// Draws `SpriteVertex` triangles with alpha blending, sampling a texture from bind
+/// group 0 and projecting with the matrix in bind group 1. Shared with the tilemap and
+/// text, which bring their own fragment shader where sprite.frag doesn't fit.
+pub(crate) fn create_pipeline(
+    device: &wgpu::Device,
+    color_format: wgpu::TextureFormat,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+    projection_bind_group_layout: &wgpu::BindGroupLayout,
+    fs_module: &wgpu::ShaderModule,
+) -> wgpu::RenderPipeline {
+    let vs_module = device.create_shader_module(&wgpu::include_spirv!("sprite.vert.spv"));
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Sprite Pipeline Layout"),
+        bind_group_layouts: &[texture_bind_group_layout, projection_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let alpha_blend = wgpu::BlendState {
+        src_factor: wgpu::BlendFactor::SrcAlpha,
+        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+        operation: wgpu::BlendOperation::Add,
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Sprite Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: "main",
+            buffers: &[SpriteVertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: fs_module,
+            entry_point: "main",
+            targets: &[wgpu::ColorTargetState {
+                format: color_format,
+                alpha_blend: alpha_blend.clone(),
+                color_blend: alpha_blend,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Flipped and mirrored sprites wind the other way
+            cull_mode: wgpu::CullMode::None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+    })
+}
+
+/// Draws textured quads. Sprites queued with `draw` during a frame are written into one
+/// vertex buffer by `prepare` and drawn by `render` with one call per run of sprites
+/// sharing a layer and texture.
+pub struct SpriteBatch {
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    textures: Vec<wgpu::BindGroup>,
+    projection_buffer: wgpu::Buffer,
+    projection_bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+
+    sprites: Vec<Sprite>,
+    /// Capacity of the buffers in sprites, they grow as needed.
+    capacity: usize,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    /// Index ranges from the last `prepare`, in sprites.
+    batches: Vec<(TextureId, std::ops::Range<u32>)>,
+}
+
+impl SpriteBatch {
+    pub fn new(device: &wgpu::Device, color_format: wgpu::TextureFormat, capacity: usize) -> Self {
+        let texture_bind_group_layout = Texture::bind_group_layout(device);
+
+        let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+        let projection_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sprite Projection Buffer"),
+            contents: bytemuck::cast_slice(&[identity]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let projection_bind_group_layout = projection_bind_group_layout(device);
+        let projection_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &projection_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: projection_buffer.as_entire_binding(),
+            }],
+            label: Some("sprite_projection_bind_group"),
+        });
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("sprite.frag.spv"));
+        let render_pipeline = create_pipeline(
+            device,
+            color_format,
+            &texture_bind_group_layout,
+            &projection_bind_group_layout,
+            &fs_module,
+        );
+
+        let capacity = capacity.max(1);
+        let (vertex_buffer, index_buffer) = Self::create_buffers(device, capacity);
+
+        Self {
+            texture_bind_group_layout,
+            textures: Vec::new(),
+            projection_buffer,
+            projection_bind_group,
+            render_pipeline,
+            sprites: Vec::new(),
+            capacity,
+            vertex_buffer,
+            index_buffer,
+            batches: Vec::new(),
+        }
+    }
+
+    fn create_buffers(device: &wgpu::Device, capacity: usize) -> (wgpu::Buffer, wgpu::Buffer) {
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Vertex Buffer"),
+            size: (capacity * 4 * std::mem::size_of::<SpriteVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Every quad uses the same pattern, so the indices never change
+        let indices = (0..capacity as u32)
+            .flat_map(|quad| {
+                let first = quad * 4;
+                vec![first, first + 1, first + 2, first, first + 2, first + 3]
+            })
+            .collect::<Vec<u32>>();
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sprite Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        (vertex_buffer, index_buffer)
+    }
+
+    /// The `texture::Texture::bind_group_layout` the sprite pipeline samples through.
+    pub fn texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.texture_bind_group_layout
+    }
+
+    /// Makes `texture` available to sprites. Put many images in one texture and pick them
+    /// with `Sprite::source` to keep the number of draw calls down.
+    pub fn add_texture(&mut self, device: &wgpu::Device, texture: &Texture) -> TextureId {
+        let bind_group = texture.bind_group(
+            device,
+            &self.texture_bind_group_layout,
+            Some("sprite_texture_bind_group"),
+        );
+        self.textures.push(bind_group);
+        TextureId(self.textures.len() - 1)
+    }
+
+    pub fn set_projection(&self, queue: &wgpu::Queue, projection: Matrix4<f32>) {
+        let projection: [[f32; 4]; 4] = projection.into();
+        queue.write_buffer(
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(&[projection]),
+        );
+    }
+
+    /// Queues `sprite` for the next `prepare`.
+    pub fn draw(&mut self, sprite: Sprite) {
+        self.sprites.push(sprite);
+    }
+
+    /// Sprites queued since the last `prepare`.
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Draw calls the last `prepare` produced.
+    pub fn draw_calls(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Uploads the queued sprites and clears the queue, growing the buffers if they
+    /// don't fit.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        // Stable, so sprites sharing a layer and texture keep the order they were drawn in
+        self.sprites
+            .sort_by_key(|sprite| (sprite.layer, sprite.texture));
+
+        if self.sprites.len() > self.capacity {
+            self.capacity = self.sprites.len().next_power_of_two();
+            let (vertex_buffer, index_buffer) = Self::create_buffers(device, self.capacity);
+            self.vertex_buffer = vertex_buffer;
+            self.index_buffer = index_buffer;
+        }
+
+        let mut vertices = Vec::with_capacity(self.sprites.len() * 4);
+        for sprite in &self.sprites {
+            vertices.extend_from_slice(&sprite.vertices());
+        }
+        if !vertices.is_empty() {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        }
+
+        self.batches.clear();
+        let mut previous = None;
+        for (index, sprite) in self.sprites.iter().enumerate() {
+            let key = (sprite.layer, sprite.texture);
+            let index = index as u32;
+            match self.batches.last_mut() {
+                Some((_, range)) if previous == Some(key) => range.end = index + 1,
+                _ => self.batches.push((sprite.texture, index..index + 1)),
+            }
+            previous = Some(key);
+        }
+        self.sprites.clear();
+    }
+
+    /// Draws what the last `prepare` uploaded.
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.batches.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(1, &self.projection_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for (texture, sprites) in &self.batches {
+            render_pass.set_bind_group(0, &self.textures[texture.0], &[]);
+            render_pass.draw_indexed(sprites.start * 6..sprites.end * 6, 0, 0..1);
+        }
+    }
+}
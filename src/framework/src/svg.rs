@@ -0,0 +1,1414 @@
+//! Loads the static subset of SVG that drawing tools export for flat artwork: paths,
+//! basic shapes, groups with transforms, and fills and strokes painted with solid
+//! colors or linear gradients. Text, images, filters, clipping and radial gradients
+//! are skipped.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::*;
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector2};
+
+use crate::vector::{FillRule, LineCap, LineJoin, Path, StrokeStyle, Tessellation, Tessellator};
+use crate::xml::{self, Element};
+
+/// A parsed document, with every shape already in document units: the view box mapped
+/// onto `width` x `height`, y pointing down.
+#[derive(Clone, Debug)]
+pub struct Svg {
+    pub width: f32,
+    pub height: f32,
+    pub shapes: Vec<Shape>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Shape {
+    pub path: Path,
+    pub fill: Option<Fill>,
+    pub stroke: Option<Stroke>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Fill {
+    pub paint: Paint,
+    pub rule: FillRule,
+}
+
+#[derive(Clone, Debug)]
+pub struct Stroke {
+    pub paint: Paint,
+    /// The width is already scaled by the shape's transform.
+    pub style: StrokeStyle,
+}
+
+/// Colors are sRGB as written in the file, with opacity folded into alpha.
+#[derive(Clone, Debug)]
+pub enum Paint {
+    Solid([f32; 4]),
+    LinearGradient(LinearGradient),
+}
+
+#[derive(Clone, Debug)]
+pub struct LinearGradient {
+    pub start: Vector2<f32>,
+    pub end: Vector2<f32>,
+    pub stops: Vec<GradientStop>,
+    /// Maps document units into the space `start` and `end` are in.
+    pub inverse_transform: Matrix4<f32>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: [f32; 4],
+}
+
+impl Paint {
+    pub fn color_at(&self, point: Vector2<f32>) -> [f32; 4] {
+        match self {
+            Paint::Solid(color) => *color,
+            Paint::LinearGradient(gradient) => gradient.color_at(point),
+        }
+    }
+}
+
+impl LinearGradient {
+    /// Colors before the first stop and after the last one are padded, like SVG's
+    /// default `spreadMethod`.
+    pub fn color_at(&self, point: Vector2<f32>) -> [f32; 4] {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [0.0; 4],
+        };
+        let t = self.offset_at(point);
+
+        if t <= first.offset {
+            return first.color;
+        }
+        for pair in self.stops.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if t <= b.offset {
+                let span = b.offset - a.offset;
+                let f = if span > 0.0 {
+                    (t - a.offset) / span
+                } else {
+                    1.0
+                };
+                let mut color = [0.0; 4];
+                for (i, channel) in color.iter_mut().enumerate() {
+                    *channel = a.color[i] + (b.color[i] - a.color[i]) * f;
+                }
+                return color;
+            }
+        }
+        last.color
+    }
+
+    /// How far along from `start` to `end` a point is, what stop offsets are measured in.
+    pub fn offset_at(&self, point: Vector2<f32>) -> f32 {
+        let point = self
+            .inverse_transform
+            .transform_point(Point3::new(point.x, point.y, 0.0));
+        let axis = self.end - self.start;
+        if axis.magnitude2() > 0.0 {
+            (Vector2::new(point.x, point.y) - self.start).dot(axis) / axis.magnitude2()
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Svg {
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Couldn't read {:?}", path))?;
+        Self::parse(&text).with_context(|| format!("Couldn't load {:?}", path))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let root = xml::parse(text)?;
+        if root.name != "svg" {
+            bail!("Expected an <svg> root element, found <{}>", root.name);
+        }
+
+        let view_box = match root.attribute("viewBox") {
+            Some(value) => {
+                let numbers = parse_numbers(value)?;
+                match numbers[..] {
+                    [x, y, width, height] if width > 0.0 && height > 0.0 => {
+                        Some([x, y, width, height])
+                    }
+                    _ => bail!("Invalid viewBox {:?}", value),
+                }
+            }
+            None => None,
+        };
+        let size = |name: &str, fallback: f32| -> Result<f32> {
+            match root.attribute(name) {
+                // Percentages are relative to a viewport we don't have
+                Some(value) if !value.trim_end().ends_with('%') => parse_length(value),
+                _ => Ok(fallback),
+            }
+        };
+        let width = size("width", view_box.map_or(300.0, |view_box| view_box[2]))?;
+        let height = size("height", view_box.map_or(150.0, |view_box| view_box[3]))?;
+
+        // Scale the view box uniformly to fit and center it, the default preserveAspectRatio
+        let transform = match view_box {
+            Some([x, y, view_width, view_height]) => {
+                let scale = (width / view_width).min(height / view_height);
+                Matrix4::from_translation(
+                    [
+                        (width - view_width * scale) / 2.0,
+                        (height - view_height * scale) / 2.0,
+                        0.0,
+                    ]
+                    .into(),
+                ) * Matrix4::from_nonuniform_scale(scale, scale, 1.0)
+                    * Matrix4::from_translation([-x, -y, 0.0].into())
+            }
+            None => Matrix4::identity(),
+        };
+
+        let mut gradients = HashMap::new();
+        collect_gradients(&root, &mut gradients)?;
+        let mut loader = Loader {
+            gradients,
+            size: Vector2::new(width, height),
+            shapes: Vec::new(),
+        };
+        let style = Style::default().apply(&root)?;
+        loader.load_children(&root, transform, &style)?;
+
+        Ok(Self {
+            width,
+            height,
+            shapes: loader.shapes,
+        })
+    }
+
+    /// Tessellates every shape in document order, so later shapes draw over earlier ones.
+    /// Colors are converted from sRGB to linear, matching how textures are loaded.
+    pub fn tessellate(&self, tessellator: &Tessellator) -> Tessellation {
+        let mut output = Tessellation::new();
+        for shape in &self.shapes {
+            if let Some(fill) = &shape.fill {
+                let first = (output.vertices.len(), output.indices.len());
+                tessellator.fill(&mut output, &shape.path, fill.rule, [1.0; 4]);
+                paint_vertices(&mut output, first, &fill.paint);
+            }
+            if let Some(stroke) = &shape.stroke {
+                let first = (output.vertices.len(), output.indices.len());
+                tessellator.stroke(&mut output, &shape.path, &stroke.style, [1.0; 4]);
+                paint_vertices(&mut output, first, &stroke.paint);
+            }
+        }
+        output
+    }
+}
+
+/// Colors the white vertices and triangles from `first` on with `paint`, scaling their
+/// coverage by its alpha. Colors are interpolated linearly between vertices, so the
+/// triangles are cut at every gradient stop first.
+fn paint_vertices(output: &mut Tessellation, first: (usize, usize), paint: &Paint) {
+    let (first_vertex, first_index) = first;
+    if let Paint::LinearGradient(gradient) = paint {
+        let offsets = gradient
+            .stops
+            .iter()
+            .map(|stop| stop.offset)
+            .collect::<Vec<_>>();
+        output.split_along(first_index, |point| gradient.offset_at(point), &offsets);
+    }
+    let vertices = output.vertices[first_vertex..].iter_mut();
+    for (vertex, coverage) in vertices.zip(&mut output.coverage[first_vertex..]) {
+        let color = paint.color_at(Vector2::new(vertex.position[0], vertex.position[1]));
+        vertex.color = [
+            srgb_to_linear(color[0]),
+            srgb_to_linear(color[1]),
+            srgb_to_linear(color[2]),
+        ];
+        *coverage *= color[3];
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PaintRef {
+    Color([f32; 3]),
+    /// The id of a gradient.
+    Url(String),
+}
+
+/// Presentation attributes, inherited from parent groups.
+#[derive(Clone, Debug)]
+struct Style {
+    fill: Option<PaintRef>,
+    fill_rule: FillRule,
+    fill_opacity: f32,
+    stroke: Option<PaintRef>,
+    stroke_opacity: f32,
+    stroke_style: StrokeStyle,
+    /// Group opacity is multiplied into each child rather than applied to the group
+    /// as a whole, so overlapping children show through each other.
+    opacity: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Some(PaintRef::Color([0.0, 0.0, 0.0])),
+            fill_rule: FillRule::NonZero,
+            fill_opacity: 1.0,
+            stroke: None,
+            stroke_opacity: 1.0,
+            stroke_style: StrokeStyle::default(),
+            opacity: 1.0,
+        }
+    }
+}
+
+impl Style {
+    /// This style with the element's attributes applied, and its `style` attribute over those.
+    fn apply(&self, element: &Element) -> Result<Self> {
+        let mut style = self.clone();
+        let mut opacity = 1.0;
+        let declarations = element
+            .attribute("style")
+            .into_iter()
+            .flat_map(|css| css.split(';'))
+            .filter_map(|declaration| {
+                let mut parts = declaration.splitn(2, ':');
+                Some((parts.next()?.trim(), parts.next()?.trim()))
+            });
+        let attributes = element
+            .attributes
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.trim()));
+        for (name, value) in attributes.chain(declarations) {
+            if value == "inherit" {
+                continue;
+            }
+            match name {
+                "fill" => style.fill = parse_paint(value)?,
+                "stroke" => style.stroke = parse_paint(value)?,
+                "fill-rule" => {
+                    style.fill_rule = match value {
+                        "evenodd" => FillRule::EvenOdd,
+                        _ => FillRule::NonZero,
+                    }
+                }
+                "fill-opacity" => style.fill_opacity = parse_opacity(value)?,
+                "stroke-opacity" => style.stroke_opacity = parse_opacity(value)?,
+                "opacity" => opacity = parse_opacity(value)?,
+                "stroke-width" => style.stroke_style.width = parse_length(value)?,
+                "stroke-miterlimit" => style.stroke_style.miter_limit = parse_number(value)?,
+                "stroke-linejoin" => {
+                    style.stroke_style.join = match value {
+                        "round" => LineJoin::Round,
+                        "bevel" => LineJoin::Bevel,
+                        _ => LineJoin::Miter,
+                    }
+                }
+                "stroke-linecap" => {
+                    style.stroke_style.cap = match value {
+                        "round" => LineCap::Round,
+                        "square" => LineCap::Square,
+                        _ => LineCap::Butt,
+                    }
+                }
+                _ => {}
+            }
+        }
+        style.opacity = self.opacity * opacity;
+        Ok(style)
+    }
+}
+
+struct GradientDef {
+    /// x1, y1, x2, y2, with whether each was a percentage.
+    coordinates: [Option<(f32, bool)>; 4],
+    user_space: bool,
+    transform: Matrix4<f32>,
+    stops: Vec<GradientStop>,
+    /// Another gradient to take the stops from when this one has none.
+    href: Option<String>,
+}
+
+fn collect_gradients(
+    element: &Element,
+    gradients: &mut HashMap<String, GradientDef>,
+) -> Result<()> {
+    for child in &element.elements {
+        if child.name == "linearGradient" {
+            if let Some(id) = child.attribute("id") {
+                let gradient =
+                    parse_gradient(child).with_context(|| format!("Invalid gradient {:?}", id))?;
+                gradients.insert(id.to_string(), gradient);
+            }
+        } else {
+            collect_gradients(child, gradients)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_gradient(element: &Element) -> Result<GradientDef> {
+    let mut coordinates = [None; 4];
+    for (coordinate, name) in coordinates.iter_mut().zip(["x1", "y1", "x2", "y2"].iter()) {
+        if let Some(value) = element.attribute(name) {
+            let value = value.trim();
+            *coordinate = Some(match value.strip_suffix('%') {
+                Some(percent) => (parse_number(percent)? / 100.0, true),
+                None => (parse_length(value)?, false),
+            });
+        }
+    }
+
+    let mut stops = Vec::new();
+    for stop in element.children("stop") {
+        let offset = match stop.attribute("offset").map(str::trim) {
+            Some(value) => match value.strip_suffix('%') {
+                Some(percent) => parse_number(percent)? / 100.0,
+                None => parse_number(value)?,
+            },
+            None => 0.0,
+        };
+        let mut color = [0.0, 0.0, 0.0];
+        let mut opacity = 1.0;
+        let declarations = stop
+            .attribute("style")
+            .into_iter()
+            .flat_map(|css| css.split(';'))
+            .filter_map(|declaration| {
+                let mut parts = declaration.splitn(2, ':');
+                Some((parts.next()?.trim(), parts.next()?.trim()))
+            });
+        let attributes = stop
+            .attributes
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.trim()));
+        for (name, value) in attributes.chain(declarations) {
+            match name {
+                "stop-color" => color = parse_color(value)?,
+                "stop-opacity" => opacity = parse_opacity(value)?,
+                _ => {}
+            }
+        }
+        // Offsets never go backwards, a smaller one is raised to the previous
+        let previous = stops.last().map_or(0.0, |stop: &GradientStop| stop.offset);
+        stops.push(GradientStop {
+            offset: offset.clamp(0.0, 1.0).max(previous),
+            color: [color[0], color[1], color[2], opacity],
+        });
+    }
+
+    Ok(GradientDef {
+        coordinates,
+        user_space: element.attribute("gradientUnits") == Some("userSpaceOnUse"),
+        transform: match element.attribute("gradientTransform") {
+            Some(value) => parse_transform(value)?,
+            None => Matrix4::identity(),
+        },
+        stops,
+        href: element
+            .attribute("href")
+            .and_then(|href| href.trim().strip_prefix('#'))
+            .map(str::to_string),
+    })
+}
+
+struct Loader {
+    gradients: HashMap<String, GradientDef>,
+    /// Document size, for user space gradient coordinates given in percent.
+    size: Vector2<f32>,
+    shapes: Vec<Shape>,
+}
+
+impl Loader {
+    fn load_children(
+        &mut self,
+        parent: &Element,
+        transform: Matrix4<f32>,
+        style: &Style,
+    ) -> Result<()> {
+        for element in &parent.elements {
+            let transform = match element.attribute("transform") {
+                Some(value) => transform * parse_transform(value)?,
+                None => transform,
+            };
+            match element.name.as_str() {
+                "g" | "a" | "switch" => {
+                    let style = style.apply(element)?;
+                    self.load_children(element, transform, &style)?;
+                }
+                "path" | "rect" | "circle" | "ellipse" | "line" | "polyline" | "polygon" => {
+                    let style = style.apply(element)?;
+                    let path = shape_path(element)
+                        .with_context(|| format!("Invalid <{}>", element.name))?;
+                    if let Some(path) = path {
+                        self.add_shape(path, transform, &style);
+                    }
+                }
+                // Gradients were collected up front, everything else is unsupported
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn add_shape(&mut self, mut path: Path, transform: Matrix4<f32>, style: &Style) {
+        let bounds = bounds(&path);
+        let fill = style.fill.as_ref().and_then(|paint| {
+            let opacity = style.opacity * style.fill_opacity;
+            Some(Fill {
+                paint: self.resolve(paint, opacity, bounds, transform)?,
+                rule: style.fill_rule,
+            })
+        });
+        let stroke = style
+            .stroke
+            .as_ref()
+            .and_then(|paint| {
+                let opacity = style.opacity * style.stroke_opacity;
+                // Non-uniform scales can't be followed exactly, so use the average
+                let scale = (transform.x.x * transform.y.y - transform.x.y * transform.y.x)
+                    .abs()
+                    .sqrt();
+                Some(Stroke {
+                    paint: self.resolve(paint, opacity, bounds, transform)?,
+                    style: StrokeStyle {
+                        width: style.stroke_style.width * scale,
+                        ..style.stroke_style
+                    },
+                })
+            })
+            .filter(|stroke| stroke.style.width > 0.0);
+
+        if fill.is_none() && stroke.is_none() {
+            return;
+        }
+        path.transform(&transform);
+        self.shapes.push(Shape { path, fill, stroke });
+    }
+
+    /// `None` for references to gradients that don't exist, which SVG draws as no paint.
+    fn resolve(
+        &self,
+        paint: &PaintRef,
+        opacity: f32,
+        bounds: Option<(Vector2<f32>, Vector2<f32>)>,
+        transform: Matrix4<f32>,
+    ) -> Option<Paint> {
+        let id = match paint {
+            PaintRef::Color([r, g, b]) => return Some(Paint::Solid([*r, *g, *b, opacity])),
+            PaintRef::Url(id) => id,
+        };
+        let gradient = self.gradients.get(id)?;
+
+        // Follow href chains for the stops, giving up on cycles
+        let mut stops = &gradient.stops;
+        let mut source = gradient;
+        for _ in 0..8 {
+            if !stops.is_empty() {
+                break;
+            }
+            source = self.gradients.get(source.href.as_ref()?)?;
+            stops = &source.stops;
+        }
+        let stops = stops
+            .iter()
+            .map(|stop| GradientStop {
+                color: [
+                    stop.color[0],
+                    stop.color[1],
+                    stop.color[2],
+                    stop.color[3] * opacity,
+                ],
+                ..*stop
+            })
+            .collect();
+
+        let coordinate = |index: usize, default: f32, size: f32| match gradient.coordinates[index] {
+            Some((value, true)) if gradient.user_space => value * size,
+            Some((value, _)) => value,
+            None if gradient.user_space => default * size,
+            None => default,
+        };
+        let start = Vector2::new(
+            coordinate(0, 0.0, self.size.x),
+            coordinate(1, 0.0, self.size.y),
+        );
+        let end = Vector2::new(
+            coordinate(2, 1.0, self.size.x),
+            coordinate(3, 0.0, self.size.y),
+        );
+
+        let units = if gradient.user_space {
+            Matrix4::identity()
+        } else {
+            // A flat shape has no bounding box to map onto, so it isn't painted
+            let (min, max) = bounds?;
+            let size = max - min;
+            if size.x <= 0.0 || size.y <= 0.0 {
+                return None;
+            }
+            Matrix4::from_translation(min.extend(0.0))
+                * Matrix4::from_nonuniform_scale(size.x, size.y, 1.0)
+        };
+        let inverse_transform = (transform * units * gradient.transform).invert()?;
+
+        Some(Paint::LinearGradient(LinearGradient {
+            start,
+            end,
+            stops,
+            inverse_transform,
+        }))
+    }
+}
+
+/// The smallest box around a path's flattened outline, before any transform.
+fn bounds(path: &Path) -> Option<(Vector2<f32>, Vector2<f32>)> {
+    let mut points = path
+        .flatten(0.1)
+        .into_iter()
+        .flat_map(|polyline| polyline.points);
+    let first = points.next()?;
+    Some(points.fold((first, first), |(min, max), point| {
+        (
+            Vector2::new(min.x.min(point.x), min.y.min(point.y)),
+            Vector2::new(max.x.max(point.x), max.y.max(point.y)),
+        )
+    }))
+}
+
+/// The outline of a shape element, `None` when it has nothing to draw.
+fn shape_path(element: &Element) -> Result<Option<Path>> {
+    let length = |name: &str| -> Result<f32> {
+        match element.attribute(name) {
+            Some(value) => parse_length(value),
+            None => Ok(0.0),
+        }
+    };
+    let path = match element.name.as_str() {
+        "path" => parse_path_data(element.attribute("d").unwrap_or(""))?,
+        "rect" => {
+            let (x, y) = (length("x")?, length("y")?);
+            let (width, height) = (length("width")?, length("height")?);
+            if width <= 0.0 || height <= 0.0 {
+                return Ok(None);
+            }
+            // A missing radius takes the other one
+            let (rx, ry) = match (element.attribute("rx"), element.attribute("ry")) {
+                (None, None) => (0.0, 0.0),
+                (Some(_), None) => (length("rx")?, length("rx")?),
+                (None, Some(_)) => (length("ry")?, length("ry")?),
+                (Some(_), Some(_)) => (length("rx")?, length("ry")?),
+            };
+            rounded_rect(
+                x,
+                y,
+                width,
+                height,
+                rx.min(width / 2.0),
+                ry.min(height / 2.0),
+            )
+        }
+        "circle" => {
+            let radius = length("r")?;
+            if radius <= 0.0 {
+                return Ok(None);
+            }
+            Path::circle(Vector2::new(length("cx")?, length("cy")?), radius)
+        }
+        "ellipse" => {
+            let radii = Vector2::new(length("rx")?, length("ry")?);
+            if radii.x <= 0.0 || radii.y <= 0.0 {
+                return Ok(None);
+            }
+            Path::ellipse(Vector2::new(length("cx")?, length("cy")?), radii)
+        }
+        "line" => Path::polyline(
+            &[
+                Vector2::new(length("x1")?, length("y1")?),
+                Vector2::new(length("x2")?, length("y2")?),
+            ],
+            false,
+        ),
+        _ => {
+            let numbers = parse_numbers(element.attribute("points").unwrap_or(""))?;
+            let points = numbers
+                .chunks_exact(2)
+                .map(|pair| Vector2::new(pair[0], pair[1]))
+                .collect::<Vec<_>>();
+            Path::polyline(&points, element.name == "polygon")
+        }
+    };
+    Ok(Some(path))
+}
+
+fn rounded_rect(x: f32, y: f32, width: f32, height: f32, rx: f32, ry: f32) -> Path {
+    if rx <= 0.0 || ry <= 0.0 {
+        return Path::rect(Vector2::new(x, y), Vector2::new(width, height));
+    }
+    let (right, bottom) = (x + width, y + height);
+    let mut path = Path::new();
+    let corner = |path: &mut Path, from: Vector2<f32>, to: Vector2<f32>| {
+        arc_to(path, from, Vector2::new(rx, ry), 0.0, false, true, to);
+    };
+    path.move_to(Vector2::new(x + rx, y))
+        .line_to(Vector2::new(right - rx, y));
+    corner(
+        &mut path,
+        Vector2::new(right - rx, y),
+        Vector2::new(right, y + ry),
+    );
+    path.line_to(Vector2::new(right, bottom - ry));
+    corner(
+        &mut path,
+        Vector2::new(right, bottom - ry),
+        Vector2::new(right - rx, bottom),
+    );
+    path.line_to(Vector2::new(x + rx, bottom));
+    corner(
+        &mut path,
+        Vector2::new(x + rx, bottom),
+        Vector2::new(x, bottom - ry),
+    );
+    path.line_to(Vector2::new(x, y + ry));
+    corner(&mut path, Vector2::new(x, y + ry), Vector2::new(x + rx, y));
+    path.close();
+    path
+}
+
+/// Appends an SVG elliptical arc from `from` to `to` as cubic Béziers of at most a
+/// quarter turn each, following the SVG spec's endpoint to center conversion.
+fn arc_to(
+    path: &mut Path,
+    from: Vector2<f32>,
+    radii: Vector2<f32>,
+    rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: Vector2<f32>,
+) {
+    let (mut rx, mut ry) = (radii.x.abs(), radii.y.abs());
+    if (to - from).magnitude2() < 1e-12 {
+        return;
+    }
+    if rx < 1e-6 || ry < 1e-6 {
+        path.line_to(to);
+        return;
+    }
+
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let rotate = |v: Vector2<f32>| Vector2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y);
+    let half = (from - to) / 2.0;
+    let p = Vector2::new(cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+
+    // Radii too small to reach the end point are scaled up until they just do
+    let lambda = (p.x * p.x) / (rx * rx) + (p.y * p.y) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = rx * rx * ry * ry - rx * rx * p.y * p.y - ry * ry * p.x * p.x;
+    let denominator = rx * rx * p.y * p.y + ry * ry * p.x * p.x;
+    let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        coefficient = -coefficient;
+    }
+    let center_prime = Vector2::new(coefficient * rx * p.y / ry, -coefficient * ry * p.x / rx);
+    let center = rotate(center_prime) + (from + to) / 2.0;
+
+    let angle = |u: Vector2<f32>, v: Vector2<f32>| (u.x * v.y - u.y * v.x).atan2(u.dot(v));
+    let start = Vector2::new((p.x - center_prime.x) / rx, (p.y - center_prime.y) / ry);
+    let end = Vector2::new((-p.x - center_prime.x) / rx, (-p.y - center_prime.y) / ry);
+    let theta = angle(Vector2::unit_x(), start);
+    let mut delta = angle(start, end);
+    if !sweep && delta > 0.0 {
+        delta -= std::f32::consts::TAU;
+    } else if sweep && delta < 0.0 {
+        delta += std::f32::consts::TAU;
+    }
+
+    let segments = (delta.abs() / std::f32::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+    let step = delta / segments as f32;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan();
+    let point = |t: f32| center + rotate(Vector2::new(rx * t.cos(), ry * t.sin()));
+    let tangent = |t: f32| rotate(Vector2::new(-rx * t.sin(), ry * t.cos()));
+    for i in 0..segments {
+        let t1 = theta + step * i as f32;
+        let t2 = t1 + step;
+        let end = if i + 1 == segments { to } else { point(t2) };
+        path.cubic_to(
+            point(t1) + tangent(t1) * handle,
+            end - tangent(t2) * handle,
+            end,
+        );
+    }
+}
+
+/// A cursor over the numbers and letters of path data, point lists and transforms.
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .bytes
+            .get(self.position)
+            .is_some_and(|byte| byte.is_ascii_whitespace() || *byte == b',')
+        {
+            self.position += 1;
+        }
+    }
+
+    fn is_end(&mut self) -> bool {
+        self.skip_separators();
+        self.position >= self.bytes.len()
+    }
+
+    fn has_number(&mut self) -> bool {
+        self.skip_separators();
+        self.bytes
+            .get(self.position)
+            .is_some_and(|byte| byte.is_ascii_digit() || b"+-.".contains(byte))
+    }
+
+    fn letter(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let byte = *self.bytes.get(self.position)?;
+        if byte.is_ascii_alphabetic() {
+            self.position += 1;
+            Some(byte)
+        } else {
+            None
+        }
+    }
+
+    /// Numbers may run together, as in `1-2` or `0.5.5`.
+    fn number(&mut self) -> Result<f32> {
+        self.skip_separators();
+        let start = self.position;
+        let digits = |tokens: &mut Self| {
+            while tokens
+                .bytes
+                .get(tokens.position)
+                .is_some_and(u8::is_ascii_digit)
+            {
+                tokens.position += 1;
+            }
+        };
+        if self
+            .bytes
+            .get(self.position)
+            .is_some_and(|byte| b"+-".contains(byte))
+        {
+            self.position += 1;
+        }
+        digits(self);
+        if self.bytes.get(self.position) == Some(&b'.') {
+            self.position += 1;
+            digits(self);
+        }
+        // Only an exponent if digits follow, so `2em` stays a number and a unit
+        if self
+            .bytes
+            .get(self.position)
+            .is_some_and(|byte| b"eE".contains(byte))
+        {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if self
+                .bytes
+                .get(self.position)
+                .is_some_and(|byte| b"+-".contains(byte))
+            {
+                self.position += 1;
+            }
+            if self
+                .bytes
+                .get(self.position)
+                .is_some_and(u8::is_ascii_digit)
+            {
+                digits(self);
+            } else {
+                self.position = mantissa_end;
+            }
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).unwrap_or("");
+        text.parse::<f32>()
+            .ok()
+            .filter(|number| number.is_finite())
+            .ok_or_else(|| anyhow!("Expected a number at {:?}", self.rest(start)))
+    }
+
+    /// Arc flags are a single digit and often written without separators.
+    fn flag(&mut self) -> Result<bool> {
+        self.skip_separators();
+        let flag = match self.bytes.get(self.position) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => bail!("Expected an arc flag at {:?}", self.rest(self.position)),
+        };
+        self.position += 1;
+        Ok(flag)
+    }
+
+    fn point(&mut self) -> Result<Vector2<f32>> {
+        Ok(Vector2::new(self.number()?, self.number()?))
+    }
+
+    fn rest(&self, start: usize) -> String {
+        let end = (start + 16).min(self.bytes.len());
+        String::from_utf8_lossy(&self.bytes[start..end]).into_owned()
+    }
+}
+
+fn parse_path_data(data: &str) -> Result<Path> {
+    let mut tokens = Tokens::new(data);
+    let mut path = Path::new();
+    let mut command = None;
+    let mut current = Vector2::zero();
+    let mut start = Vector2::zero();
+    // Second control points of the previous command, reflected by S and T
+    let mut last_cubic = None;
+    let mut last_quadratic = None;
+
+    while !tokens.is_end() {
+        if let Some(letter) = tokens.letter() {
+            command = Some(letter);
+        } else if command.is_none() {
+            bail!("Path data has to start with a command");
+        }
+        let letter = command.unwrap();
+        let origin = if letter.is_ascii_lowercase() {
+            current
+        } else {
+            Vector2::zero()
+        };
+        let (mut cubic, mut quadratic) = (None, None);
+
+        match letter.to_ascii_uppercase() {
+            b'M' => {
+                current = origin + tokens.point()?;
+                start = current;
+                path.move_to(current);
+                // Further pairs after a move are lines
+                command = Some(if letter == b'm' { b'l' } else { b'L' });
+            }
+            b'L' => {
+                current = origin + tokens.point()?;
+                path.line_to(current);
+            }
+            b'H' => {
+                current.x = origin.x + tokens.number()?;
+                path.line_to(current);
+            }
+            b'V' => {
+                current.y = origin.y + tokens.number()?;
+                path.line_to(current);
+            }
+            b'C' | b'S' => {
+                let control1 = if letter.eq_ignore_ascii_case(&b'C') {
+                    origin + tokens.point()?
+                } else {
+                    last_cubic.map_or(current, |control| current * 2.0 - control)
+                };
+                let control2 = origin + tokens.point()?;
+                current = origin + tokens.point()?;
+                path.cubic_to(control1, control2, current);
+                cubic = Some(control2);
+            }
+            b'Q' | b'T' => {
+                let control = if letter.eq_ignore_ascii_case(&b'Q') {
+                    origin + tokens.point()?
+                } else {
+                    last_quadratic.map_or(current, |control| current * 2.0 - control)
+                };
+                current = origin + tokens.point()?;
+                path.quadratic_to(control, current);
+                quadratic = Some(control);
+            }
+            b'A' => {
+                let radii = tokens.point()?;
+                let rotation = tokens.number()?;
+                let large_arc = tokens.flag()?;
+                let sweep = tokens.flag()?;
+                let to = origin + tokens.point()?;
+                arc_to(&mut path, current, radii, rotation, large_arc, sweep, to);
+                current = to;
+            }
+            b'Z' => {
+                path.close();
+                current = start;
+                // A close takes no numbers, so a new command has to follow
+                command = None;
+            }
+            _ => bail!("Unknown path command {:?}", letter as char),
+        }
+        last_cubic = cubic;
+        last_quadratic = quadratic;
+
+        if command.is_none() && tokens.has_number() {
+            bail!("Numbers after a close command");
+        }
+    }
+    Ok(path)
+}
+
+fn parse_numbers(text: &str) -> Result<Vec<f32>> {
+    let mut tokens = Tokens::new(text);
+    let mut numbers = Vec::new();
+    while !tokens.is_end() {
+        numbers.push(tokens.number()?);
+    }
+    Ok(numbers)
+}
+
+fn parse_number(text: &str) -> Result<f32> {
+    f32::from_str(text.trim())
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| anyhow!("Invalid number {:?}", text))
+}
+
+/// A length in user units, converting absolute units at 96 pixels per inch.
+fn parse_length(text: &str) -> Result<f32> {
+    let text = text.trim();
+    let units = [
+        ("px", 1.0),
+        ("pt", 96.0 / 72.0),
+        ("pc", 16.0),
+        ("mm", 96.0 / 25.4),
+        ("cm", 96.0 / 2.54),
+        ("in", 96.0),
+    ];
+    for (unit, scale) in units.iter() {
+        if let Some(number) = text.strip_suffix(unit) {
+            return Ok(parse_number(number)? * scale);
+        }
+    }
+    parse_number(text).with_context(|| format!("Invalid length {:?}", text))
+}
+
+fn parse_opacity(text: &str) -> Result<f32> {
+    let text = text.trim();
+    let value = match text.strip_suffix('%') {
+        Some(percent) => parse_number(percent)? / 100.0,
+        None => parse_number(text)?,
+    };
+    Ok(value.clamp(0.0, 1.0))
+}
+
+/// The transform list of a `transform` attribute, applied right to left.
+fn parse_transform(text: &str) -> Result<Matrix4<f32>> {
+    let mut transform = Matrix4::identity();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let open = rest
+            .find('(')
+            .with_context(|| format!("Invalid transform {:?}", text))?;
+        let close = rest[open..]
+            .find(')')
+            .map(|close| open + close)
+            .with_context(|| format!("Invalid transform {:?}", text))?;
+        let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let arguments = parse_numbers(&rest[open + 1..close])?;
+        let step = match (name, arguments.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => affine(a, b, c, d, e, f),
+            ("translate", &[x]) => affine(1.0, 0.0, 0.0, 1.0, x, 0.0),
+            ("translate", &[x, y]) => affine(1.0, 0.0, 0.0, 1.0, x, y),
+            ("scale", &[s]) => affine(s, 0.0, 0.0, s, 0.0, 0.0),
+            ("scale", &[x, y]) => affine(x, 0.0, 0.0, y, 0.0, 0.0),
+            ("rotate", &[angle]) => rotation(angle),
+            ("rotate", &[angle, x, y]) => {
+                affine(1.0, 0.0, 0.0, 1.0, x, y)
+                    * rotation(angle)
+                    * affine(1.0, 0.0, 0.0, 1.0, -x, -y)
+            }
+            ("skewX", &[angle]) => affine(1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0),
+            ("skewY", &[angle]) => affine(1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0),
+            _ => bail!("Invalid transform {}({:?})", name, &rest[open + 1..close]),
+        };
+        transform = transform * step;
+        rest = rest[close + 1..].trim_start();
+    }
+    Ok(transform)
+}
+
+/// SVG's `matrix(a b c d e f)`.
+fn affine(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Matrix4<f32> {
+    #[rustfmt::skip]
+    let matrix = Matrix4::new(
+        a, b, 0.0, 0.0,
+        c, d, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        e, f, 0.0, 1.0,
+    );
+    matrix
+}
+
+fn rotation(degrees: f32) -> Matrix4<f32> {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    affine(cos, sin, -sin, cos, 0.0, 0.0)
+}
+
+/// `None` for `none`, which leaves the fill or stroke off.
+fn parse_paint(text: &str) -> Result<Option<PaintRef>> {
+    let text = text.trim();
+    if text == "none" || text == "transparent" {
+        return Ok(None);
+    }
+    if let Some(url) = text.strip_prefix("url(") {
+        // Anything after the closing parenthesis is a fallback color we don't need
+        let id = url
+            .split(')')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .trim_matches(|c| c == '\'' || c == '"');
+        let id = id.strip_prefix('#').with_context(|| {
+            format!("Only local paint references are supported, not {:?}", text)
+        })?;
+        return Ok(Some(PaintRef::Url(id.to_string())));
+    }
+    Ok(Some(PaintRef::Color(parse_color(text)?)))
+}
+
+fn parse_color(text: &str) -> Result<[f32; 3]> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix('#') {
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16))
+            .collect::<Option<Vec<_>>>()
+            .with_context(|| format!("Invalid color {:?}", text))?;
+        let bytes = match digits[..] {
+            [r, g, b] => [r * 17, g * 17, b * 17],
+            [r1, r2, g1, g2, b1, b2] => [r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2],
+            _ => bail!("Invalid color {:?}", text),
+        };
+        return Ok([
+            bytes[0] as f32 / 255.0,
+            bytes[1] as f32 / 255.0,
+            bytes[2] as f32 / 255.0,
+        ]);
+    }
+    if let Some(arguments) = text
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels = arguments
+            .split(',')
+            .map(|channel| {
+                let channel = channel.trim();
+                let value = match channel.strip_suffix('%') {
+                    Some(percent) => parse_number(percent)? / 100.0,
+                    None => parse_number(channel)? / 255.0,
+                };
+                Ok(value.clamp(0.0, 1.0))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        return match channels[..] {
+            [r, g, b] => Ok([r, g, b]),
+            _ => bail!("Invalid color {:?}", text),
+        };
+    }
+
+    // The basic CSS colors plus a few that drawings use often. currentColor has no
+    // CSS color to refer to here, so it falls back to black like an unset color.
+    let rgb: [u8; 3] = match text.to_ascii_lowercase().as_str() {
+        "black" | "currentcolor" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "lime" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "silver" => [192, 192, 192],
+        "gray" | "grey" => [128, 128, 128],
+        "maroon" => [128, 0, 0],
+        "olive" => [128, 128, 0],
+        "green" => [0, 128, 0],
+        "purple" => [128, 0, 128],
+        "teal" => [0, 128, 128],
+        "navy" => [0, 0, 128],
+        "orange" => [255, 165, 0],
+        "pink" => [255, 192, 203],
+        "brown" => [165, 42, 42],
+        "gold" => [255, 215, 0],
+        _ => bail!("Unknown color {:?}", text),
+    };
+    Ok([
+        rgb[0] as f32 / 255.0,
+        rgb[1] as f32 / 255.0,
+        rgb[2] as f32 / 255.0,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHARP: Tessellator = Tessellator {
+        tolerance: 0.01,
+        feather: 0.0,
+    };
+
+    fn points(data: &str) -> Vec<Vec<[f32; 2]>> {
+        parse_path_data(data)
+            .unwrap()
+            .flatten(0.1)
+            .into_iter()
+            .map(|polyline| polyline.points.iter().map(|&p| p.into()).collect())
+            .collect()
+    }
+
+    /// Covered area, weighting each triangle by its average coverage.
+    fn area(output: &Tessellation) -> f32 {
+        output
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                let corner = |i: usize| {
+                    let index = triangle[i] as usize;
+                    let position = output.vertices[index].position;
+                    (
+                        Vector2::new(position[0], position[1]),
+                        output.coverage[index],
+                    )
+                };
+                let ((a, ca), (b, cb), (c, cc)) = (corner(0), corner(1), corner(2));
+                let cross = (b - a).x * (c - a).y - (b - a).y * (c - a).x;
+                cross.abs() / 2.0 * (ca + cb + cc) / 3.0
+            })
+            .sum()
+    }
+
+    fn parse(body: &str) -> Result<Svg> {
+        Svg::parse(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">{}</svg>"#,
+            body
+        ))
+    }
+
+    fn svg_area(body: &str) -> f32 {
+        area(&parse(body).unwrap().tessellate(&SHARP))
+    }
+
+    fn assert_matrix(actual: Matrix4<f32>, expected: Matrix4<f32>) {
+        let actual: &[f32; 16] = actual.as_ref();
+        let expected: &[f32; 16] = expected.as_ref();
+        for (a, b) in actual.iter().zip(expected.iter()) {
+            assert!((a - b).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn path_commands() {
+        assert_eq!(
+            points("M 1 2 L 3 4 h 2 v -1 H 0 V 0 z m 5,5 l1-1"),
+            vec![
+                vec![
+                    [1.0, 2.0],
+                    [3.0, 4.0],
+                    [5.0, 4.0],
+                    [5.0, 3.0],
+                    [0.0, 3.0],
+                    [0.0, 0.0]
+                ],
+                vec![[6.0, 7.0], [7.0, 6.0]],
+            ]
+        );
+        // Pairs after a move are lines, and numbers can run together
+        assert_eq!(
+            points("m1.5.5 1 0-1-1e0"),
+            vec![vec![[1.5, 0.5], [2.5, 0.5], [1.5, -0.5]]]
+        );
+        // S and T reflect the previous control point, so these are straight lines
+        let curves = points("M 0 0 C 1 0 2 0 3 0 S 5 0 6 0 Q 7 0 8 0 T 10 0");
+        assert!(curves[0].iter().all(|point| point[1] == 0.0));
+        assert_eq!(curves[0].last(), Some(&[10.0, 0.0]));
+    }
+
+    #[test]
+    fn path_errors() {
+        for data in &[
+            "1 1",
+            "M 0 0 L 1",
+            "M 0 0 X 1 1",
+            "M 0 0 Z 1 1",
+            "M 0 0 A 1 1 0 2 0 1 1",
+        ] {
+            assert!(parse_path_data(data).is_err(), "{:?} parsed", data);
+        }
+    }
+
+    #[test]
+    fn arcs() {
+        // Half a circle of radius 5 around (5, 0), the second with radii too small to reach
+        for data in &["M 0 0 A 5 5 0 0 1 10 0 Z", "M 0 0 A 1 1 0 0 1 10 0 Z"] {
+            let arc = points(data);
+            assert_eq!(arc[0][0], [0.0, 0.0]);
+            assert_eq!(arc[0].last(), Some(&[10.0, 0.0]));
+            for point in &arc[0] {
+                let radius = (Vector2::from(*point) - Vector2::new(5.0, 0.0)).magnitude();
+                assert!((radius - 5.0).abs() < 0.1, "{:?} is off the circle", point);
+            }
+            let mut output = Tessellation::new();
+            SHARP.fill(
+                &mut output,
+                &parse_path_data(data).unwrap(),
+                FillRule::NonZero,
+                [1.0; 4],
+            );
+            let half_circle = std::f32::consts::PI * 25.0 / 2.0;
+            assert!((area(&output) - half_circle).abs() < 0.1);
+        }
+        // A positive sweep turns clockwise on screen, so over the top with y down
+        assert!(points("M 0 0 A 5 5 0 0 1 10 0")[0][1][1] < 0.0);
+        assert!(points("M 0 0 A 5 5 0 0 0 10 0")[0][1][1] > 0.0);
+        // Zero radii are straight lines
+        assert_eq!(
+            points("M 0 0 A 0 5 0 0 1 10 0"),
+            vec![vec![[0.0, 0.0], [10.0, 0.0]]]
+        );
+    }
+
+    #[test]
+    fn transforms() {
+        assert_matrix(
+            parse_transform("translate(10) scale(2, 3)").unwrap(),
+            affine(2.0, 0.0, 0.0, 3.0, 10.0, 0.0),
+        );
+        assert_matrix(
+            parse_transform(" matrix(1,2,3,4,5,6) ").unwrap(),
+            affine(1.0, 2.0, 3.0, 4.0, 5.0, 6.0),
+        );
+        // Rotating about a point keeps that point where it is
+        let rotate = parse_transform("rotate(90, 5, 5)").unwrap();
+        let moved = rotate.transform_point(Point3::new(5.0, 5.0, 0.0));
+        assert!((moved.x - 5.0).abs() < 1e-5 && (moved.y - 5.0).abs() < 1e-5);
+        assert_matrix(
+            parse_transform("skewX(45)").unwrap(),
+            affine(1.0, 0.0, 1.0, 1.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn transform_errors() {
+        for text in &[
+            "a)b(",
+            ")(",
+            "translate(1",
+            "translate 1",
+            "scale(1) )scale(2)",
+            "skewX(1 2)",
+            "spin(90)",
+            "translate(1 x)",
+        ] {
+            assert!(parse_transform(text).is_err(), "{:?} parsed", text);
+        }
+    }
+
+    #[test]
+    fn zero_length_caps() {
+        let dot = |cap: &str, data: &str| {
+            svg_area(&format!(
+                r#"<path d="{}" fill="none" stroke="black" stroke-width="4" stroke-linecap="{}"/>"#,
+                data, cap
+            ))
+        };
+        let circle = std::f32::consts::PI * 4.0;
+        assert!((dot("round", "M 5 5 Z") - circle).abs() < 0.1);
+        assert!((dot("round", "M 5 5 L 5 5") - circle).abs() < 0.1);
+        assert!((dot("square", "M 5 5 l 0 0") - 16.0).abs() < 1e-3);
+        assert_eq!(dot("butt", "M 5 5 Z"), 0.0);
+        // A lone move isn't a subpath
+        assert_eq!(dot("round", "M 5 5"), 0.0);
+        assert_eq!(
+            dot("round", "M 5 5 M 10 10 L 20 10"),
+            dot("round", "M 10 10 L 20 10")
+        );
+    }
+
+    #[test]
+    fn non_finite_numbers() {
+        for body in &[
+            r#"<rect x="NaN" width="10" height="10"/>"#,
+            r#"<rect width="inf" height="10"/>"#,
+            r#"<rect width="10" height="1e39"/>"#,
+            r#"<path d="M0 0 L1e39 0 L0 10 Z"/>"#,
+            r#"<path d="M0 0 L10 0 L0 10 Z" transform="scale(1e39)"/>"#,
+        ] {
+            assert!(parse(body).is_err(), "{:?} parsed", body);
+        }
+        // Finite numbers can still overflow once transformed, those contours are dropped
+        let overflow = r#"<path d="M0 0 L1e30 0 L0 10 Z" transform="scale(1e30)"/>"#;
+        assert_eq!(svg_area(overflow), 0.0);
+    }
+
+    #[test]
+    fn gradient_stops() {
+        let svg = parse(
+            r##"<linearGradient id="g">
+                <stop offset="0" stop-color="red"/>
+                <stop offset="0.25" stop-color="lime"/>
+                <stop offset="1" stop-color="blue"/>
+            </linearGradient>
+            <rect width="100" height="10" fill="url(#g)"/>"##,
+        )
+        .unwrap();
+        let output = svg.tessellate(&SHARP);
+        assert!((area(&output) - 1000.0).abs() < 1e-2);
+
+        let x = |index: u32| output.vertices[index as usize].position[0];
+        for triangle in output.indices.chunks(3) {
+            let xs = [x(triangle[0]), x(triangle[1]), x(triangle[2])];
+            assert!(
+                xs.iter().all(|x| *x <= 25.001) || xs.iter().all(|x| *x >= 24.999),
+                "{:?} crosses the middle stop",
+                xs
+            );
+        }
+        let assert_color = |at: f32, expected: [f32; 3]| {
+            let vertex = output
+                .vertices
+                .iter()
+                .find(|vertex| (vertex.position[0] - at).abs() < 1e-3)
+                .expect("No vertex at the stop");
+            for (actual, expected) in vertex.color.iter().zip(&expected) {
+                assert!(
+                    (actual - expected).abs() < 1e-5,
+                    "{:?} at {}",
+                    vertex.color,
+                    at
+                );
+            }
+        };
+        assert_color(0.0, [1.0, 0.0, 0.0]);
+        assert_color(25.0, [0.0, 1.0, 0.0]);
+        assert_color(100.0, [0.0, 0.0, 1.0]);
+    }
+}
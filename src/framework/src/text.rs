@@ -0,0 +1,758 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::*;
+use cgmath::{Matrix4, SquareMatrix};
+use rusttype::{point, GlyphId, Scale};
+use wgpu::util::DeviceExt;
+
+use crate::sprite::{self, SpriteVertex};
+
+/// Width and height of each glyph atlas texture.
+const ATLAS_SIZE: u32 = 1024;
+/// Empty pixels around each glyph so filtering doesn't pick up its neighbours.
+const ATLAS_PADDING: u32 = 1;
+/// Distance field glyphs are rasterized once at this size and scaled to any other.
+pub const SDF_SIZE: f32 = 48.0;
+/// How far from the edge distances are stored, in pixels of an `SDF_SIZE` glyph.
+const SDF_SPREAD: u32 = 6;
+
+pub struct Font {
+    font: rusttype::Font<'static>,
+}
+
+impl Font {
+    /// Reads a TrueType or OpenType font.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        let font = rusttype::Font::try_from_vec(bytes).context("Invalid font")?;
+        Ok(Self { font })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("Couldn't read {:?}", path))?;
+        Self::from_bytes(bytes).with_context(|| format!("Couldn't load {:?}", path))
+    }
+
+    /// Distance between the baselines of two lines of text `size` pixels high.
+    pub fn line_height(&self, size: f32) -> f32 {
+        let v_metrics = self.font.v_metrics(Scale::uniform(size));
+        v_metrics.ascent - v_metrics.descent + v_metrics.line_gap
+    }
+}
+
+/// How glyphs are stored and drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GlyphMode {
+    /// Rasterized for each size they are drawn at. Sharpest at small sizes.
+    Bitmap,
+    /// Stored once as a signed distance field that stays sharp when scaled up, rotated
+    /// or zoomed, at the cost of rounding off fine details.
+    Sdf,
+}
+
+impl GlyphMode {
+    fn index(self) -> usize {
+        match self {
+            GlyphMode::Bitmap => 0,
+            GlyphMode::Sdf => 1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// A block of text to lay out and draw.
+#[derive(Copy, Clone, Debug)]
+pub struct Section<'a> {
+    pub text: &'a str,
+    /// Top left corner of the block, in the units of the renderer's projection.
+    pub position: [f32; 2],
+    /// Height of a line in pixels.
+    pub size: f32,
+    pub color: [f32; 4],
+    /// Wraps lines at word boundaries to fit. Without it only newlines start a line.
+    pub max_width: Option<f32>,
+    /// Aligns lines within `max_width`, or around `position` when there is none.
+    pub align: Align,
+    pub mode: GlyphMode,
+}
+
+impl<'a> Section<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            position: [0.0, 0.0],
+            size: 16.0,
+            color: [1.0; 4],
+            max_width: None,
+            align: Align::Left,
+            mode: GlyphMode::Bitmap,
+        }
+    }
+}
+
+/// Where each glyph of a section goes.
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    /// Glyphs with their pen position on the baseline. Whitespace is left out.
+    pub glyphs: Vec<(GlyphId, [f32; 2])>,
+    pub width: f32,
+    pub height: f32,
+    pub lines: usize,
+}
+
+/// A glyph on a line being laid out.
+#[derive(Copy, Clone)]
+struct LineGlyph {
+    id: GlyphId,
+    x: f32,
+    advance: f32,
+    whitespace: bool,
+}
+
+/// Width of a line, not counting whitespace at its end.
+fn line_width(line: &[LineGlyph]) -> f32 {
+    line.iter()
+        .rev()
+        .find(|glyph| !glyph.whitespace)
+        .map_or(0.0, |glyph| glyph.x + glyph.advance)
+}
+
+pub fn layout(font: &Font, section: &Section) -> Layout {
+    let font = &font.font;
+    let scale = Scale::uniform(section.size);
+    let v_metrics = font.v_metrics(scale);
+    let line_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+
+    let mut lines = Vec::new();
+    for paragraph in section.text.split('\n') {
+        let mut line: Vec<LineGlyph> = Vec::new();
+        let mut x = 0.0;
+        let mut previous = None;
+        // Where the line can be wrapped: just after the last whitespace
+        let mut break_at = None;
+        for c in paragraph.chars().filter(|c| !c.is_control()) {
+            let glyph = font.glyph(c).scaled(scale);
+            let id = glyph.id();
+            if let Some(previous) = previous {
+                x += font.pair_kerning(scale, previous, id);
+            }
+            let advance = glyph.h_metrics().advance_width;
+            let whitespace = c.is_whitespace();
+
+            let overflows = section
+                .max_width
+                .is_some_and(|max_width| x + advance > max_width);
+            if overflows && !whitespace && !line.is_empty() {
+                // Carry the word being written over to the next line. Words longer than
+                // a line are broken wherever they overflow.
+                let rest = line.split_off(break_at.unwrap_or(line.len()));
+                lines.push(line);
+                let shift = rest.first().map_or(x, |glyph| glyph.x);
+                line = rest
+                    .into_iter()
+                    .map(|glyph| LineGlyph {
+                        x: glyph.x - shift,
+                        ..glyph
+                    })
+                    .collect();
+                x -= shift;
+                break_at = None;
+            }
+
+            line.push(LineGlyph {
+                id,
+                x,
+                advance,
+                whitespace,
+            });
+            x += advance;
+            if whitespace {
+                break_at = Some(line.len());
+            }
+            previous = Some(id);
+        }
+        lines.push(line);
+    }
+
+    let box_width = section.max_width.unwrap_or(0.0);
+    let mut layout = Layout {
+        glyphs: Vec::new(),
+        width: 0.0,
+        height: lines.len() as f32 * line_height,
+        lines: lines.len(),
+    };
+    for (index, line) in lines.iter().enumerate() {
+        let width = line_width(line);
+        layout.width = layout.width.max(width);
+        let left = section.position[0]
+            + match section.align {
+                Align::Left => 0.0,
+                Align::Center => (box_width - width) / 2.0,
+                Align::Right => box_width - width,
+            };
+        let baseline = section.position[1] + v_metrics.ascent + index as f32 * line_height;
+        layout.glyphs.extend(
+            line.iter()
+                .filter(|glyph| !glyph.whitespace)
+                .map(|glyph| (glyph.id, [left + glyph.x, baseline])),
+        );
+    }
+    layout
+}
+
+/// Where a glyph is in its atlas.
+#[derive(Copy, Clone, Debug)]
+struct AtlasGlyph {
+    /// Top left and bottom right in texture coordinates.
+    uv: [f32; 4],
+    /// From the pen position to the top left corner, in rasterized pixels.
+    offset: [f32; 2],
+    size: [f32; 2],
+}
+
+/// Glyphs packed into rows of a single channel texture as they are first drawn.
+struct GlyphAtlas {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    /// Keyed by glyph and rasterized size, None for glyphs with nothing to draw.
+    glyphs: HashMap<(GlyphId, u32), Option<AtlasGlyph>>,
+    cursor: [u32; 2],
+    row_height: u32,
+}
+
+impl GlyphAtlas {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("glyph_atlas_bind_group"),
+        });
+        Self {
+            texture,
+            bind_group,
+            glyphs: HashMap::new(),
+            cursor: [ATLAS_PADDING; 2],
+            row_height: 0,
+        }
+    }
+
+    /// Forgets every glyph so the space can be reused.
+    fn clear(&mut self) {
+        self.glyphs.clear();
+        self.cursor = [ATLAS_PADDING; 2];
+        self.row_height = 0;
+    }
+
+    /// Uploads a `width` x `height` image, returning where it went or None when the
+    /// atlas is full.
+    fn insert(
+        &mut self,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Option<[u32; 2]> {
+        if self.cursor[0] + width + ATLAS_PADDING > ATLAS_SIZE {
+            self.cursor = [
+                ATLAS_PADDING,
+                self.cursor[1] + self.row_height + ATLAS_PADDING,
+            ];
+            self.row_height = 0;
+        }
+        if self.cursor[0] + width + ATLAS_PADDING > ATLAS_SIZE
+            || self.cursor[1] + height + ATLAS_PADDING > ATLAS_SIZE
+        {
+            return None;
+        }
+
+        let position = self.cursor;
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: position[0],
+                    y: position[1],
+                    z: 0,
+                },
+            },
+            pixels,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: width,
+                rows_per_image: height,
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        );
+        self.cursor[0] += width + ATLAS_PADDING;
+        self.row_height = self.row_height.max(height);
+        Some(position)
+    }
+}
+
+/// Coverage of a glyph rasterized `size` pixels high, with the offset of its top left
+/// corner from the pen position. None for glyphs without an outline, like spaces.
+fn rasterize(font: &Font, id: GlyphId, size: f32) -> Option<(Vec<u8>, u32, u32, [i32; 2])> {
+    let glyph = font
+        .font
+        .glyph(id)
+        .scaled(Scale::uniform(size))
+        .positioned(point(0.0, 0.0));
+    let bounds = glyph.pixel_bounding_box()?;
+    let width = bounds.width() as u32;
+    let height = bounds.height() as u32;
+    let mut pixels = vec![0; (width * height) as usize];
+    glyph.draw(|x, y, coverage| {
+        pixels[(y * width + x) as usize] = (coverage * 255.0).round() as u8;
+    });
+    Some((pixels, width, height, [bounds.min.x, bounds.min.y]))
+}
+
+/// Turns glyph coverage into distances from the outline, `spread` pixels out in each
+/// direction. 128 is on the outline, brighter is further inside. The result is
+/// `spread` pixels larger on every side.
+fn distance_field(coverage: &[u8], width: u32, height: u32, spread: u32) -> (Vec<u8>, u32, u32) {
+    let padded_width = (width + spread * 2) as usize;
+    let padded_height = (height + spread * 2) as usize;
+    let mut inside = vec![false; padded_width * padded_height];
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            inside[(y + spread as usize) * padded_width + x + spread as usize] =
+                coverage[y * width as usize + x] >= 128;
+        }
+    }
+
+    let to_inside = squared_distances(&inside, padded_width, padded_height);
+    let outside = inside.iter().map(|inside| !inside).collect::<Vec<_>>();
+    let to_outside = squared_distances(&outside, padded_width, padded_height);
+    let pixels = (0..inside.len())
+        .map(|index| {
+            // Distances are between pixel centers, the outline is half a pixel away
+            let signed = if inside[index] {
+                0.5 - to_outside[index].sqrt()
+            } else {
+                to_inside[index].sqrt() - 0.5
+            };
+            let value = 0.5 - signed as f32 / (2.0 * spread as f32);
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect();
+    (pixels, padded_width as u32, padded_height as u32)
+}
+
+const FAR: f64 = 1e20;
+
+/// Squared distance from every pixel to the nearest one set in `sources`, using
+/// Felzenszwalb and Huttenlocher's exact transform along columns and then rows.
+fn squared_distances(sources: &[bool], width: usize, height: usize) -> Vec<f64> {
+    let mut grid = sources
+        .iter()
+        .map(|source| if *source { 0.0 } else { FAR })
+        .collect::<Vec<f64>>();
+    let longest = width.max(height);
+    let mut input = vec![0.0; longest];
+    let mut output = vec![0.0; longest];
+    let mut parabolas = vec![0; longest];
+    let mut boundaries = vec![0.0; longest + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            input[y] = grid[y * width + x];
+        }
+        transform_1d(
+            &input[..height],
+            &mut output[..height],
+            &mut parabolas,
+            &mut boundaries,
+        );
+        for y in 0..height {
+            grid[y * width + x] = output[y];
+        }
+    }
+    for y in 0..height {
+        let row = y * width..(y + 1) * width;
+        input[..width].copy_from_slice(&grid[row.clone()]);
+        transform_1d(
+            &input[..width],
+            &mut output[..width],
+            &mut parabolas,
+            &mut boundaries,
+        );
+        grid[row].copy_from_slice(&output[..width]);
+    }
+    grid
+}
+
+/// The lower envelope of parabolas rooted at each sample of `input`.
+fn transform_1d(
+    input: &[f64],
+    output: &mut [f64],
+    parabolas: &mut [usize],
+    boundaries: &mut [f64],
+) {
+    let intersection = |q: usize, p: usize| {
+        ((input[q] + (q * q) as f64) - (input[p] + (p * p) as f64)) / (2 * q - 2 * p) as f64
+    };
+
+    let mut k = 0;
+    parabolas[0] = 0;
+    boundaries[0] = -FAR;
+    boundaries[1] = FAR;
+    for q in 1..input.len() {
+        let mut s = intersection(q, parabolas[k]);
+        while s <= boundaries[k] {
+            k -= 1;
+            s = intersection(q, parabolas[k]);
+        }
+        k += 1;
+        parabolas[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = FAR;
+    }
+
+    k = 0;
+    for (q, output) in output.iter_mut().enumerate() {
+        while boundaries[k + 1] < q as f64 {
+            k += 1;
+        }
+        let distance = q as f64 - parabolas[k] as f64;
+        *output = distance * distance + input[parabolas[k]];
+    }
+}
+
+/// A glyph waiting for `prepare`.
+#[derive(Copy, Clone, Debug)]
+struct QueuedGlyph {
+    id: GlyphId,
+    position: [f32; 2],
+    size: f32,
+    color: [f32; 4],
+    mode: GlyphMode,
+}
+
+/// Draws text in one font. Sections queued with `queue` during a frame are rasterized
+/// into the glyph atlases as needed and written into one vertex buffer by `prepare`.
+pub struct TextRenderer {
+    font: Font,
+    /// One per `GlyphMode`.
+    atlases: Vec<GlyphAtlas>,
+    pipelines: Vec<wgpu::RenderPipeline>,
+    projection_buffer: wgpu::Buffer,
+    projection_bind_group: wgpu::BindGroup,
+
+    queued: Vec<QueuedGlyph>,
+    /// Capacity of the buffers in glyphs, they grow as needed.
+    capacity: usize,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    /// Glyph ranges from the last `prepare`.
+    batches: Vec<(GlyphMode, Range<u32>)>,
+}
+
+impl TextRenderer {
+    pub fn new(device: &wgpu::Device, color_format: wgpu::TextureFormat, font: Font) -> Self {
+        let texture_bind_group_layout = crate::texture::Texture::bind_group_layout(device);
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Glyph Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let atlases = vec![
+            GlyphAtlas::new(
+                device,
+                &texture_bind_group_layout,
+                &sampler,
+                "Bitmap Glyph Atlas",
+            ),
+            GlyphAtlas::new(
+                device,
+                &texture_bind_group_layout,
+                &sampler,
+                "SDF Glyph Atlas",
+            ),
+        ];
+
+        let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+        let projection_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Projection Buffer"),
+            contents: bytemuck::cast_slice(&[identity]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let projection_bind_group_layout = sprite::projection_bind_group_layout(device);
+        let projection_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &projection_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: projection_buffer.as_entire_binding(),
+            }],
+            label: Some("text_projection_bind_group"),
+        });
+        let bitmap_module =
+            device.create_shader_module(&wgpu::include_spirv!("text_bitmap.frag.spv"));
+        let sdf_module = device.create_shader_module(&wgpu::include_spirv!("text_sdf.frag.spv"));
+        let pipelines = [bitmap_module, sdf_module]
+            .iter()
+            .map(|fs_module| {
+                sprite::create_pipeline(
+                    device,
+                    color_format,
+                    &texture_bind_group_layout,
+                    &projection_bind_group_layout,
+                    fs_module,
+                )
+            })
+            .collect();
+
+        let capacity = 256;
+        let (vertex_buffer, index_buffer) = Self::create_buffers(device, capacity);
+        Self {
+            font,
+            atlases,
+            pipelines,
+            projection_buffer,
+            projection_bind_group,
+            queued: Vec::new(),
+            capacity,
+            vertex_buffer,
+            index_buffer,
+            batches: Vec::new(),
+        }
+    }
+
+    fn create_buffers(device: &wgpu::Device, capacity: usize) -> (wgpu::Buffer, wgpu::Buffer) {
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Vertex Buffer"),
+            size: (capacity * 4 * std::mem::size_of::<SpriteVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let indices = (0..capacity as u32)
+            .flat_map(|quad| {
+                let first = quad * 4;
+                vec![first, first + 1, first + 2, first, first + 2, first + 3]
+            })
+            .collect::<Vec<u32>>();
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        (vertex_buffer, index_buffer)
+    }
+
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    /// Usually `sprite::screen_projection`, so sections are placed in pixels.
+    pub fn set_projection(&self, queue: &wgpu::Queue, projection: Matrix4<f32>) {
+        let projection: [[f32; 4]; 4] = projection.into();
+        queue.write_buffer(
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(&[projection]),
+        );
+    }
+
+    /// The size `section` takes up, without drawing it.
+    pub fn measure(&self, section: &Section) -> [f32; 2] {
+        let layout = layout(&self.font, section);
+        [layout.width, layout.height]
+    }
+
+    /// Lays out `section` for the next `prepare`.
+    pub fn queue(&mut self, section: &Section) {
+        let layout = layout(&self.font, section);
+        self.queued
+            .extend(layout.glyphs.iter().map(|(id, position)| QueuedGlyph {
+                id: *id,
+                position: *position,
+                size: section.size,
+                color: section.color,
+                mode: section.mode,
+            }));
+    }
+
+    /// Rasterizes new glyphs and uploads the queued text, then clears the queue.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        // Draw each mode with one call
+        self.queued.sort_by_key(|glyph| glyph.mode.index());
+        let vertices = match self.build_vertices(queue, false) {
+            Some(vertices) => vertices,
+            None => {
+                // Start the atlases over with just the glyphs in use, and leave out any
+                // that still don't fit
+                for atlas in &mut self.atlases {
+                    atlas.clear();
+                }
+                self.build_vertices(queue, true).unwrap_or_default()
+            }
+        };
+
+        let glyphs = vertices.len() / 4;
+        if glyphs > self.capacity {
+            self.capacity = glyphs.next_power_of_two();
+            let (vertex_buffer, index_buffer) = Self::create_buffers(device, self.capacity);
+            self.vertex_buffer = vertex_buffer;
+            self.index_buffer = index_buffer;
+        }
+        if !vertices.is_empty() {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        }
+        self.queued.clear();
+    }
+
+    /// Returns None if an atlas fills up, unless `skip_missing` is set.
+    fn build_vertices(
+        &mut self,
+        queue: &wgpu::Queue,
+        skip_missing: bool,
+    ) -> Option<Vec<SpriteVertex>> {
+        let mut vertices = Vec::with_capacity(self.queued.len() * 4);
+        self.batches.clear();
+        for glyph in &self.queued {
+            // Bitmap glyphs are rasterized at whole pixel sizes and drawn unscaled
+            let (raster_size, key) = match glyph.mode {
+                GlyphMode::Bitmap => {
+                    let size = glyph.size.round().max(1.0);
+                    (size, size as u32)
+                }
+                GlyphMode::Sdf => (SDF_SIZE, 0),
+            };
+            let atlas = &mut self.atlases[glyph.mode.index()];
+            let entry = match atlas.glyphs.get(&(glyph.id, key)) {
+                Some(entry) => *entry,
+                None => {
+                    let entry = match rasterize(&self.font, glyph.id, raster_size) {
+                        Some((coverage, width, height, offset)) => {
+                            let (pixels, width, height, offset) = match glyph.mode {
+                                GlyphMode::Bitmap => (coverage, width, height, offset),
+                                GlyphMode::Sdf => {
+                                    let (pixels, width, height) =
+                                        distance_field(&coverage, width, height, SDF_SPREAD);
+                                    let spread = SDF_SPREAD as i32;
+                                    (
+                                        pixels,
+                                        width,
+                                        height,
+                                        [offset[0] - spread, offset[1] - spread],
+                                    )
+                                }
+                            };
+                            match atlas.insert(queue, width, height, &pixels) {
+                                Some([x, y]) => Some(AtlasGlyph {
+                                    uv: [
+                                        x as f32 / ATLAS_SIZE as f32,
+                                        y as f32 / ATLAS_SIZE as f32,
+                                        (x + width) as f32 / ATLAS_SIZE as f32,
+                                        (y + height) as f32 / ATLAS_SIZE as f32,
+                                    ],
+                                    offset: [offset[0] as f32, offset[1] as f32],
+                                    size: [width as f32, height as f32],
+                                }),
+                                None if skip_missing => continue,
+                                None => return None,
+                            }
+                        }
+                        None => None,
+                    };
+                    atlas.glyphs.insert((glyph.id, key), entry);
+                    entry
+                }
+            };
+            let entry = match entry {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            let (origin, scale) = match glyph.mode {
+                // Whole pixels keep bitmap glyphs crisp
+                GlyphMode::Bitmap => ([glyph.position[0].round(), glyph.position[1].round()], 1.0),
+                GlyphMode::Sdf => (glyph.position, glyph.size / SDF_SIZE),
+            };
+            let left = origin[0] + entry.offset[0] * scale;
+            let top = origin[1] + entry.offset[1] * scale;
+            let right = left + entry.size[0] * scale;
+            let bottom = top + entry.size[1] * scale;
+            let [u0, v0, u1, v1] = entry.uv;
+            let vertex = |x: f32, y: f32, u: f32, v: f32| SpriteVertex {
+                position: [x, y, 0.0],
+                tex_coords: [u, v],
+                color: glyph.color,
+            };
+            let index = (vertices.len() / 4) as u32;
+            vertices.extend_from_slice(&[
+                vertex(left, top, u0, v0),
+                vertex(left, bottom, u0, v1),
+                vertex(right, bottom, u1, v1),
+                vertex(right, top, u1, v0),
+            ]);
+            match self.batches.last_mut() {
+                Some((mode, range)) if *mode == glyph.mode => range.end = index + 1,
+                _ => self.batches.push((glyph.mode, index..index + 1)),
+            }
+        }
+        Some(vertices)
+    }
+
+    /// Draws what the last `prepare` uploaded.
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.batches.is_empty() {
+            return;
+        }
+        render_pass.set_bind_group(1, &self.projection_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for (mode, glyphs) in &self.batches {
+            let index = mode.index();
+            render_pass.set_pipeline(&self.pipelines[index]);
+            render_pass.set_bind_group(0, &self.atlases[index].bind_group, &[]);
+            render_pass.draw_indexed(glyphs.start * 6..glyphs.end * 6, 0, 0..1);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec4 v_color;
+layout(location=0) out vec4 f_color;
+
+// Glyph coverage in the red channel
+layout(set = 0, binding = 0) uniform texture2D t_glyphs;
+layout(set = 0, binding = 1) uniform sampler s_glyphs;
+
+void main() {
+    float coverage = texture(sampler2D(t_glyphs, s_glyphs), v_tex_coords).r;
+    f_color = vec4(v_color.rgb, v_color.a * coverage);
+}
@@ -0,0 +1,17 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec4 v_color;
+layout(location=0) out vec4 f_color;
+
+// Distance to the glyph's edge in the red channel, 0.5 on the edge and larger inside
+layout(set = 0, binding = 0) uniform texture2D t_glyphs;
+layout(set = 0, binding = 1) uniform sampler s_glyphs;
+
+void main() {
+    float distance = texture(sampler2D(t_glyphs, s_glyphs), v_tex_coords).r;
+    // Blend over about a screen pixel whatever size the text is drawn at
+    float width = max(fwidth(distance) * 0.5, 0.001);
+    float alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+    f_color = vec4(v_color.rgb, v_color.a * alpha);
+}
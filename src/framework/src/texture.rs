@@ -0,0 +1,584 @@
+use std::iter;
+
+use anyhow::*;
+use image::GenericImageView;
+
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+}
+
+impl Texture {
+    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    pub const CUBE_HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        label: &str,
+    ) -> Self {
+        Self::create_multisampled_depth_texture(device, sc_desc, 1, label)
+    }
+
+    /// Depth texture for pipelines drawing with `sample_count` samples per pixel.
+    pub fn create_multisampled_depth_texture(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        sample_count: u32,
+        label: &str,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: sc_desc.width,
+            height: sc_desc.height,
+            depth: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+        is_normal_map: bool,
+    ) -> Result<Self> {
+        let img = image::load_from_memory(bytes)?;
+        Self::from_image(device, queue, &img, Some(label), is_normal_map)
+    }
+
+    /// `is_normal_map` picks a linear format instead of sRGB. Pass true for any texture
+    /// that holds data rather than color, such as normal or metallic-roughness maps.
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        is_normal_map: bool,
+    ) -> Result<Self> {
+        let rgba = img.to_rgba8();
+        let dimensions = img.dimensions();
+
+        let size = wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // Normal maps store vectors rather than colors, so they must not be gamma decoded.
+            format: if is_normal_map {
+                wgpu::TextureFormat::Rgba8Unorm
+            } else {
+                wgpu::TextureFormat::Rgba8UnormSrgb
+            },
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &rgba,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * dimensions.0,
+                rows_per_image: dimensions.1,
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+        })
+    }
+
+    /// A 1x1 texture, handy as a stand-in for a missing map.
+    pub fn from_color(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color: [u8; 4],
+        label: &str,
+        is_normal_map: bool,
+    ) -> Self {
+        let img =
+            image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba(color)));
+        Self::from_image(device, queue, &img, Some(label), is_normal_map).unwrap()
+    }
+
+    /// A cubemap from six square faces given in wgpu's layer order: +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cube_from_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[image::DynamicImage],
+        label: &str,
+    ) -> Result<Self> {
+        if faces.len() != 6 {
+            bail!("A cubemap needs 6 faces, got {}", faces.len());
+        }
+        let (face_size, height) = faces[0].dimensions();
+        if face_size != height {
+            bail!("Cubemap faces must be square, got {}x{}", face_size, height);
+        }
+
+        let mut data = Vec::with_capacity((face_size * face_size * 4 * 6) as usize);
+        for face in faces {
+            if face.dimensions() != (face_size, face_size) {
+                bail!("Cubemap faces must all be the same size");
+            }
+            data.extend_from_slice(&face.to_rgba8());
+        }
+
+        Ok(Self::create_cube(
+            device,
+            queue,
+            face_size,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            4,
+            &data,
+            label,
+        ))
+    }
+
+    /// Decodes a Radiance `.hdr` panorama and projects it onto a cubemap.
+    pub fn cube_from_hdr_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        face_size: u32,
+        label: &str,
+    ) -> Result<Self> {
+        let decoder = image::codecs::hdr::HdrDecoder::new(bytes)?;
+        let meta = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .into_iter()
+            .map(|pixel| pixel.0)
+            .collect::<Vec<_>>();
+        Ok(Self::cube_from_equirectangular(
+            device,
+            queue,
+            meta.width,
+            meta.height,
+            &pixels,
+            face_size,
+            label,
+        ))
+    }
+
+    /// Projects a linear, equirectangular (latitude/longitude) panorama onto the six faces
+    /// of a `CUBE_HDR_FORMAT` cubemap, so values above 1.0 survive.
+    pub fn cube_from_equirectangular(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        pixels: &[[f32; 3]],
+        face_size: u32,
+        label: &str,
+    ) -> Self {
+        let sample = |x: i64, y: i64| {
+            // Wrap around horizontally, clamp at the poles.
+            let x = x.rem_euclid(width as i64) as usize;
+            let y = y.max(0).min(height as i64 - 1) as usize;
+            pixels[y * width as usize + x]
+        };
+
+        let mut data = Vec::with_capacity((face_size * face_size * 8 * 6) as usize);
+        for face in 0..6 {
+            for y in 0..face_size {
+                for x in 0..face_size {
+                    let u = (x as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                    let v = (y as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                    let [dx, dy, dz] = cube_face_direction(face, u, v);
+                    let length = (dx * dx + dy * dy + dz * dz).sqrt();
+
+                    let longitude = dz.atan2(dx);
+                    let latitude = (dy / length).asin();
+                    let px = (0.5 + longitude / (2.0 * std::f32::consts::PI)) * width as f32 - 0.5;
+                    let py = (0.5 - latitude / std::f32::consts::PI) * height as f32 - 0.5;
+
+                    // Bilinear filtering
+                    let (x0, y0) = (px.floor() as i64, py.floor() as i64);
+                    let (fx, fy) = (px - px.floor(), py - py.floor());
+                    let mut color = [0.0; 3];
+                    for (sx, sy, weight) in [
+                        (x0, y0, (1.0 - fx) * (1.0 - fy)),
+                        (x0 + 1, y0, fx * (1.0 - fy)),
+                        (x0, y0 + 1, (1.0 - fx) * fy),
+                        (x0 + 1, y0 + 1, fx * fy),
+                    ]
+                    .iter()
+                    {
+                        let texel = sample(*sx, *sy);
+                        for c in 0..3 {
+                            color[c] += texel[c] * weight;
+                        }
+                    }
+
+                    for channel in color.iter().chain(iter::once(&1.0)) {
+                        data.extend_from_slice(&f32_to_f16(*channel).to_le_bytes());
+                    }
+                }
+            }
+        }
+
+        Self::create_cube(
+            device,
+            queue,
+            face_size,
+            Self::CUBE_HDR_FORMAT,
+            8,
+            &data,
+            label,
+        )
+    }
+
+    fn create_cube(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        face_size: u32,
+        format: wgpu::TextureFormat,
+        bytes_per_pixel: u32,
+        data: &[u8],
+        label: &str,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: face_size,
+            height: face_size,
+            // Cubemaps are 2D textures with 6 array layers
+            depth: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            data,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: bytes_per_pixel * face_size,
+                rows_per_image: face_size,
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(label),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Same as `bind_group_layout` but for a cubemap view.
+    pub fn cube_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                        filtering: true,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("cube_texture_bind_group_layout"),
+        })
+    }
+
+    /// Layout for a single sampled 2D texture at binding 0 and its sampler at binding 1,
+    /// the same shape tutorial5 uses for `t_diffuse`/`s_diffuse`.
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                        filtering: true,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("texture_bind_group_layout"),
+        })
+    }
+
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        label: Option<&str>,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label,
+        })
+    }
+}
+
+/// Direction through texel (u, v) of a cube face, both in -1..1 with v pointing down,
+/// following the face orientations wgpu and Vulkan use.
+fn cube_face_direction(face: u32, u: f32, v: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    }
+}
+
+/// Converts to IEEE half precision, rounding toward zero. Good enough for texture data.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity or NaN
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        // Too large, clamp to infinity
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // Subnormal
+        let mantissa = mantissa | 0x80_0000;
+        sign | (mantissa >> (14 - exponent)) as u16
+    } else {
+        sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16
+    }
+}
+
+/// Copies a 2D texture back to the CPU, blocking until the GPU is done with it. Linear
+/// float textures are encoded to sRGB so the result can be saved like any other image.
+/// The texture needs `COPY_SRC` usage, and any commands writing to it must already have
+/// been submitted.
+pub async fn read_to_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> Result<image::RgbaImage> {
+    let bytes_per_pixel = match format {
+        wgpu::TextureFormat::Rgba8Unorm
+        | wgpu::TextureFormat::Rgba8UnormSrgb
+        | wgpu::TextureFormat::Bgra8Unorm
+        | wgpu::TextureFormat::Bgra8UnormSrgb => 4,
+        wgpu::TextureFormat::Rgba16Float => 8,
+        _ => bail!("Can't read back textures in {:?}", format),
+    };
+    // Rows in the buffer have to be aligned, the padding is dropped when unpacking
+    let unpadded_bytes_per_row = width * bytes_per_pixel;
+    let bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TextureCopyView {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::BufferCopyView {
+            buffer: &buffer,
+            layout: wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row,
+                rows_per_image: height,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+    );
+    queue.submit(iter::once(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let mapping = slice.map_async(wgpu::MapMode::Read);
+    device.poll(wgpu::Maintain::Wait);
+    mapping
+        .await
+        .map_err(|_| anyhow!("Couldn't map the readback buffer"))?;
+
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(bytes_per_row as usize) {
+            let row = &row[..unpadded_bytes_per_row as usize];
+            match format {
+                wgpu::TextureFormat::Rgba16Float => {
+                    for texel in row.chunks(8) {
+                        let channel =
+                            |i: usize| f16_to_f32(u16::from_le_bytes([texel[i], texel[i + 1]]));
+                        pixels.extend_from_slice(&[
+                            linear_to_srgb(channel(0)),
+                            linear_to_srgb(channel(2)),
+                            linear_to_srgb(channel(4)),
+                            (channel(6).clamp(0.0, 1.0) * 255.0).round() as u8,
+                        ]);
+                    }
+                }
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+                    for texel in row.chunks(4) {
+                        pixels.extend_from_slice(&[texel[2], texel[1], texel[0], texel[3]]);
+                    }
+                }
+                _ => pixels.extend_from_slice(row),
+            }
+        }
+    }
+    buffer.unmap();
+
+    image::RgbaImage::from_raw(width, height, pixels)
+        .context("Readback didn't match the texture size")
+}
+
+/// Encodes a linear color channel with the sRGB transfer function.
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let encoded = if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+/// Converts from IEEE half precision.
+fn f16_to_f32(value: u16) -> f32 {
+    let sign = if value & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((value >> 10) & 0x1f) as i32;
+    let mantissa = (value & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
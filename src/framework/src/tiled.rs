@@ -0,0 +1,936 @@
+//! Reads maps made with the Tiled editor (https://www.mapeditor.org), saved either as
+//! .tmx or as JSON, along with external .tsx and JSON tilesets. Only orthogonal, finite
+//! maps are supported. Group layers are flattened into the layers they contain.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::*;
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+use crate::json::{self, Fields, Value};
+use crate::xml::{self, Element};
+
+/// The top bits of a tile id say how the tile is flipped, the rest is its gid.
+pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// Also covers the bit hexagonal maps use for 120 degree rotation.
+const FLAGS: u32 = 0xf000_0000;
+
+/// A tile id as stored in a layer, with its flip flags. Gid 0 means no tile.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tile(pub u32);
+
+impl Tile {
+    pub fn gid(self) -> u32 {
+        self.0 & !FLAGS
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.gid() == 0
+    }
+
+    pub fn flipped_horizontally(self) -> bool {
+        self.0 & FLIPPED_HORIZONTALLY != 0
+    }
+
+    pub fn flipped_vertically(self) -> bool {
+        self.0 & FLIPPED_VERTICALLY != 0
+    }
+
+    /// Swaps the tile's x and y axes, which with the other flips gives 90 degree turns.
+    /// Tiled applies it before the horizontal and vertical flips.
+    pub fn flipped_diagonally(self) -> bool {
+        self.0 & FLIPPED_DIAGONALLY != 0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnimationFrame {
+    /// Id of the tile to show, local to the tileset.
+    pub tile: u32,
+    pub duration: Duration,
+}
+
+/// A single image cut into tiles.
+#[derive(Clone, Debug)]
+pub struct Tileset {
+    /// Gid of the tileset's first tile, the rest follow in order.
+    pub first_gid: u32,
+    pub name: String,
+    /// Resolved against the file the tileset came from.
+    pub image: PathBuf,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Pixels between neighbouring tiles.
+    pub spacing: u32,
+    /// Pixels around the edge of the image.
+    pub margin: u32,
+    pub tile_count: u32,
+    pub columns: u32,
+    /// Frames for the tiles that are animated, by local id.
+    pub animations: HashMap<u32, Vec<AnimationFrame>>,
+}
+
+impl Tileset {
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.tile_count
+    }
+
+    /// Top left corner of a tile in the image, in pixels.
+    pub fn tile_position(&self, local_id: u32) -> (u32, u32) {
+        let columns = self.columns.max(1);
+        (
+            self.margin + local_id % columns * (self.tile_width + self.spacing),
+            self.margin + local_id / columns * (self.tile_height + self.spacing),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Row by row from the top left.
+    pub tiles: Vec<Tile>,
+    pub opacity: f32,
+    pub visible: bool,
+    /// In pixels.
+    pub offset: [f32; 2],
+}
+
+impl TileLayer {
+    pub fn tile(&self, x: u32, y: u32) -> Tile {
+        if x < self.width && y < self.height {
+            self.tiles[y as usize * self.width as usize + x as usize]
+        } else {
+            Tile::default()
+        }
+    }
+}
+
+/// Anything placed in an object layer, such as a spawn point, a trigger area or a tile
+/// placed freely.
+#[derive(Clone, Debug)]
+pub struct Object {
+    pub id: u32,
+    pub name: String,
+    /// Called "type", or "class" since Tiled 1.9.
+    pub kind: String,
+    /// In pixels. Tile objects are placed by their bottom left corner, everything else by
+    /// its top left.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Degrees clockwise around (x, y).
+    pub rotation: f32,
+    /// Set for objects that show a tile.
+    pub tile: Option<Tile>,
+    pub visible: bool,
+    /// Points relative to (x, y) for polygons and polylines.
+    pub points: Vec<[f32; 2]>,
+    /// Custom properties, with values as Tiled writes them.
+    pub properties: Vec<(String, String)>,
+}
+
+impl Object {
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub objects: Vec<Object>,
+    pub opacity: f32,
+    pub visible: bool,
+    pub offset: [f32; 2],
+}
+
+#[derive(Clone, Debug)]
+pub enum Layer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+impl Layer {
+    pub fn name(&self) -> &str {
+        match self {
+            Layer::Tiles(layer) => &layer.name,
+            Layer::Objects(layer) => &layer.name,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Map {
+    /// In tiles.
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tilesets: Vec<Tileset>,
+    /// Bottom to top.
+    pub layers: Vec<Layer>,
+}
+
+impl Map {
+    /// Loads a .tmx or .json map and the tilesets it refers to.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Couldn't read {:?}", path))?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let map = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") | Some("tmj") => Self::from_json(&text, directory),
+            _ => Self::from_tmx(&text, directory),
+        };
+        map.with_context(|| format!("Couldn't load {:?}", path))
+    }
+
+    /// `directory` is where paths in the map are relative to.
+    pub fn from_tmx(text: &str, directory: &Path) -> Result<Self> {
+        let root = xml::parse(text)?;
+        ensure!(
+            root.name == "map",
+            "Expected a <map>, found <{}>",
+            root.name
+        );
+        check_supported(root.attribute("orientation"), root.attribute("infinite"))?;
+
+        let mut tilesets = Vec::new();
+        for element in root.children("tileset") {
+            let first_gid = element.parse("firstgid")?;
+            let tileset = match element.attribute("source") {
+                Some(source) => Tileset::load(first_gid, &directory.join(source))?,
+                None => Tileset::from_tsx_element(first_gid, element, directory)?,
+            };
+            tilesets.push(tileset);
+        }
+
+        let mut layers = Vec::new();
+        tmx_layers(&root, [0.0, 0.0], 1.0, true, &mut layers)?;
+        Ok(Self {
+            width: root.parse("width")?,
+            height: root.parse("height")?,
+            tile_width: root.parse("tilewidth")?,
+            tile_height: root.parse("tileheight")?,
+            tilesets,
+            layers,
+        })
+    }
+
+    pub fn from_json(text: &str, directory: &Path) -> Result<Self> {
+        let root = json::parse(text)?;
+        check_supported(
+            root.get("orientation").and_then(Value::as_str),
+            root.get("infinite")
+                .and_then(Value::as_bool)
+                .map(|infinite| if infinite { "1" } else { "0" }),
+        )?;
+
+        let mut tilesets = Vec::new();
+        for value in root.field_array("tilesets")? {
+            let first_gid = value.field_u32("firstgid")?;
+            let tileset = match value.get("source").and_then(Value::as_str) {
+                Some(source) => Tileset::load(first_gid, &directory.join(source))?,
+                None => Tileset::from_json_value(first_gid, value, directory)?,
+            };
+            tilesets.push(tileset);
+        }
+
+        let mut layers = Vec::new();
+        json_layers(
+            root.field_array("layers")?,
+            [0.0, 0.0],
+            1.0,
+            true,
+            &mut layers,
+        )?;
+        Ok(Self {
+            width: root.field_u32("width")?,
+            height: root.field_u32("height")?,
+            tile_width: root.field_u32("tilewidth")?,
+            tile_height: root.field_u32("tileheight")?,
+            tilesets,
+            layers,
+        })
+    }
+
+    /// The tileset `gid` belongs to and its id within it.
+    pub fn tileset_for(&self, gid: u32) -> Option<(usize, u32)> {
+        self.tilesets
+            .iter()
+            .position(|tileset| tileset.contains(gid))
+            .map(|index| (index, gid - self.tilesets[index].first_gid))
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name() == name)
+    }
+}
+
+fn check_supported(orientation: Option<&str>, infinite: Option<&str>) -> Result<()> {
+    let orientation = orientation.unwrap_or("orthogonal");
+    ensure!(
+        orientation == "orthogonal",
+        "Only orthogonal maps are supported, not {}",
+        orientation
+    );
+    ensure!(
+        infinite.unwrap_or("0") == "0",
+        "Infinite maps aren't supported, resize the map to make it finite"
+    );
+    Ok(())
+}
+
+impl Tileset {
+    /// Loads an external .tsx or JSON tileset.
+    fn load(first_gid: u32, path: &Path) -> Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Couldn't read {:?}", path))?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let tileset = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") | Some("tsj") => json::parse(&text)
+                .and_then(|value| Self::from_json_value(first_gid, &value, directory)),
+            _ => xml::parse(&text)
+                .and_then(|element| Self::from_tsx_element(first_gid, &element, directory)),
+        };
+        tileset.with_context(|| format!("Couldn't load tileset {:?}", path))
+    }
+
+    fn from_tsx_element(first_gid: u32, element: &Element, directory: &Path) -> Result<Self> {
+        let name = element.attribute("name").unwrap_or_default().to_string();
+        let image = element.child("image").with_context(|| {
+            format!(
+                "Tileset {:?} has no image, image collections aren't supported",
+                name
+            )
+        })?;
+
+        let mut animations = HashMap::new();
+        for tile in element.children("tile") {
+            if let Some(animation) = tile.child("animation") {
+                let frames = animation
+                    .children("frame")
+                    .map(|frame| {
+                        Ok(AnimationFrame {
+                            tile: frame.parse("tileid")?,
+                            duration: Duration::from_millis(frame.parse("duration")?),
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                animations.insert(tile.parse("id")?, frames);
+            }
+        }
+
+        let tile_width = element.parse("tilewidth")?;
+        let image_width = image.parse("width")?;
+        let spacing = element.parse_or("spacing", 0)?;
+        let margin = element.parse_or("margin", 0)?;
+        Ok(Self {
+            first_gid,
+            name,
+            image: directory.join(image.attribute("source").context("<image> has no source")?),
+            image_width,
+            image_height: image.parse("height")?,
+            tile_width,
+            tile_height: element.parse("tileheight")?,
+            spacing,
+            margin,
+            tile_count: element.parse("tilecount")?,
+            columns: match element.parse_or("columns", 0)? {
+                0 => columns(image_width, tile_width, spacing, margin),
+                columns => columns,
+            },
+            animations,
+        })
+    }
+
+    fn from_json_value(first_gid: u32, value: &Value, directory: &Path) -> Result<Self> {
+        let name = value
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let image = value
+            .get("image")
+            .and_then(Value::as_str)
+            .with_context(|| {
+                format!(
+                    "Tileset {:?} has no image, image collections aren't supported",
+                    name
+                )
+            })?;
+
+        let mut animations = HashMap::new();
+        for tile in value
+            .get("tiles")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            if let Some(animation) = tile.get("animation").and_then(Value::as_array) {
+                let frames = animation
+                    .iter()
+                    .map(|frame| {
+                        Ok(AnimationFrame {
+                            tile: frame.field_u32("tileid")?,
+                            duration: Duration::from_millis(frame.field_u32("duration")? as u64),
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                animations.insert(tile.field_u32("id")?, frames);
+            }
+        }
+
+        let optional = |key: &str| -> Result<u32> {
+            match value.get(key) {
+                Some(_) => value.field_u32(key),
+                None => Ok(0),
+            }
+        };
+        let tile_width = value.field_u32("tilewidth")?;
+        let image_width = value.field_u32("imagewidth")?;
+        let spacing = optional("spacing")?;
+        let margin = optional("margin")?;
+        Ok(Self {
+            first_gid,
+            name,
+            image: directory.join(image),
+            image_width,
+            image_height: value.field_u32("imageheight")?,
+            tile_width,
+            tile_height: value.field_u32("tileheight")?,
+            spacing,
+            margin,
+            tile_count: value.field_u32("tilecount")?,
+            columns: match optional("columns")? {
+                0 => columns(image_width, tile_width, spacing, margin),
+                columns => columns,
+            },
+            animations,
+        })
+    }
+}
+
+/// How many tiles fit across an image, for tilesets that don't say.
+fn columns(image_width: u32, tile_width: u32, spacing: u32, margin: u32) -> u32 {
+    (image_width.saturating_sub(margin * 2) + spacing) / (tile_width + spacing).max(1)
+}
+
+fn tmx_layers(
+    parent: &Element,
+    offset: [f32; 2],
+    opacity: f32,
+    visible: bool,
+    layers: &mut Vec<Layer>,
+) -> Result<()> {
+    for element in &parent.elements {
+        let name = element.attribute("name").unwrap_or_default().to_string();
+        let offset = [
+            offset[0] + element.parse_or("offsetx", 0.0)?,
+            offset[1] + element.parse_or("offsety", 0.0)?,
+        ];
+        let opacity = opacity * element.parse_or("opacity", 1.0)?;
+        let visible = visible && element.parse_or("visible", 1)? != 0;
+        let layer = match element.name.as_str() {
+            "layer" => {
+                let width = element.parse("width")?;
+                let height = element.parse("height")?;
+                let data = element.child("data").context("Layer has no <data>")?;
+                let tiles = match data.attribute("encoding") {
+                    Some("csv") => data
+                        .text
+                        .split(',')
+                        .map(|gid| {
+                            gid.trim()
+                                .parse()
+                                .map(Tile)
+                                .with_context(|| format!("Invalid tile {:?}", gid.trim()))
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                    Some("base64") => decode_tiles(&data.text, data.attribute("compression"))?,
+                    Some(encoding) => bail!("Unknown tile encoding {:?}", encoding),
+                    None => data
+                        .children("tile")
+                        .map(|tile| Ok(Tile(tile.parse_or("gid", 0)?)))
+                        .collect::<Result<Vec<_>>>()?,
+                };
+                Layer::Tiles(tile_layer(
+                    name, width, height, tiles, opacity, visible, offset,
+                )?)
+            }
+            "objectgroup" => {
+                let objects = element
+                    .children("object")
+                    .map(tmx_object)
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| format!("Invalid object in layer {:?}", name))?;
+                Layer::Objects(ObjectLayer {
+                    name,
+                    objects,
+                    opacity,
+                    visible,
+                    offset,
+                })
+            }
+            "group" => {
+                tmx_layers(element, offset, opacity, visible, layers)?;
+                continue;
+            }
+            // Image layers and everything that isn't a layer
+            _ => continue,
+        };
+        layers.push(layer);
+    }
+    Ok(())
+}
+
+fn tmx_object(element: &Element) -> Result<Object> {
+    let points = element
+        .child("polygon")
+        .or_else(|| element.child("polyline"))
+        .and_then(|shape| shape.attribute("points"))
+        .map(|points| {
+            points
+                .split_whitespace()
+                .map(|point| {
+                    let mut coordinates = point.split(',').map(f32::from_str);
+                    match (coordinates.next(), coordinates.next()) {
+                        (Some(Ok(x)), Some(Ok(y))) => Ok([x, y]),
+                        _ => bail!("Invalid point {:?}", point),
+                    }
+                })
+                .collect::<Result<Vec<_>>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+    let properties = element
+        .child("properties")
+        .map(|properties| {
+            properties
+                .children("property")
+                .map(|property| {
+                    (
+                        property.attribute("name").unwrap_or_default().to_string(),
+                        property
+                            .attribute("value")
+                            .unwrap_or_else(|| property.text.trim())
+                            .to_string(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(Object {
+        id: element.parse_or("id", 0)?,
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        kind: element
+            .attribute("type")
+            .or_else(|| element.attribute("class"))
+            .unwrap_or_default()
+            .to_string(),
+        x: element.parse_or("x", 0.0)?,
+        y: element.parse_or("y", 0.0)?,
+        width: element.parse_or("width", 0.0)?,
+        height: element.parse_or("height", 0.0)?,
+        rotation: element.parse_or("rotation", 0.0)?,
+        tile: element
+            .attribute("gid")
+            .map(|_| element.parse("gid").map(Tile))
+            .transpose()?,
+        visible: element.parse_or("visible", 1)? != 0,
+        points,
+        properties,
+    })
+}
+
+fn json_layers(
+    values: &[Value],
+    offset: [f32; 2],
+    opacity: f32,
+    visible: bool,
+    layers: &mut Vec<Layer>,
+) -> Result<()> {
+    for value in values {
+        let number =
+            |key: &str, default: f64| value.get(key).and_then(Value::as_f64).unwrap_or(default);
+        let name = value
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let offset = [
+            offset[0] + number("offsetx", 0.0) as f32,
+            offset[1] + number("offsety", 0.0) as f32,
+        ];
+        let opacity = opacity * number("opacity", 1.0) as f32;
+        let visible = visible
+            && value
+                .get("visible")
+                .and_then(Value::as_bool)
+                .unwrap_or(true);
+        let layer = match value.field_str("type")? {
+            "tilelayer" => {
+                let width = value.field_u32("width")?;
+                let height = value.field_u32("height")?;
+                let tiles = match value.field("data")? {
+                    Value::String(data) => {
+                        decode_tiles(data, value.get("compression").and_then(Value::as_str))?
+                    }
+                    Value::Array(gids) => gids
+                        .iter()
+                        .map(|gid| {
+                            gid.as_u64()
+                                .filter(|gid| *gid <= u32::MAX as u64)
+                                .map(|gid| Tile(gid as u32))
+                                .context("Tiles should be whole numbers")
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                    _ => bail!("Layer {:?} has invalid data", name),
+                };
+                Layer::Tiles(tile_layer(
+                    name, width, height, tiles, opacity, visible, offset,
+                )?)
+            }
+            "objectgroup" => {
+                let objects = value
+                    .field_array("objects")?
+                    .iter()
+                    .map(json_object)
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| format!("Invalid object in layer {:?}", name))?;
+                Layer::Objects(ObjectLayer {
+                    name,
+                    objects,
+                    opacity,
+                    visible,
+                    offset,
+                })
+            }
+            "group" => {
+                json_layers(
+                    value.field_array("layers")?,
+                    offset,
+                    opacity,
+                    visible,
+                    layers,
+                )?;
+                continue;
+            }
+            _ => continue,
+        };
+        layers.push(layer);
+    }
+    Ok(())
+}
+
+fn json_object(value: &Value) -> Result<Object> {
+    let number = |key: &str| value.get(key).and_then(Value::as_f64).unwrap_or(0.0) as f32;
+    let string = |key: &str| value.get(key).and_then(Value::as_str);
+    let points = value
+        .get("polygon")
+        .or_else(|| value.get("polyline"))
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|point| Ok([point.field_f64("x")? as f32, point.field_f64("y")? as f32]))
+        .collect::<Result<Vec<_>>>()?;
+    let properties = value
+        .get("properties")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|property| {
+            let value = match property.field("value")? {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            Ok((property.field_str("name")?.to_string(), value))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Object {
+        id: number("id") as u32,
+        name: string("name").unwrap_or_default().to_string(),
+        kind: string("type")
+            .or_else(|| string("class"))
+            .unwrap_or_default()
+            .to_string(),
+        x: number("x"),
+        y: number("y"),
+        width: number("width"),
+        height: number("height"),
+        rotation: number("rotation"),
+        tile: value
+            .get("gid")
+            .map(|_| value.field_u32("gid").map(Tile))
+            .transpose()?,
+        visible: value
+            .get("visible")
+            .and_then(Value::as_bool)
+            .unwrap_or(true),
+        points,
+        properties,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn tile_layer(
+    name: String,
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+    opacity: f32,
+    visible: bool,
+    offset: [f32; 2],
+) -> Result<TileLayer> {
+    let count = (width as usize)
+        .checked_mul(height as usize)
+        .with_context(|| format!("Layer {:?} is too large", name))?;
+    ensure!(
+        tiles.len() == count,
+        "Layer {:?} should have {} tiles but has {}",
+        name,
+        count,
+        tiles.len()
+    );
+    Ok(TileLayer {
+        name,
+        width,
+        height,
+        tiles,
+        opacity,
+        visible,
+        offset,
+    })
+}
+
+/// Tiles stored as base64 little endian u32s, optionally compressed.
+fn decode_tiles(text: &str, compression: Option<&str>) -> Result<Vec<Tile>> {
+    // TMX files wrap and indent the data
+    let text = text
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect::<String>();
+    let bytes = base64::decode(&text).context("Invalid base64 tile data")?;
+    let mut decompressed = Vec::new();
+    let bytes = match compression.unwrap_or("") {
+        "" => bytes,
+        "zlib" => {
+            ZlibDecoder::new(&bytes[..])
+                .read_to_end(&mut decompressed)
+                .context("Couldn't decompress tiles")?;
+            decompressed
+        }
+        "gzip" => {
+            GzDecoder::new(&bytes[..])
+                .read_to_end(&mut decompressed)
+                .context("Couldn't decompress tiles")?;
+            decompressed
+        }
+        compression => bail!("{} compressed tiles aren't supported", compression),
+    };
+    let tiles = bytes.chunks_exact(4);
+    ensure!(
+        tiles.remainder().is_empty(),
+        "Tile data isn't a whole number of tiles"
+    );
+    Ok(tiles
+        .map(|tile| Tile(u32::from_le_bytes([tile[0], tile[1], tile[2], tile[3]])))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::{Compression, GzBuilder};
+
+    use super::*;
+
+    const GIDS: [u32; 6] = [1, 2, 3, 0, FLIPPED_HORIZONTALLY | 1, 5];
+
+    fn tmx(data: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.4" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16" tilecount="8" columns="4">
+  <image source="tiles.png" width="64" height="32"/>
+ </tileset>
+ <layer id="1" name="ground" width="3" height="2">
+  {}
+ </layer>
+</map>"#,
+            data
+        )
+    }
+
+    fn json(data: &str, compression: &str) -> String {
+        format!(
+            r#"{{
+  "orientation": "orthogonal", "infinite": false,
+  "width": 3, "height": 2, "tilewidth": 16, "tileheight": 16,
+  "tilesets": [{{
+    "firstgid": 1, "name": "tiles", "image": "tiles.png", "imagewidth": 64,
+    "imageheight": 32, "tilewidth": 16, "tileheight": 16, "tilecount": 8
+  }}],
+  "layers": [{{
+    "type": "tilelayer", "name": "ground", "width": 3, "height": 2,
+    "data": {}, "compression": "{}"
+  }}]
+}}"#,
+            data, compression
+        )
+    }
+
+    fn tile_bytes() -> Vec<u8> {
+        GIDS.iter()
+            .flat_map(|gid| gid.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    fn zlib(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn gids(map: &Map) -> Vec<u32> {
+        match map.layer("ground") {
+            Some(Layer::Tiles(layer)) => layer.tiles.iter().map(|tile| tile.0).collect(),
+            _ => panic!("Map has no ground layer"),
+        }
+    }
+
+    fn tmx_map(data: &str) -> Result<Map> {
+        Map::from_tmx(&tmx(data), Path::new(""))
+    }
+
+    #[test]
+    fn csv_layer() {
+        let map = tmx_map("<data encoding=\"csv\">\n1,2,3,\n0,2147483649,5\n</data>").unwrap();
+        assert_eq!(gids(&map), GIDS);
+        assert_eq!(map.tileset_for(5), Some((0, 4)));
+        let layer = match &map.layers[0] {
+            Layer::Tiles(layer) => layer,
+            Layer::Objects(_) => unreachable!(),
+        };
+        assert!(layer.tile(1, 1).flipped_horizontally());
+        assert_eq!(layer.tile(1, 1).gid(), 1);
+        assert!(layer.tile(3, 0).is_empty());
+    }
+
+    #[test]
+    fn base64_layer() {
+        // Tiled wraps and indents the data
+        let encoded = base64::encode(tile_bytes());
+        let (first, second) = encoded.split_at(10);
+        let data = format!(
+            "<data encoding=\"base64\">\n   {}\n   {}\n  </data>",
+            first, second
+        );
+        assert_eq!(gids(&tmx_map(&data).unwrap()), GIDS);
+
+        let text = json(&format!("\"{}\"", encoded), "");
+        assert_eq!(gids(&Map::from_json(&text, Path::new("")).unwrap()), GIDS);
+    }
+
+    #[test]
+    fn zlib_layer() {
+        let encoded = base64::encode(zlib(&tile_bytes()));
+        let data = format!(
+            "<data encoding=\"base64\" compression=\"zlib\">{}</data>",
+            encoded
+        );
+        assert_eq!(gids(&tmx_map(&data).unwrap()), GIDS);
+
+        let text = json(&format!("\"{}\"", encoded), "zlib");
+        assert_eq!(gids(&Map::from_json(&text, Path::new("")).unwrap()), GIDS);
+    }
+
+    #[test]
+    fn gzip_layer() {
+        let mut plain = GzEncoder::new(Vec::new(), Compression::default());
+        plain.write_all(&tile_bytes()).unwrap();
+        // The extra field, file name and comment all come before the compressed data
+        let mut annotated = GzBuilder::new()
+            .extra(vec![1, 2, 3, 4])
+            .filename("ground.bin")
+            .comment("tiles")
+            .write(Vec::new(), Compression::default());
+        annotated.write_all(&tile_bytes()).unwrap();
+
+        for gzip in [plain.finish().unwrap(), annotated.finish().unwrap()].iter() {
+            let data = format!(
+                "<data encoding=\"base64\" compression=\"gzip\">{}</data>",
+                base64::encode(gzip)
+            );
+            assert_eq!(gids(&tmx_map(&data).unwrap()), GIDS);
+        }
+    }
+
+    #[test]
+    fn invalid_tile_data() {
+        let encoded = base64::encode(tile_bytes());
+        let zlib = base64::encode(zlib(&tile_bytes()));
+        let inputs = [
+            // Padding in the middle
+            format!("<data encoding=\"base64\">AQ==AAAA{}</data>", encoded),
+            // Truncated base64 and a partial tile
+            format!(
+                "<data encoding=\"base64\">{}</data>",
+                &encoded[..encoded.len() - 3]
+            ),
+            format!(
+                "<data encoding=\"base64\">{}</data>",
+                base64::encode(&tile_bytes()[..23])
+            ),
+            // Truncated and mislabelled compressed data
+            format!(
+                "<data encoding=\"base64\" compression=\"zlib\">{}</data>",
+                &zlib[..zlib.len() / 2]
+            ),
+            format!(
+                "<data encoding=\"base64\" compression=\"gzip\">{}</data>",
+                zlib
+            ),
+            format!(
+                "<data encoding=\"base64\" compression=\"zstd\">{}</data>",
+                zlib
+            ),
+            // Too few tiles
+            "<data encoding=\"csv\">1,2,3</data>".to_string(),
+            "<data encoding=\"csv\">1,x,3,4,5,6</data>".to_string(),
+        ];
+        for data in &inputs {
+            assert!(tmx_map(data).is_err(), "{} loaded", data);
+        }
+    }
+
+    #[test]
+    fn huge_layer() {
+        let text = tmx("<data encoding=\"csv\">1</data>").replace(
+            "name=\"ground\" width=\"3\" height=\"2\"",
+            "name=\"ground\" width=\"4294967295\" height=\"4294967295\"",
+        );
+        assert!(Map::from_tmx(&text, Path::new("")).is_err());
+    }
+}
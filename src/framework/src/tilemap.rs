@@ -0,0 +1,561 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::time::Duration;
+
+use anyhow::*;
+use cgmath::Matrix4;
+use image::GenericImageView;
+use wgpu::util::DeviceExt;
+
+use crate::camera::Camera2d;
+use crate::sprite::{self, SpriteVertex};
+use crate::texture::Texture;
+use crate::tiled::{Layer, Map, Tile, Tileset};
+
+/// Width and height of a chunk, in tiles. Each chunk is drawn with one static vertex
+/// buffer per tileset it uses, and skipped entirely when the camera can't see it.
+pub const CHUNK_SIZE: u32 = 32;
+
+/// A tile placed in the world, before it becomes vertices.
+#[derive(Copy, Clone, Debug)]
+struct TileQuad {
+    tileset: usize,
+    local_id: u32,
+    tile: Tile,
+    /// Top left, bottom left, bottom right and top right, in world pixels.
+    corners: [[f32; 2]; 4],
+    color: [f32; 4],
+}
+
+struct Mesh {
+    tileset: usize,
+    vertex_buffer: wgpu::Buffer,
+    quads: u32,
+}
+
+struct Chunk {
+    min: [f32; 2],
+    max: [f32; 2],
+    meshes: Vec<Mesh>,
+    /// Animated tiles change texture coordinates as time passes, so they are written
+    /// into a shared dynamic buffer each update instead.
+    animated: Vec<TileQuad>,
+}
+
+struct MapLayer {
+    name: String,
+    visible: bool,
+    chunks: Vec<Chunk>,
+}
+
+struct TilesetTexture {
+    tileset: Tileset,
+    width: u32,
+    height: u32,
+    bind_group: wgpu::BindGroup,
+}
+
+enum Draw {
+    Chunk { layer: usize, chunk: usize },
+    Animated { tileset: usize, quads: Range<u32> },
+}
+
+/// Draws the tile and tile object layers of a Tiled map.
+pub struct Tilemap {
+    tilesets: Vec<TilesetTexture>,
+    layers: Vec<MapLayer>,
+    projection_buffer: wgpu::Buffer,
+    projection_bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+
+    /// In quads, shared by the static meshes and the animated tiles.
+    index_capacity: usize,
+    index_buffer: wgpu::Buffer,
+    animated_capacity: usize,
+    animated_buffer: wgpu::Buffer,
+
+    draws: Vec<Draw>,
+    visible_chunks: usize,
+}
+
+impl Tilemap {
+    /// Loads the map's tileset images and builds the chunks of every layer.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color_format: wgpu::TextureFormat,
+        map: &Map,
+    ) -> Result<Self> {
+        let texture_bind_group_layout = Texture::bind_group_layout(device);
+        // Neighbouring tiles would bleed into each other with linear filtering
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Tileset Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let tilesets = map
+            .tilesets
+            .iter()
+            .map(|tileset| {
+                let img = image::open(&tileset.image)
+                    .with_context(|| format!("Couldn't open {:?}", tileset.image))?;
+                let texture = Texture::from_image(device, queue, &img, Some(&tileset.name), false)?;
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &texture_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&texture.view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&sampler),
+                        },
+                    ],
+                    label: Some("tileset_bind_group"),
+                });
+                Ok(TilesetTexture {
+                    tileset: tileset.clone(),
+                    width: img.width(),
+                    height: img.height(),
+                    bind_group,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let identity: [[f32; 4]; 4] = Matrix4::from_scale(1.0).into();
+        let projection_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tilemap Projection Buffer"),
+            contents: bytemuck::cast_slice(&[identity]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let projection_bind_group_layout = sprite::projection_bind_group_layout(device);
+        let projection_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &projection_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: projection_buffer.as_entire_binding(),
+            }],
+            label: Some("tilemap_projection_bind_group"),
+        });
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("sprite.frag.spv"));
+        let render_pipeline = sprite::create_pipeline(
+            device,
+            color_format,
+            &texture_bind_group_layout,
+            &projection_bind_group_layout,
+            &fs_module,
+        );
+
+        let mut tilemap = Self {
+            tilesets,
+            layers: Vec::new(),
+            projection_buffer,
+            projection_bind_group,
+            render_pipeline,
+            index_capacity: 0,
+            index_buffer: create_index_buffer(device, 1),
+            animated_capacity: 1,
+            animated_buffer: create_animated_buffer(device, 1),
+            draws: Vec::new(),
+            visible_chunks: 0,
+        };
+        for layer in &map.layers {
+            let layer = tilemap.build_layer(device, map, layer);
+            tilemap.layers.push(layer);
+        }
+        let largest_mesh = tilemap
+            .layers
+            .iter()
+            .flat_map(|layer| &layer.chunks)
+            .flat_map(|chunk| &chunk.meshes)
+            .map(|mesh| mesh.quads as usize)
+            .max()
+            .unwrap_or(1);
+        tilemap.reserve_indices(device, largest_mesh);
+        Ok(tilemap)
+    }
+
+    fn build_layer(&self, device: &wgpu::Device, map: &Map, layer: &Layer) -> MapLayer {
+        let chunk_width = (CHUNK_SIZE * map.tile_width) as f32;
+        let chunk_height = (CHUNK_SIZE * map.tile_height) as f32;
+        let mut quads: BTreeMap<(i32, i32), Vec<TileQuad>> = BTreeMap::new();
+        let (name, visible) = match layer {
+            Layer::Tiles(layer) => {
+                let color = [1.0, 1.0, 1.0, layer.opacity];
+                for y in 0..layer.height {
+                    for x in 0..layer.width {
+                        let tile = layer.tile(x, y);
+                        let (tileset, local_id) = match map.tileset_for(tile.gid()) {
+                            Some(found) => found,
+                            None => continue,
+                        };
+                        // Tiles taller or wider than the grid hang up and right from
+                        // their cell's bottom left corner, as they do in Tiled
+                        let tileset_data = &map.tilesets[tileset];
+                        let left = layer.offset[0] + (x * map.tile_width) as f32;
+                        let bottom = layer.offset[1] + ((y + 1) * map.tile_height) as f32;
+                        let right = left + tileset_data.tile_width as f32;
+                        let top = bottom - tileset_data.tile_height as f32;
+                        quads
+                            .entry(((x / CHUNK_SIZE) as i32, (y / CHUNK_SIZE) as i32))
+                            .or_default()
+                            .push(TileQuad {
+                                tileset,
+                                local_id,
+                                tile,
+                                corners: [
+                                    [left, top],
+                                    [left, bottom],
+                                    [right, bottom],
+                                    [right, top],
+                                ],
+                                color,
+                            });
+                    }
+                }
+                (&layer.name, layer.visible)
+            }
+            Layer::Objects(layer) => {
+                let color = [1.0, 1.0, 1.0, layer.opacity];
+                for object in layer.objects.iter().filter(|object| object.visible) {
+                    let tile = match object.tile {
+                        Some(tile) => tile,
+                        None => continue,
+                    };
+                    let (tileset, local_id) = match map.tileset_for(tile.gid()) {
+                        Some(found) => found,
+                        None => continue,
+                    };
+                    let tileset_data = &map.tilesets[tileset];
+                    let width = if object.width > 0.0 {
+                        object.width
+                    } else {
+                        tileset_data.tile_width as f32
+                    };
+                    let height = if object.height > 0.0 {
+                        object.height
+                    } else {
+                        tileset_data.tile_height as f32
+                    };
+                    // Tile objects hang from their bottom left corner, which they rotate around
+                    let (sin, cos) = object.rotation.to_radians().sin_cos();
+                    let x = layer.offset[0] + object.x;
+                    let y = layer.offset[1] + object.y;
+                    let corner = |local_x: f32, local_y: f32| {
+                        [
+                            x + local_x * cos - local_y * sin,
+                            y + local_x * sin + local_y * cos,
+                        ]
+                    };
+                    quads
+                        .entry((
+                            (x / chunk_width).floor() as i32,
+                            (y / chunk_height).floor() as i32,
+                        ))
+                        .or_default()
+                        .push(TileQuad {
+                            tileset,
+                            local_id,
+                            tile,
+                            corners: [
+                                corner(0.0, -height),
+                                corner(0.0, 0.0),
+                                corner(width, 0.0),
+                                corner(width, -height),
+                            ],
+                            color,
+                        });
+                }
+                (&layer.name, layer.visible)
+            }
+        };
+
+        let chunks = quads
+            .into_values()
+            .map(|quads| self.build_chunk(device, quads))
+            .collect();
+        MapLayer {
+            name: name.clone(),
+            visible,
+            chunks,
+        }
+    }
+
+    fn build_chunk(&self, device: &wgpu::Device, quads: Vec<TileQuad>) -> Chunk {
+        let mut min = [f32::MAX; 2];
+        let mut max = [f32::MIN; 2];
+        for corner in quads.iter().flat_map(|quad| quad.corners.iter()) {
+            min = [min[0].min(corner[0]), min[1].min(corner[1])];
+            max = [max[0].max(corner[0]), max[1].max(corner[1])];
+        }
+
+        let (mut animated, mut still): (Vec<_>, Vec<_>) = quads.into_iter().partition(|quad| {
+            self.tilesets[quad.tileset]
+                .tileset
+                .animations
+                .contains_key(&quad.local_id)
+        });
+        animated.sort_by_key(|quad| quad.tileset);
+        still.sort_by_key(|quad| quad.tileset);
+
+        let meshes = tileset_runs(&still)
+            .into_iter()
+            .map(|quads| {
+                let vertices = quads
+                    .iter()
+                    .flat_map(|quad| self.vertices(quad, quad.local_id).to_vec())
+                    .collect::<Vec<_>>();
+                Mesh {
+                    tileset: quads[0].tileset,
+                    vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Tilemap Chunk Vertex Buffer"),
+                        contents: bytemuck::cast_slice(&vertices),
+                        usage: wgpu::BufferUsage::VERTEX,
+                    }),
+                    quads: quads.len() as u32,
+                }
+            })
+            .collect();
+        Chunk {
+            min,
+            max,
+            meshes,
+            animated,
+        }
+    }
+
+    fn vertices(&self, quad: &TileQuad, local_id: u32) -> [SpriteVertex; 4] {
+        let texture = &self.tilesets[quad.tileset];
+        let tileset = &texture.tileset;
+        let (x, y) = tileset.tile_position(local_id);
+        let u = [
+            x as f32 / texture.width as f32,
+            (x + tileset.tile_width) as f32 / texture.width as f32,
+        ];
+        let v = [
+            y as f32 / texture.height as f32,
+            (y + tileset.tile_height) as f32 / texture.height as f32,
+        ];
+        let tile = quad.tile;
+        let corner = |index: usize, mut s: usize, mut t: usize| {
+            // Flip the corner first, then swap axes for the diagonal flip. Sampling this
+            // way matches Tiled doing the diagonal flip to the image first.
+            if tile.flipped_horizontally() {
+                s = 1 - s;
+            }
+            if tile.flipped_vertically() {
+                t = 1 - t;
+            }
+            if tile.flipped_diagonally() {
+                std::mem::swap(&mut s, &mut t);
+            }
+            let [x, y] = quad.corners[index];
+            SpriteVertex {
+                position: [x, y, 0.0],
+                tex_coords: [u[s], v[t]],
+                color: quad.color,
+            }
+        };
+        [
+            corner(0, 0, 0),
+            corner(1, 0, 1),
+            corner(2, 1, 1),
+            corner(3, 1, 0),
+        ]
+    }
+
+    fn reserve_indices(&mut self, device: &wgpu::Device, quads: usize) {
+        if quads > self.index_capacity {
+            self.index_capacity = quads.next_power_of_two();
+            self.index_buffer = create_index_buffer(device, self.index_capacity);
+        }
+    }
+
+    pub fn layer_names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|layer| layer.name.as_str())
+    }
+
+    /// Returns false when there is no layer called `name`.
+    pub fn set_layer_visible(&mut self, name: &str, visible: bool) -> bool {
+        match self.layers.iter_mut().find(|layer| layer.name == name) {
+            Some(layer) => {
+                layer.visible = visible;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_layer_visible(&self, name: &str) -> bool {
+        self.layers
+            .iter()
+            .any(|layer| layer.name == name && layer.visible)
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.layers.iter().map(|layer| layer.chunks.len()).sum()
+    }
+
+    /// Chunks the last `update` found on screen.
+    pub fn visible_chunks(&self) -> usize {
+        self.visible_chunks
+    }
+
+    /// Finds the chunks `camera` can see and advances animated tiles to `time`, which
+    /// is measured from when the map started playing.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera: &Camera2d,
+        time: Duration,
+    ) {
+        let projection: [[f32; 4]; 4] = camera.build_view_projection_matrix().into();
+        queue.write_buffer(
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(&[projection]),
+        );
+
+        let (min, max) = camera.visible_bounds();
+        let mut draws = Vec::new();
+        let mut animated = Vec::new();
+        for (layer_index, layer) in self.layers.iter().enumerate() {
+            if !layer.visible {
+                continue;
+            }
+            let first_animated = animated.len();
+            for (chunk_index, chunk) in layer.chunks.iter().enumerate() {
+                let on_screen = chunk.max[0] >= min.x
+                    && chunk.min[0] <= max.x
+                    && chunk.max[1] >= min.y
+                    && chunk.min[1] <= max.y;
+                if !on_screen {
+                    continue;
+                }
+                draws.push(Draw::Chunk {
+                    layer: layer_index,
+                    chunk: chunk_index,
+                });
+                animated.extend(chunk.animated.iter().copied());
+            }
+
+            // Animated tiles go over the layer's other tiles, one draw per tileset
+            animated[first_animated..].sort_by_key(|quad| quad.tileset);
+            let mut start = first_animated;
+            for quads in tileset_runs(&animated[first_animated..]) {
+                let end = start + quads.len();
+                draws.push(Draw::Animated {
+                    tileset: quads[0].tileset,
+                    quads: start as u32..end as u32,
+                });
+                start = end;
+            }
+        }
+        self.visible_chunks = draws
+            .iter()
+            .filter(|draw| matches!(draw, Draw::Chunk { .. }))
+            .count();
+        self.draws = draws;
+
+        if animated.is_empty() {
+            return;
+        }
+        if animated.len() > self.animated_capacity {
+            self.animated_capacity = animated.len().next_power_of_two();
+            self.animated_buffer = create_animated_buffer(device, self.animated_capacity);
+        }
+        self.reserve_indices(device, animated.len());
+        let time = time.as_millis() as u64;
+        let vertices = animated
+            .iter()
+            .flat_map(|quad| {
+                let frames = &self.tilesets[quad.tileset].tileset.animations[&quad.local_id];
+                let length: u64 = frames
+                    .iter()
+                    .map(|frame| frame.duration.as_millis() as u64)
+                    .sum();
+                let mut remaining = time % length.max(1);
+                let mut local_id = frames.first().map_or(quad.local_id, |frame| frame.tile);
+                for frame in frames {
+                    let duration = frame.duration.as_millis() as u64;
+                    local_id = frame.tile;
+                    if remaining < duration {
+                        break;
+                    }
+                    remaining -= duration;
+                }
+                self.vertices(quad, local_id).to_vec()
+            })
+            .collect::<Vec<_>>();
+        queue.write_buffer(&self.animated_buffer, 0, bytemuck::cast_slice(&vertices));
+    }
+
+    /// Draws the chunks the last `update` found, bottom layer first.
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.draws.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(1, &self.projection_bind_group, &[]);
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for draw in &self.draws {
+            match draw {
+                Draw::Chunk { layer, chunk } => {
+                    for mesh in &self.layers[*layer].chunks[*chunk].meshes {
+                        render_pass.set_bind_group(0, &self.tilesets[mesh.tileset].bind_group, &[]);
+                        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                        render_pass.draw_indexed(0..mesh.quads * 6, 0, 0..1);
+                    }
+                }
+                Draw::Animated { tileset, quads } => {
+                    render_pass.set_bind_group(0, &self.tilesets[*tileset].bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, self.animated_buffer.slice(..));
+                    render_pass.draw_indexed(quads.start * 6..quads.end * 6, 0, 0..1);
+                }
+            }
+        }
+    }
+}
+
+/// Splits quads sorted by tileset into runs sharing one.
+fn tileset_runs(quads: &[TileQuad]) -> Vec<&[TileQuad]> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for end in 1..=quads.len() {
+        if end == quads.len() || quads[end].tileset != quads[start].tileset {
+            runs.push(&quads[start..end]);
+            start = end;
+        }
+    }
+    runs
+}
+
+fn create_index_buffer(device: &wgpu::Device, quads: usize) -> wgpu::Buffer {
+    let indices = (0..quads as u32)
+        .flat_map(|quad| {
+            let first = quad * 4;
+            vec![first, first + 1, first + 2, first, first + 2, first + 3]
+        })
+        .collect::<Vec<u32>>();
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Tilemap Index Buffer"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: wgpu::BufferUsage::INDEX,
+    })
+}
+
+fn create_animated_buffer(device: &wgpu::Device, quads: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Tilemap Animated Vertex Buffer"),
+        size: (quads * 4 * std::mem::size_of::<SpriteVertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
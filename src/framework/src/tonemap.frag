@@ -0,0 +1,45 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_hdr;
+layout(set=0, binding=1) uniform sampler s_hdr;
+layout(set=0, binding=2) uniform texture2D t_bloom;
+layout(set=0, binding=3) uniform Tonemap {
+    float u_exposure;
+    // Must match framework::hdr::Tonemapper
+    uint u_tonemapper;
+    float u_bloom_intensity;
+    float _padding;
+};
+
+const uint TONEMAPPER_NONE = 0;
+const uint TONEMAPPER_REINHARD = 1;
+const uint TONEMAPPER_ACES = 2;
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 x) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+void main() {
+    vec3 color = texture(sampler2D(t_hdr, s_hdr), v_tex_coords).rgb;
+    color += texture(sampler2D(t_bloom, s_hdr), v_tex_coords).rgb * u_bloom_intensity;
+    color *= u_exposure;
+
+    if (u_tonemapper == TONEMAPPER_REINHARD) {
+        color = color / (color + vec3(1.0));
+    } else if (u_tonemapper == TONEMAPPER_ACES) {
+        color = aces(color);
+    }
+
+    // The swap chain is sRGB, so the hardware does the gamma encoding
+    f_color = vec4(color, 1.0);
+}
@@ -0,0 +1,428 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+
+use cgmath::Vector2;
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+use crate::sprite::{screen_projection, Sprite, SpriteBatch, TextureId};
+use crate::text::{Font, Section, TextRenderer};
+use crate::texture::Texture;
+
+const PANEL_WIDTH: f32 = 280.0;
+const PANEL_GAP: f32 = 8.0;
+const PADDING: f32 = 8.0;
+const ROW_HEIGHT: f32 = 22.0;
+const ROW_GAP: f32 = 4.0;
+const TEXT_SIZE: f32 = 15.0;
+
+const BACKGROUND: [f32; 4] = [0.08, 0.08, 0.1, 0.85];
+const TITLE: [f32; 4] = [0.2, 0.25, 0.4, 1.0];
+const WIDGET: [f32; 4] = [0.22, 0.22, 0.26, 1.0];
+const HOVERED: [f32; 4] = [0.3, 0.3, 0.36, 1.0];
+const ACCENT: [f32; 4] = [0.35, 0.55, 0.9, 1.0];
+const TEXT: [f32; 4] = [0.92, 0.92, 0.92, 1.0];
+
+/// Panels are drawn on the bottom layer, widgets on top of them.
+const BACKGROUND_LAYER: i32 = 0;
+const WIDGET_LAYER: i32 = 1;
+const FILL_LAYER: i32 = 2;
+
+/// Left mouse button state, with the presses and releases since the last frame.
+#[derive(Copy, Clone, Debug, Default)]
+struct Pointer {
+    position: [f32; 2],
+    down: bool,
+    pressed: bool,
+    released: bool,
+}
+
+fn contains(rect: [f32; 4], point: [f32; 2]) -> bool {
+    point[0] >= rect[0]
+        && point[0] < rect[0] + rect[2]
+        && point[1] >= rect[1]
+        && point[1] < rect[1] + rect[3]
+}
+
+/// An immediate mode debug overlay. Every frame the app describes its panels between
+/// `begin_frame` and `end_frame`, and widgets report whether they were used right
+/// away, so there is no widget state to keep in sync with the app's own.
+pub struct Ui {
+    sprites: SpriteBatch,
+    text: TextRenderer,
+    white: TextureId,
+    pointer: Pointer,
+    /// The widget the mouse was pressed on, until it is released.
+    active: Option<u64>,
+    collapsed: HashSet<String>,
+    /// Panel areas from the last frame, clicks inside them belong to the UI.
+    panel_rects: Vec<[f32; 4]>,
+    next_panel_rects: Vec<[f32; 4]>,
+    /// Top of the next panel.
+    next_y: f32,
+    size: [f32; 2],
+    /// Hidden overlays draw nothing and ignore input.
+    pub visible: bool,
+}
+
+impl Ui {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color_format: wgpu::TextureFormat,
+        font: Font,
+    ) -> Self {
+        let mut sprites = SpriteBatch::new(device, color_format, 256);
+        let white = sprites.add_texture(
+            device,
+            &Texture::from_color(device, queue, [255; 4], "ui_white", false),
+        );
+        Self {
+            sprites,
+            text: TextRenderer::new(device, color_format, font),
+            white,
+            pointer: Pointer::default(),
+            active: None,
+            collapsed: HashSet::new(),
+            panel_rects: Vec::new(),
+            next_panel_rects: Vec::new(),
+            next_y: PANEL_GAP,
+            size: [1.0, 1.0],
+            visible: true,
+        }
+    }
+
+    /// Makes `texture` available to `Panel::image`.
+    pub fn add_texture(&mut self, device: &wgpu::Device, texture: &Texture) -> TextureId {
+        self.sprites.add_texture(device, texture)
+    }
+
+    /// Tracks the mouse. Returns true when the event was meant for the UI, such as a
+    /// click on a panel, so the app can leave it alone.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        if !self.visible {
+            return false;
+        }
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer.position = [position.x as f32, position.y as f32];
+                self.active.is_some()
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let captured = self.active.is_some() || self.is_pointer_over();
+                match state {
+                    ElementState::Pressed => {
+                        self.pointer.down = true;
+                        self.pointer.pressed = true;
+                    }
+                    ElementState::Released => {
+                        self.pointer.down = false;
+                        self.pointer.released = true;
+                    }
+                }
+                captured
+            }
+            WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. } => {
+                self.is_pointer_over()
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the cursor is over a panel.
+    pub fn is_pointer_over(&self) -> bool {
+        self.visible
+            && self
+                .panel_rects
+                .iter()
+                .any(|rect| contains(*rect, self.pointer.position))
+    }
+
+    /// Starts describing a frame for a `width` x `height` target.
+    pub fn begin_frame(&mut self, width: f32, height: f32) {
+        self.size = [width, height];
+        self.next_y = PANEL_GAP;
+        self.next_panel_rects.clear();
+    }
+
+    /// Adds a panel below the previous one. Clicking its title collapses it, in which
+    /// case `build` isn't called.
+    pub fn panel<F: FnOnce(&mut Panel)>(&mut self, title: &str, build: F) {
+        if !self.visible {
+            return;
+        }
+        let x = PANEL_GAP;
+        let top = self.next_y;
+        let title_rect = [x, top, PANEL_WIDTH, ROW_HEIGHT];
+        if self.clicked(title, title_rect) && !self.collapsed.remove(title) {
+            self.collapsed.insert(title.to_string());
+        }
+        let collapsed = self.collapsed.contains(title);
+        self.rect(title_rect, TITLE, WIDGET_LAYER);
+        let marker = if collapsed { "+" } else { "-" };
+        self.label_at(&format!("{} {}", marker, title), title_rect);
+
+        let mut bottom = top + ROW_HEIGHT;
+        if !collapsed {
+            let mut panel = Panel {
+                ui: self,
+                title,
+                x: x + PADDING,
+                y: top + ROW_HEIGHT + PADDING,
+                width: PANEL_WIDTH - PADDING * 2.0,
+            };
+            build(&mut panel);
+            bottom = panel.y - ROW_GAP + PADDING;
+        }
+        let rect = [x, top, PANEL_WIDTH, bottom - top];
+        self.rect(rect, BACKGROUND, BACKGROUND_LAYER);
+        self.next_panel_rects.push(rect);
+        self.next_y = bottom + PANEL_GAP;
+    }
+
+    /// Uploads what the frame's panels drew.
+    pub fn end_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        std::mem::swap(&mut self.panel_rects, &mut self.next_panel_rects);
+        if !self.visible {
+            self.panel_rects.clear();
+        }
+        self.pointer.pressed = false;
+        self.pointer.released = false;
+        if !self.pointer.down {
+            self.active = None;
+        }
+
+        let projection = screen_projection(self.size[0], self.size[1]);
+        self.sprites.set_projection(queue, projection);
+        self.sprites.prepare(device, queue);
+        self.text.set_projection(queue, projection);
+        self.text.prepare(device, queue);
+    }
+
+    /// Draws the overlay. Use it last in the pass so it ends up on top of the scene.
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.sprites.render(render_pass);
+        self.text.render(render_pass);
+    }
+
+    fn rect(&mut self, rect: [f32; 4], color: [f32; 4], layer: i32) {
+        let mut sprite = Sprite::new(self.white);
+        sprite.position = Vector2::new(rect[0], rect[1]);
+        sprite.size = Vector2::new(rect[2], rect[3]);
+        sprite.origin = Vector2::new(0.0, 0.0);
+        sprite.color = color;
+        sprite.layer = layer;
+        self.sprites.draw(sprite);
+    }
+
+    /// One line of text, vertically centered in `rect`.
+    fn label_at(&mut self, text: &str, rect: [f32; 4]) {
+        let line_height = self.text.font().line_height(TEXT_SIZE);
+        self.text.queue(&Section {
+            position: [rect[0] + 4.0, rect[1] + (rect[3] - line_height) / 2.0],
+            size: TEXT_SIZE,
+            color: TEXT,
+            ..Section::new(text)
+        });
+    }
+
+    fn is_hovered(&self, rect: [f32; 4]) -> bool {
+        contains(rect, self.pointer.position) && (self.active.is_none() || !self.pointer.down)
+    }
+
+    /// Makes the widget called `key` active when the mouse is pressed on it.
+    fn press(&mut self, key: &str, rect: [f32; 4]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let id = hasher.finish();
+        if self.pointer.pressed && contains(rect, self.pointer.position) {
+            self.active = Some(id);
+        }
+        id
+    }
+
+    /// Whether the mouse was pressed and released on the widget called `key`.
+    fn clicked(&mut self, key: &str, rect: [f32; 4]) -> bool {
+        let id = self.press(key, rect);
+        self.pointer.released && self.active == Some(id) && contains(rect, self.pointer.position)
+    }
+}
+
+/// Lays widgets out top to bottom inside a panel.
+pub struct Panel<'a> {
+    ui: &'a mut Ui,
+    title: &'a str,
+    x: f32,
+    y: f32,
+    width: f32,
+}
+
+impl<'a> Panel<'a> {
+    /// Reserves a full width row `height` high.
+    fn row(&mut self, height: f32) -> [f32; 4] {
+        let rect = [self.x, self.y, self.width, height];
+        self.y += height + ROW_GAP;
+        rect
+    }
+
+    /// Widgets are told apart by their panel and label.
+    fn key(&self, label: &str) -> String {
+        format!("{}/{}", self.title, label)
+    }
+
+    /// Text wrapped to the panel's width.
+    pub fn label(&mut self, text: &str) {
+        let section = Section {
+            size: TEXT_SIZE,
+            color: TEXT,
+            max_width: Some(self.width),
+            ..Section::new(text)
+        };
+        let height = self.ui.text.measure(&section)[1];
+        let rect = self.row(height);
+        self.ui.text.queue(&Section {
+            position: [rect[0], rect[1]],
+            ..section
+        });
+    }
+
+    pub fn separator(&mut self) {
+        let rect = self.row(1.0);
+        self.ui.rect(rect, WIDGET, WIDGET_LAYER);
+    }
+
+    /// Returns true when clicked.
+    pub fn button(&mut self, label: &str) -> bool {
+        let rect = self.row(ROW_HEIGHT);
+        let clicked = self.ui.clicked(&self.key(label), rect);
+        let color = if self.ui.is_hovered(rect) {
+            HOVERED
+        } else {
+            WIDGET
+        };
+        self.ui.rect(rect, color, WIDGET_LAYER);
+        self.ui.label_at(label, rect);
+        clicked
+    }
+
+    /// A box with a label next to it. Returns true when `value` changed.
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let rect = self.row(ROW_HEIGHT);
+        let clicked = self.ui.clicked(&self.key(label), rect);
+        if clicked {
+            *value = !*value;
+        }
+        self.toggle(label, rect, *value, 2.0);
+        clicked
+    }
+
+    /// One of a group of options sharing `current`. Returns true when it was picked.
+    pub fn radio<T: PartialEq>(&mut self, label: &str, current: &mut T, value: T) -> bool {
+        let rect = self.row(ROW_HEIGHT);
+        let clicked = self.ui.clicked(&self.key(label), rect) && *current != value;
+        let selected = clicked || *current == value;
+        if clicked {
+            *current = value;
+        }
+        self.toggle(label, rect, selected, 5.0);
+        clicked
+    }
+
+    /// Draws a checkbox or radio button, with the mark inset by `inset` when `on`.
+    fn toggle(&mut self, label: &str, rect: [f32; 4], on: bool, inset: f32) {
+        let size = ROW_HEIGHT - 6.0;
+        let mark = [rect[0], rect[1] + 3.0, size, size];
+        let color = if self.ui.is_hovered(rect) {
+            HOVERED
+        } else {
+            WIDGET
+        };
+        self.ui.rect(mark, color, WIDGET_LAYER);
+        if on {
+            let inner = [
+                mark[0] + inset,
+                mark[1] + inset,
+                size - inset * 2.0,
+                size - inset * 2.0,
+            ];
+            self.ui.rect(inner, ACCENT, FILL_LAYER);
+        }
+        self.ui
+            .label_at(label, [rect[0] + size, rect[1], rect[2] - size, rect[3]]);
+    }
+
+    /// Drag anywhere along the bar to set `value`. Returns true when it changed.
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let rect = self.row(ROW_HEIGHT);
+        let id = self.ui.press(&self.key(label), rect);
+        let (min, max) = (*range.start(), *range.end());
+        let previous = *value;
+        if self.ui.active == Some(id) && self.ui.pointer.down {
+            let t = ((self.ui.pointer.position[0] - rect[0]) / rect[2]).clamp(0.0, 1.0);
+            *value = min + (max - min) * t;
+        }
+
+        let color = if self.ui.is_hovered(rect) || self.ui.active == Some(id) {
+            HOVERED
+        } else {
+            WIDGET
+        };
+        self.ui.rect(rect, color, WIDGET_LAYER);
+        let t = if max > min {
+            ((*value - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.ui
+            .rect([rect[0], rect[1], rect[2] * t, rect[3]], ACCENT, FILL_LAYER);
+        self.ui.label_at(&format!("{}: {:.3}", label, value), rect);
+        *value != previous
+    }
+
+    /// A swatch with a slider for each channel. Returns true when `color` changed.
+    pub fn color_edit(&mut self, label: &str, color: &mut wgpu::Color) -> bool {
+        let rect = self.row(ROW_HEIGHT);
+        let swatch = [
+            rect[0] + rect[2] - ROW_HEIGHT * 2.0,
+            rect[1],
+            ROW_HEIGHT * 2.0,
+            rect[3],
+        ];
+        self.ui.rect(
+            swatch,
+            [color.r as f32, color.g as f32, color.b as f32, 1.0],
+            WIDGET_LAYER,
+        );
+        self.ui.label_at(label, rect);
+
+        let mut channels = [color.r, color.g, color.b, color.a];
+        let mut changed = false;
+        for (name, channel) in ["r", "g", "b", "a"].iter().zip(channels.iter_mut()) {
+            let mut value = *channel as f32;
+            if self.slider(&format!("{} {}", label, name), &mut value, 0.0..=1.0) {
+                *channel = value as f64;
+                changed = true;
+            }
+        }
+        let [r, g, b, a] = channels;
+        *color = wgpu::Color { r, g, b, a };
+        changed
+    }
+
+    /// Shows `texture` as wide as the panel, keeping the aspect ratio of `size`.
+    pub fn image(&mut self, texture: TextureId, size: [u32; 2]) {
+        let height = self.width * size[1] as f32 / size[0].max(1) as f32;
+        let rect = self.row(height);
+        let mut sprite = Sprite::new(texture);
+        sprite.position = Vector2::new(rect[0], rect[1]);
+        sprite.size = Vector2::new(rect[2], rect[3]);
+        sprite.origin = Vector2::new(0.0, 0.0);
+        sprite.layer = WIDGET_LAYER;
+        self.ui.sprites.draw(sprite);
+    }
+}
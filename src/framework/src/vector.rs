@@ -0,0 +1,1208 @@
+//! Vector paths tessellated into triangles. Strokes get a width, joins and caps, fills
+//! support curves, holes and both SVG fill rules, and every edge fades out over a thin
+//! feathered fringe so shapes look smooth without MSAA.
+use std::collections::HashMap;
+
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Vector2};
+use wgpu::util::DeviceExt;
+
+use crate::sprite::projection_bind_group_layout;
+
+/// The same layout as tutorial4's `Vertex`, so its pipeline can draw the vertex buffer
+/// of a tessellation as is, just without the anti-aliasing. The coverage goes in a
+/// second buffer, see `Tessellation::coverage`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl Vertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float3,
+                },
+            ],
+        }
+    }
+
+    /// The per vertex coverage, read from the second vertex buffer.
+    pub fn coverage_desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<f32>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 2,
+                format: wgpu::VertexFormat::Float,
+            }],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// How overlapping contours of a fill combine, named after SVG's `fill-rule`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+impl FillRule {
+    fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// Longest miter, relative to the width, before a miter join is drawn as a bevel.
+    pub miter_limit: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Command {
+    MoveTo(Vector2<f32>),
+    LineTo(Vector2<f32>),
+    QuadraticTo(Vector2<f32>, Vector2<f32>),
+    CubicTo(Vector2<f32>, Vector2<f32>, Vector2<f32>),
+    Close,
+}
+
+/// Control points of the magic number approximation of a quarter circle with a cubic.
+const KAPPA: f32 = 0.552_284_8;
+const MAX_CURVE_SEGMENTS: f32 = 256.0;
+const EPSILON: f32 = 1e-5;
+
+/// A list of contours built from lines and Bézier curves, like an SVG path.
+#[derive(Clone, Debug, Default)]
+pub struct Path {
+    commands: Vec<Command>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a new contour at `to`.
+    pub fn move_to(&mut self, to: Vector2<f32>) -> &mut Self {
+        self.commands.push(Command::MoveTo(to));
+        self
+    }
+
+    pub fn line_to(&mut self, to: Vector2<f32>) -> &mut Self {
+        self.commands.push(Command::LineTo(to));
+        self
+    }
+
+    pub fn quadratic_to(&mut self, control: Vector2<f32>, to: Vector2<f32>) -> &mut Self {
+        self.commands.push(Command::QuadraticTo(control, to));
+        self
+    }
+
+    pub fn cubic_to(
+        &mut self,
+        control1: Vector2<f32>,
+        control2: Vector2<f32>,
+        to: Vector2<f32>,
+    ) -> &mut Self {
+        self.commands.push(Command::CubicTo(control1, control2, to));
+        self
+    }
+
+    /// Joins the current contour back to its start.
+    pub fn close(&mut self) -> &mut Self {
+        self.commands.push(Command::Close);
+        self
+    }
+
+    pub fn polyline(points: &[Vector2<f32>], closed: bool) -> Self {
+        let mut path = Self::new();
+        for (i, point) in points.iter().enumerate() {
+            if i == 0 {
+                path.move_to(*point);
+            } else {
+                path.line_to(*point);
+            }
+        }
+        if closed {
+            path.close();
+        }
+        path
+    }
+
+    pub fn rect(min: Vector2<f32>, size: Vector2<f32>) -> Self {
+        Self::polyline(
+            &[
+                min,
+                min + Vector2::new(size.x, 0.0),
+                min + size,
+                min + Vector2::new(0.0, size.y),
+            ],
+            true,
+        )
+    }
+
+    pub fn ellipse(center: Vector2<f32>, radii: Vector2<f32>) -> Self {
+        let x = Vector2::new(radii.x, 0.0);
+        let y = Vector2::new(0.0, radii.y);
+        let mut path = Self::new();
+        path.move_to(center + x);
+        for (from, to) in [(x, y), (y, -x), (-x, -y), (-y, x)].iter() {
+            path.cubic_to(
+                center + from + to * KAPPA,
+                center + to + from * KAPPA,
+                center + to,
+            );
+        }
+        path.close();
+        path
+    }
+
+    pub fn circle(center: Vector2<f32>, radius: f32) -> Self {
+        Self::ellipse(center, Vector2::new(radius, radius))
+    }
+
+    /// Applies an affine `transform` to every point, curves stay curves.
+    pub fn transform(&mut self, transform: &Matrix4<f32>) {
+        let apply = |point: &mut Vector2<f32>| {
+            *point = transform
+                .transform_point(cgmath::Point3::new(point.x, point.y, 0.0))
+                .to_vec()
+                .truncate();
+        };
+        for command in &mut self.commands {
+            match command {
+                Command::MoveTo(to) | Command::LineTo(to) => apply(to),
+                Command::QuadraticTo(control, to) => {
+                    apply(control);
+                    apply(to);
+                }
+                Command::CubicTo(control1, control2, to) => {
+                    apply(control1);
+                    apply(control2);
+                    apply(to);
+                }
+                Command::Close => {}
+            }
+        }
+    }
+
+    /// Replaces the curves with line segments that stay within `tolerance` of them.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        let tolerance = tolerance.max(EPSILON);
+        let mut polylines = Vec::new();
+        let mut current = Polyline::default();
+        let mut last = Vector2::zero();
+        // Whether the contour has a segment, even a zero length one, or is just a move
+        let mut drawn = false;
+
+        for command in &self.commands {
+            match *command {
+                Command::MoveTo(to) => {
+                    current.finish(&mut polylines, drawn);
+                    current.push(to);
+                    last = to;
+                    drawn = false;
+                }
+                Command::LineTo(to) => {
+                    if current.points.is_empty() {
+                        current.push(last);
+                    }
+                    current.push(to);
+                    last = to;
+                    drawn = true;
+                }
+                Command::QuadraticTo(control, to) => {
+                    if current.points.is_empty() {
+                        current.push(last);
+                    }
+                    let from = last;
+                    let segments =
+                        curve_segments((from - control * 2.0 + to).magnitude() / 4.0, tolerance);
+                    for i in 1..=segments {
+                        let t = i as f32 / segments as f32;
+                        let u = 1.0 - t;
+                        current.push(from * (u * u) + control * (2.0 * u * t) + to * (t * t));
+                    }
+                    last = to;
+                    drawn = true;
+                }
+                Command::CubicTo(control1, control2, to) => {
+                    if current.points.is_empty() {
+                        current.push(last);
+                    }
+                    let from = last;
+                    let bend = (from - control1 * 2.0 + control2)
+                        .magnitude()
+                        .max((control1 - control2 * 2.0 + to).magnitude());
+                    let segments = curve_segments(bend * 0.75, tolerance);
+                    for i in 1..=segments {
+                        let t = i as f32 / segments as f32;
+                        let u = 1.0 - t;
+                        current.push(
+                            from * (u * u * u)
+                                + control1 * (3.0 * u * u * t)
+                                + control2 * (3.0 * u * t * t)
+                                + to * (t * t * t),
+                        );
+                    }
+                    last = to;
+                    drawn = true;
+                }
+                Command::Close => {
+                    if let Some(start) = current.points.first().copied() {
+                        current.closed = true;
+                        current.finish(&mut polylines, true);
+                        last = start;
+                        drawn = false;
+                    }
+                }
+            }
+        }
+        current.finish(&mut polylines, drawn);
+        polylines
+    }
+}
+
+/// Uniform steps keeping a curve within `tolerance` of its chords, `bend` being an
+/// eighth of the curve's largest second derivative.
+fn curve_segments(bend: f32, tolerance: f32) -> usize {
+    (bend / tolerance)
+        .sqrt()
+        .ceil()
+        .clamp(1.0, MAX_CURVE_SEGMENTS) as usize
+}
+
+/// One flattened contour of a `Path`. A single point is a contour of zero length,
+/// which strokes only draw with round or square caps.
+#[derive(Clone, Debug, Default)]
+pub struct Polyline {
+    pub points: Vec<Vector2<f32>>,
+    pub closed: bool,
+}
+
+impl Polyline {
+    /// Adds a point unless it repeats the previous one.
+    fn push(&mut self, point: Vector2<f32>) {
+        if self
+            .points
+            .last()
+            .is_some_and(|last| (point - last).magnitude2() < EPSILON * EPSILON)
+        {
+            return;
+        }
+        self.points.push(point);
+    }
+
+    /// Moves the contour to `polylines`, dropping it when it is only a move or has
+    /// points at infinity or NaN, which can't be tessellated.
+    fn finish(&mut self, polylines: &mut Vec<Polyline>, drawn: bool) {
+        let mut polyline = std::mem::take(self);
+        if !polyline
+            .points
+            .iter()
+            .all(|point| point.x.is_finite() && point.y.is_finite())
+        {
+            return;
+        }
+        if polyline.closed && polyline.points.len() > 2 {
+            let first = polyline.points[0];
+            if polyline
+                .points
+                .last()
+                .is_some_and(|last| (first - last).magnitude2() < EPSILON * EPSILON)
+            {
+                polyline.points.pop();
+            }
+        }
+        match polyline.points.len() {
+            0 => {}
+            1 if !drawn => {}
+            1 => {
+                polyline.closed = false;
+                polylines.push(polyline);
+            }
+            _ => polylines.push(polyline),
+        }
+    }
+}
+
+/// Triangles produced by a `Tessellator`, ready to upload. Unlike tutorial4, which
+/// draws `u16` indices with `IndexFormat::Uint16`, indices are `u32` so large shapes
+/// and documents fit in one draw. Use `u16_indices` to fill tutorial4's index buffer.
+#[derive(Clone, Debug, Default)]
+pub struct Tessellation {
+    pub vertices: Vec<Vertex>,
+    /// One alpha per vertex: the color's alpha times how much of the pixel the shape
+    /// covers, which falls to zero across the feathered fringe.
+    pub coverage: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl Tessellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.coverage.clear();
+        self.indices.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// The indices as tutorial4's `u16`, or `None` once there are more vertices than
+    /// those can address. Split the shapes over several tessellations in that case.
+    pub fn u16_indices(&self) -> Option<Vec<u16>> {
+        if self.vertices.len() > u16::MAX as usize + 1 {
+            return None;
+        }
+        Some(self.indices.iter().map(|&index| index as u16).collect())
+    }
+
+    fn push_vertex(&mut self, position: Vector2<f32>, color: [f32; 4], coverage: f32) -> u32 {
+        self.vertices.push(Vertex {
+            position: [position.x, position.y, 0.0],
+            color: [color[0], color[1], color[2]],
+        });
+        self.coverage.push(color[3] * coverage);
+        self.vertices.len() as u32 - 1
+    }
+
+    fn push_quad(&mut self, a: u32, b: u32, c: u32, d: u32) {
+        self.indices.extend_from_slice(&[a, b, c, a, c, d]);
+    }
+
+    /// Cuts the triangles from `first_index` on wherever `measure` crosses one of
+    /// `values`. `measure` has to be affine, so anything that is linear in it between
+    /// those values can then be evaluated per vertex, like a gradient between its stops.
+    pub(crate) fn split_along(
+        &mut self,
+        first_index: usize,
+        measure: impl Fn(Vector2<f32>) -> f32,
+        values: &[f32],
+    ) {
+        for &value in values {
+            let side = |vertex: &Vertex| {
+                measure(Vector2::new(vertex.position[0], vertex.position[1])) - value
+            };
+            // Triangles sharing an edge share the vertex cutting it
+            let mut cuts = HashMap::new();
+            let triangles = self.indices.split_off(first_index);
+            for triangle in triangles.chunks(3) {
+                let sides = [0, 1, 2].map(|i| side(&self.vertices[triangle[i] as usize]));
+                if sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0) {
+                    self.indices.extend_from_slice(triangle);
+                    continue;
+                }
+
+                let (mut below, mut above) = (Vec::new(), Vec::new());
+                for i in 0..3 {
+                    let (p, q) = (triangle[i], triangle[(i + 1) % 3]);
+                    let (side_p, side_q) = (sides[i], sides[(i + 1) % 3]);
+                    if side_p <= 0.0 {
+                        below.push(p);
+                    }
+                    if side_p >= 0.0 {
+                        above.push(p);
+                    }
+                    if side_p * side_q < 0.0 {
+                        let (low, high) = (p.min(q), p.max(q));
+                        let cut = *cuts.entry((low, high)).or_insert_with(|| {
+                            let (a, b) = (low as usize, high as usize);
+                            let (side_a, side_b) =
+                                (side(&self.vertices[a]), side(&self.vertices[b]));
+                            let t = side_a / (side_a - side_b);
+                            let lerp = |a: f32, b: f32| a + (b - a) * t;
+                            let (va, vb) = (self.vertices[a], self.vertices[b]);
+                            let mut vertex = va;
+                            for i in 0..3 {
+                                vertex.position[i] = lerp(va.position[i], vb.position[i]);
+                                vertex.color[i] = lerp(va.color[i], vb.color[i]);
+                            }
+                            self.vertices.push(vertex);
+                            self.coverage.push(lerp(self.coverage[a], self.coverage[b]));
+                            self.vertices.len() as u32 - 1
+                        });
+                        below.push(cut);
+                        above.push(cut);
+                    }
+                }
+                for polygon in [below, above].iter() {
+                    for i in 1..polygon.len() - 1 {
+                        self.indices
+                            .extend_from_slice(&[polygon[0], polygon[i], polygon[i + 1]]);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn upload(&self, device: &wgpu::Device) -> GpuTessellation {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vector Vertex Buffer"),
+            contents: bytemuck::cast_slice(&self.vertices),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let coverage_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vector Coverage Buffer"),
+            contents: bytemuck::cast_slice(&self.coverage),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vector Index Buffer"),
+            contents: bytemuck::cast_slice(&self.indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        GpuTessellation {
+            vertex_buffer,
+            coverage_buffer,
+            index_buffer,
+            num_indices: self.indices.len() as u32,
+        }
+    }
+}
+
+pub struct GpuTessellation {
+    pub vertex_buffer: wgpu::Buffer,
+    pub coverage_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub num_indices: u32,
+}
+
+impl GpuTessellation {
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.num_indices == 0 {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.coverage_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+/// A cross section of a stroke: the offsets to its left and right edges per unit of
+/// half width. Consecutive sections are joined by quads.
+#[derive(Copy, Clone, Debug)]
+struct Section {
+    center: Vector2<f32>,
+    left: Vector2<f32>,
+    right: Vector2<f32>,
+    /// Zero for the sections that only carry the fringe past a cap.
+    coverage: f32,
+}
+
+impl Section {
+    fn new(center: Vector2<f32>, left: Vector2<f32>, right: Vector2<f32>) -> Self {
+        Self {
+            center,
+            left,
+            right,
+            coverage: 1.0,
+        }
+    }
+}
+
+/// Left normal of a unit direction.
+fn normal(direction: Vector2<f32>) -> Vector2<f32> {
+    Vector2::new(-direction.y, direction.x)
+}
+
+fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn rotate(v: Vector2<f32>, angle: f32) -> Vector2<f32> {
+    let (sin, cos) = angle.sin_cos();
+    Vector2::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
+
+/// Turns paths into triangles. Coordinates are in whatever units the paths use, so
+/// `tolerance` and `feather` should be about a pixel in those units.
+#[derive(Copy, Clone, Debug)]
+pub struct Tessellator {
+    /// Furthest the line segments replacing a curve may stray from it.
+    pub tolerance: f32,
+    /// Width of the fringe edges fade out over, zero turns anti-aliasing off.
+    pub feather: f32,
+}
+
+impl Default for Tessellator {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.25,
+            feather: 1.0,
+        }
+    }
+}
+
+impl Tessellator {
+    /// Strokes every contour of `path`, centered on its outline.
+    pub fn stroke(
+        &self,
+        output: &mut Tessellation,
+        path: &Path,
+        style: &StrokeStyle,
+        color: [f32; 4],
+    ) {
+        let feather = self.feather.max(0.0);
+        // Lines thinner than the fringe fade instead of getting thinner
+        let (half_width, color) = if style.width < feather {
+            let mut faded = color;
+            faded[3] *= style.width.max(0.0) / feather;
+            (0.0, faded)
+        } else {
+            // The fringe straddles the edge so the stroke keeps its width
+            ((style.width - feather) / 2.0, color)
+        };
+        if half_width <= 0.0 && feather <= 0.0 {
+            return;
+        }
+
+        for polyline in path.flatten(self.tolerance) {
+            if polyline.points.len() == 1 && style.cap == LineCap::Butt {
+                continue;
+            }
+            let sections = self.stroke_sections(&polyline, style, half_width);
+            let first = output.vertices.len() as u32;
+            for section in &sections {
+                let Section {
+                    center,
+                    left,
+                    right,
+                    coverage,
+                } = *section;
+                output.push_vertex(center + left * (half_width + feather), color, 0.0);
+                output.push_vertex(center + left * half_width, color, coverage);
+                output.push_vertex(center + right * half_width, color, coverage);
+                output.push_vertex(center + right * (half_width + feather), color, 0.0);
+            }
+
+            let count = sections.len() as u32;
+            let pairs = if polyline.closed { count } else { count - 1 };
+            for i in 0..pairs {
+                let a = first + i * 4;
+                let b = first + (i + 1) % count * 4;
+                output.push_quad(a + 1, a + 2, b + 2, b + 1);
+                if feather > 0.0 {
+                    output.push_quad(a, a + 1, b + 1, b);
+                    output.push_quad(a + 2, a + 3, b + 3, b + 2);
+                }
+            }
+        }
+    }
+
+    /// Sections along a polyline whose fully covered core is `half_width` either side.
+    fn stroke_sections(
+        &self,
+        polyline: &Polyline,
+        style: &StrokeStyle,
+        half_width: f32,
+    ) -> Vec<Section> {
+        let points = &polyline.points;
+        let n = points.len();
+        // Angle between the points of a round join or cap, fine enough for the fringe's edge
+        let radius = half_width + self.feather.max(0.0);
+        let arc_step = if radius > self.tolerance {
+            2.0 * (1.0 - self.tolerance / radius).acos()
+        } else {
+            std::f32::consts::FRAC_PI_2
+        }
+        .max(0.05);
+
+        let direction = |i: usize| (points[(i + 1) % n] - points[i]).normalize();
+        let length = |i: usize| (points[(i + 1) % n] - points[i]).magnitude();
+
+        let mut sections = Vec::new();
+        if n == 1 {
+            // Like browsers, cap both ends of a zero length contour facing along x
+            let d = Vector2::unit_x();
+            let mut cap = self.cap_sections(points[0], -d, normal(d), style, half_width, arc_step);
+            cap.reverse();
+            sections.extend(cap);
+            sections.extend(self.cap_sections(
+                points[0],
+                d,
+                normal(d),
+                style,
+                half_width,
+                arc_step,
+            ));
+            return sections;
+        }
+        if !polyline.closed {
+            let d = direction(0);
+            let mut cap = self.cap_sections(points[0], -d, normal(d), style, half_width, arc_step);
+            cap.reverse();
+            sections.extend(cap);
+        }
+
+        let joins = if polyline.closed { 0..n } else { 1..n - 1 };
+        for i in joins {
+            let previous = (i + n - 1) % n;
+            let (d0, d1) = (direction(previous), direction(i));
+            let (n0, n1) = (normal(d0), normal(d1));
+            let point = points[i];
+
+            let denominator = 1.0 + n0.dot(n1);
+            let miter = if denominator > EPSILON {
+                (n0 + n1) / denominator
+            } else {
+                Vector2::zero()
+            };
+            if cross(d0, d1).abs() < EPSILON && d0.dot(d1) > 0.0 {
+                sections.push(Section::new(point, n0, -n0));
+                continue;
+            }
+            if style.join == LineJoin::Miter
+                && denominator > EPSILON
+                && miter.magnitude() <= style.miter_limit
+            {
+                sections.push(Section::new(point, miter, -miter));
+                continue;
+            }
+
+            // Keep the inner corner from reaching past the neighbouring segments
+            let limit = if half_width > 0.0 {
+                (length(previous).min(length(i)) / half_width).max(1.0)
+            } else {
+                f32::INFINITY
+            };
+            let inner = if miter.magnitude() > limit {
+                miter.normalize() * limit
+            } else {
+                miter
+            };
+
+            let turns_left = cross(d0, d1) > 0.0;
+            let (from, to) = if turns_left { (-n0, -n1) } else { (n0, n1) };
+            let outer = if style.join == LineJoin::Round {
+                let angle = from.dot(to).clamp(-1.0, 1.0).acos();
+                let steps = (angle / arc_step).ceil().max(1.0) as usize;
+                let sign = if cross(from, to) >= 0.0 { 1.0 } else { -1.0 };
+                (0..=steps)
+                    .map(|step| rotate(from, sign * angle * step as f32 / steps as f32))
+                    .collect()
+            } else {
+                vec![from, to]
+            };
+            sections.extend(outer.into_iter().map(|outer| {
+                if turns_left {
+                    Section::new(point, inner, outer)
+                } else {
+                    Section::new(point, outer, -inner)
+                }
+            }));
+        }
+
+        if !polyline.closed {
+            let d = direction(n - 2);
+            sections.extend(self.cap_sections(
+                points[n - 1],
+                d,
+                normal(d),
+                style,
+                half_width,
+                arc_step,
+            ));
+        }
+        sections
+    }
+
+    /// Sections capping the end at `point`, ordered from the line towards the tip.
+    /// `outward` points away from the line and `left` is the line's left normal.
+    fn cap_sections(
+        &self,
+        point: Vector2<f32>,
+        outward: Vector2<f32>,
+        left: Vector2<f32>,
+        style: &StrokeStyle,
+        half_width: f32,
+        arc_step: f32,
+    ) -> Vec<Section> {
+        let feather = self.feather.max(0.0);
+        let fringe = |offset: f32| Section {
+            coverage: 0.0,
+            ..Section::new(point + outward * offset, left, -left)
+        };
+        // Like the sides, the fringe straddles where the stroke ends
+        match style.cap {
+            LineCap::Butt => {
+                let mut sections =
+                    vec![Section::new(point - outward * (feather / 2.0), left, -left)];
+                if feather > 0.0 {
+                    sections.push(fringe(feather / 2.0));
+                }
+                sections
+            }
+            LineCap::Square => {
+                let mut sections = vec![
+                    Section::new(point, left, -left),
+                    Section::new(point + outward * half_width, left, -left),
+                ];
+                if feather > 0.0 {
+                    sections.push(fringe(half_width + feather));
+                }
+                sections
+            }
+            LineCap::Round => {
+                let steps = (std::f32::consts::FRAC_PI_2 / arc_step).ceil().max(1.0) as usize;
+                (0..=steps)
+                    .rev()
+                    .map(|step| {
+                        let angle = std::f32::consts::FRAC_PI_2 * step as f32 / steps as f32;
+                        let (sin, cos) = angle.sin_cos();
+                        Section::new(
+                            point,
+                            outward * cos + left * sin,
+                            outward * cos - left * sin,
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Fills every contour of `path`, closing open ones. The fringe sits just outside
+    /// the outline, so fills look about half a feather larger than their path.
+    pub fn fill(&self, output: &mut Tessellation, path: &Path, rule: FillRule, color: [f32; 4]) {
+        let contours = path
+            .flatten(self.tolerance)
+            .into_iter()
+            .filter(|polyline| polyline.points.len() > 2)
+            .collect::<Vec<_>>();
+        let edges = contours
+            .iter()
+            .flat_map(|contour| {
+                let points = &contour.points;
+                (0..points.len()).map(move |i| (points[i], points[(i + 1) % points.len()]))
+            })
+            .collect::<Vec<_>>();
+        if edges.is_empty() {
+            return;
+        }
+
+        fill_trapezoids(output, &edges, rule, color);
+
+        if self.feather > 0.0 {
+            for contour in &contours {
+                fill_fringe(output, &contour.points, &edges, rule, color, self.feather);
+            }
+        }
+    }
+}
+
+/// Splits the filled area into horizontal bands at every vertex and crossing, where
+/// no edges cross inside a band, and fills the spans between edges in each band.
+fn fill_trapezoids(
+    output: &mut Tessellation,
+    edges: &[(Vector2<f32>, Vector2<f32>)],
+    rule: FillRule,
+    color: [f32; 4],
+) {
+    let mut ys = edges.iter().map(|(a, _)| a.y).collect::<Vec<_>>();
+    for (i, (a, b)) in edges.iter().enumerate() {
+        for (c, d) in &edges[i + 1..] {
+            let denominator = cross(b - a, d - c);
+            if denominator.abs() < EPSILON {
+                continue;
+            }
+            let t = cross(c - a, d - c) / denominator;
+            let u = cross(c - a, b - a) / denominator;
+            if t > EPSILON && t < 1.0 - EPSILON && u > EPSILON && u < 1.0 - EPSILON {
+                ys.push(a.y + (b.y - a.y) * t);
+            }
+        }
+    }
+    ys.sort_by(|a, b| a.total_cmp(b));
+    ys.dedup_by(|a, b| (*a - *b).abs() < EPSILON);
+
+    let mut crossings = Vec::new();
+    for band in ys.windows(2) {
+        let (top, bottom) = (band[0], band[1]);
+        crossings.clear();
+        for (a, b) in edges {
+            let (min, max) = if a.y < b.y { (a, b) } else { (b, a) };
+            if max.y - min.y < EPSILON || min.y > top + EPSILON || max.y < bottom - EPSILON {
+                continue;
+            }
+            let x_at =
+                |y: f32| min.x + (max.x - min.x) * ((y - min.y) / (max.y - min.y)).clamp(0.0, 1.0);
+            let winding = if a.y < b.y { 1 } else { -1 };
+            crossings.push((x_at(top), x_at(bottom), winding));
+        }
+        crossings.sort_by(|a, b| (a.0 + a.1).total_cmp(&(b.0 + b.1)));
+
+        let mut winding = 0;
+        let mut start = None;
+        for &(x_top, x_bottom, edge_winding) in &crossings {
+            let was_inside = rule.is_inside(winding);
+            winding += edge_winding;
+            match (was_inside, rule.is_inside(winding)) {
+                (false, true) => start = Some((x_top, x_bottom)),
+                (true, false) => {
+                    if let Some((start_top, start_bottom)) = start.take() {
+                        let a = output.push_vertex(Vector2::new(start_top, top), color, 1.0);
+                        let b = output.push_vertex(Vector2::new(x_top, top), color, 1.0);
+                        let c = output.push_vertex(Vector2::new(x_bottom, bottom), color, 1.0);
+                        let d = output.push_vertex(Vector2::new(start_bottom, bottom), color, 1.0);
+                        output.push_quad(a, b, c, d);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Winding number of `point` against the closed contours made of `edges`.
+fn winding_number(point: Vector2<f32>, edges: &[(Vector2<f32>, Vector2<f32>)]) -> i32 {
+    let mut winding = 0;
+    for (a, b) in edges {
+        let side = cross(b - a, point - a);
+        if a.y <= point.y && b.y > point.y && side > 0.0 {
+            winding += 1;
+        } else if b.y <= point.y && a.y > point.y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+/// A strip fading from the contour outwards, away from the filled side.
+fn fill_fringe(
+    output: &mut Tessellation,
+    points: &[Vector2<f32>],
+    edges: &[(Vector2<f32>, Vector2<f32>)],
+    rule: FillRule,
+    color: [f32; 4],
+    feather: f32,
+) {
+    let n = points.len();
+    let direction = |i: usize| (points[(i + 1) % n] - points[i]).normalize();
+
+    // Probe next to the longest edge to find which side of the contour is filled
+    let longest = (0..n)
+        .max_by(|a, b| {
+            let length = |i: usize| (points[(i + 1) % n] - points[i]).magnitude2();
+            length(*a).total_cmp(&length(*b))
+        })
+        .unwrap();
+    let middle = (points[longest] + points[(longest + 1) % n]) / 2.0;
+    let probe = middle + normal(direction(longest)) * feather * 0.1;
+    let outward = if rule.is_inside(winding_number(probe, edges)) {
+        -1.0
+    } else {
+        1.0
+    };
+
+    let first = output.vertices.len() as u32;
+    for (i, point) in points.iter().enumerate() {
+        let n0 = normal(direction((i + n - 1) % n)) * outward;
+        let n1 = normal(direction(i)) * outward;
+        let denominator = 1.0 + n0.dot(n1);
+        let mut miter = if denominator > EPSILON {
+            (n0 + n1) / denominator
+        } else {
+            n0
+        };
+        // Sharp corners would throw the fringe far out, so cut them short
+        if miter.magnitude() > 2.0 {
+            miter = miter.normalize() * 2.0;
+        }
+        output.push_vertex(*point, color, 1.0);
+        output.push_vertex(point + miter * feather, color, 0.0);
+    }
+    for i in 0..n as u32 {
+        let a = first + i * 2;
+        let b = first + (i + 1) % n as u32 * 2;
+        output.push_quad(a, a + 1, b + 1, b);
+    }
+}
+
+/// Draws `GpuTessellation`s with alpha blending, projecting them with the matrix set
+/// by `set_projection`.
+pub struct VectorRenderer {
+    projection_buffer: wgpu::Buffer,
+    projection_bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl VectorRenderer {
+    pub fn new(device: &wgpu::Device, color_format: wgpu::TextureFormat) -> Self {
+        let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+        let projection_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vector Projection Buffer"),
+            contents: bytemuck::cast_slice(&[identity]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let projection_bind_group_layout = projection_bind_group_layout(device);
+        let projection_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &projection_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: projection_buffer.as_entire_binding(),
+            }],
+            label: Some("vector_projection_bind_group"),
+        });
+
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("vector.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("vector.frag.spv"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Vector Pipeline Layout"),
+            bind_group_layouts: &[&projection_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let alpha_blend = wgpu::BlendState {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Vector Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vs_module,
+                entry_point: "main",
+                buffers: &[Vertex::desc(), Vertex::coverage_desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fs_module,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format: color_format,
+                    alpha_blend: alpha_blend.clone(),
+                    color_blend: alpha_blend,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // Strokes and trapezoids come out in either winding
+                cull_mode: wgpu::CullMode::None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        Self {
+            projection_buffer,
+            projection_bind_group,
+            render_pipeline,
+        }
+    }
+
+    pub fn set_projection(&self, queue: &wgpu::Queue, projection: Matrix4<f32>) {
+        let projection: [[f32; 4]; 4] = projection.into();
+        queue.write_buffer(
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(&[projection]),
+        );
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, mesh: &'a GpuTessellation) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.projection_bind_group, &[]);
+        mesh.draw(render_pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHARP: Tessellator = Tessellator {
+        tolerance: 0.01,
+        feather: 0.0,
+    };
+
+    /// Covered area, weighting each triangle by its average coverage.
+    fn area(output: &Tessellation) -> f32 {
+        output
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+                let point = |i: usize| {
+                    Vector2::new(
+                        output.vertices[i].position[0],
+                        output.vertices[i].position[1],
+                    )
+                };
+                let coverage = (output.coverage[a] + output.coverage[b] + output.coverage[c]) / 3.0;
+                cross(point(b) - point(a), point(c) - point(a)).abs() / 2.0 * coverage
+            })
+            .sum()
+    }
+
+    fn fill_area(tessellator: &Tessellator, path: &Path, rule: FillRule) -> f32 {
+        let mut output = Tessellation::new();
+        tessellator.fill(&mut output, path, rule, [1.0; 4]);
+        area(&output)
+    }
+
+    fn stroke_area(path: &Path, style: &StrokeStyle) -> f32 {
+        let mut output = Tessellation::new();
+        SHARP.stroke(&mut output, path, style, [1.0; 4]);
+        area(&output)
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() <= expected * 1e-3,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    /// A five pointed star drawn in one stroke, so its middle winds twice.
+    fn pentagram(radius: f32) -> Path {
+        let points = (0..5)
+            .map(|i| {
+                let angle = i as f32 * 4.0 * std::f32::consts::PI / 5.0;
+                Vector2::new(angle.sin(), angle.cos()) * radius
+            })
+            .collect::<Vec<_>>();
+        Path::polyline(&points, true)
+    }
+
+    #[test]
+    fn vertex_matches_tutorial4() {
+        assert_eq!(std::mem::size_of::<Vertex>(), 24);
+    }
+
+    #[test]
+    fn u16_indices() {
+        let mut output = Tessellation::new();
+        let square = Path::rect(Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        SHARP.fill(&mut output, &square, FillRule::NonZero, [1.0; 4]);
+        let indices = output.u16_indices().unwrap();
+        assert!(indices
+            .iter()
+            .zip(&output.indices)
+            .all(|(a, b)| *a as u32 == *b));
+
+        let vertex = output.vertices[0];
+        output.vertices.resize(u16::MAX as usize + 1, vertex);
+        assert!(output.u16_indices().is_some());
+        output.vertices.push(vertex);
+        assert!(output.u16_indices().is_none());
+    }
+
+    #[test]
+    fn square_fill() {
+        let square = Path::rect(Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        for rule in [FillRule::NonZero, FillRule::EvenOdd].iter() {
+            assert_near(fill_area(&SHARP, &square, *rule), 100.0);
+        }
+    }
+
+    #[test]
+    fn donut_fill() {
+        let mut donut = Path::rect(Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        donut
+            .move_to(Vector2::new(3.0, 3.0))
+            .line_to(Vector2::new(7.0, 3.0))
+            .line_to(Vector2::new(7.0, 7.0))
+            .line_to(Vector2::new(3.0, 7.0))
+            .close();
+        assert_near(fill_area(&SHARP, &donut, FillRule::EvenOdd), 84.0);
+        // Both squares wind the same way, so non-zero fills the hole
+        assert_near(fill_area(&SHARP, &donut, FillRule::NonZero), 100.0);
+    }
+
+    #[test]
+    fn pentagram_fill() {
+        let radius = 100.0f32;
+        let inner = radius * 72f32.to_radians().cos() / 36f32.to_radians().cos();
+        let star = 5.0 * radius * inner * 36f32.to_radians().sin();
+        let pentagon = 2.5 * inner * inner * 72f32.to_radians().sin();
+        let path = pentagram(radius);
+        assert_near(fill_area(&SHARP, &path, FillRule::NonZero), star);
+        assert_near(fill_area(&SHARP, &path, FillRule::EvenOdd), star - pentagon);
+    }
+
+    #[test]
+    fn stroke_caps() {
+        let line = Path::polyline(&[Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0)], false);
+        let style = |cap| StrokeStyle {
+            width: 4.0,
+            cap,
+            ..Default::default()
+        };
+        assert_near(stroke_area(&line, &style(LineCap::Butt)), 40.0);
+        assert_near(stroke_area(&line, &style(LineCap::Square)), 56.0);
+        let round = 40.0 + std::f32::consts::PI * 4.0;
+        assert!((stroke_area(&line, &style(LineCap::Round)) - round).abs() < 0.1);
+    }
+
+    #[test]
+    fn closed_stroke() {
+        // A miter joined square outline covers the ring between its inner and outer edges
+        let square = Path::rect(Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        let style = StrokeStyle {
+            width: 2.0,
+            ..Default::default()
+        };
+        assert_near(stroke_area(&square, &style), 144.0 - 64.0);
+    }
+}
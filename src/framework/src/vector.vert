@@ -0,0 +1,17 @@
+#version 450
+
+// tutorial4's vertex, plus the coverage of anti-aliased edges from a second buffer
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec3 a_color;
+layout(location=2) in float a_coverage;
+
+layout(location=0) out vec4 v_color;
+
+layout(set=0, binding=0) uniform Projection {
+    mat4 u_view_proj;
+};
+
+void main() {
+    v_color = vec4(a_color, a_coverage);
+    gl_Position = u_view_proj * vec4(a_position, 1.0);
+}
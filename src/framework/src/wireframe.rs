@@ -0,0 +1,69 @@
+/// How triangle meshes are rasterized.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DrawMode {
+    Fill,
+    Wireframe,
+    Points,
+}
+
+impl DrawMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fill => Self::Wireframe,
+            Self::Wireframe => Self::Points,
+            Self::Points => Self::Fill,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fill => "fill",
+            Self::Wireframe => "wireframe",
+            Self::Points => "points",
+        }
+    }
+
+    /// Primitive state for drawing triangle meshes in this mode. With `native` the
+    /// rasterizer's polygon mode is used, which needs `Features::NON_FILL_POLYGON_MODE`.
+    /// Otherwise the topology changes instead and the mesh has to be drawn with
+    /// `GpuMesh::draw_edges` or `GpuMesh::draw_points`.
+    pub fn primitive_state(self, native: bool) -> wgpu::PrimitiveState {
+        let (topology, polygon_mode) = match (self, native) {
+            (Self::Fill, _) => (
+                wgpu::PrimitiveTopology::TriangleList,
+                wgpu::PolygonMode::Fill,
+            ),
+            (Self::Wireframe, true) => (
+                wgpu::PrimitiveTopology::TriangleList,
+                wgpu::PolygonMode::Line,
+            ),
+            (Self::Points, true) => (
+                wgpu::PrimitiveTopology::TriangleList,
+                wgpu::PolygonMode::Point,
+            ),
+            (Self::Wireframe, false) => {
+                (wgpu::PrimitiveTopology::LineList, wgpu::PolygonMode::Fill)
+            }
+            (Self::Points, false) => (wgpu::PrimitiveTopology::PointList, wgpu::PolygonMode::Fill),
+        };
+
+        wgpu::PrimitiveState {
+            topology,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Back edges are part of the wireframe, and lines and points have no facing
+            cull_mode: if self == Self::Fill {
+                wgpu::CullMode::Back
+            } else {
+                wgpu::CullMode::None
+            },
+            polygon_mode,
+        }
+    }
+}
+
+/// The subset of `Features::NON_FILL_POLYGON_MODE` the adapter supports, pass it to
+/// `request_device` so wireframes can be drawn natively where possible.
+pub fn polygon_mode_features(adapter: &wgpu::Adapter) -> wgpu::Features {
+    adapter.features() & wgpu::Features::NON_FILL_POLYGON_MODE
+}
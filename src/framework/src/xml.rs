@@ -0,0 +1,84 @@
+//! Just enough of an XML tree to walk the .tmx, .tsx and .svg files the framework
+//! loads, built on xml-rs.
+
+use std::str::FromStr;
+
+use anyhow::*;
+use xml::reader::{EventReader, XmlEvent};
+
+#[derive(Clone, Debug)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub elements: Vec<Element>,
+    pub text: String,
+}
+
+/// Reads the root element of a document, with namespace prefixes dropped from names.
+pub fn parse(text: &str) -> Result<Element> {
+    let mut stack: Vec<Element> = Vec::new();
+    for event in EventReader::from_str(text) {
+        match event.context("Invalid XML")? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => stack.push(Element {
+                name: name.local_name,
+                attributes: attributes
+                    .into_iter()
+                    .map(|attribute| (attribute.name.local_name, attribute.value))
+                    .collect(),
+                elements: Vec::new(),
+                text: String::new(),
+            }),
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().context("Unbalanced XML")?;
+                match stack.last_mut() {
+                    Some(parent) => parent.elements.push(element),
+                    None => return Ok(element),
+                }
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+    bail!("The document has no root element")
+}
+
+impl Element {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn parse<T: FromStr>(&self, name: &str) -> Result<T> {
+        let value = self
+            .attribute(name)
+            .with_context(|| format!("<{}> has no {} attribute", self.name, name))?;
+        value
+            .parse()
+            .map_err(|_| anyhow!("<{}> has an invalid {} {:?}", self.name, name, value))
+    }
+
+    pub fn parse_or<T: FromStr>(&self, name: &str, default: T) -> Result<T> {
+        match self.attribute(name) {
+            Some(_) => self.parse(name),
+            None => Ok(default),
+        }
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements.iter().find(|element| element.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements
+            .iter()
+            .filter(move |element| element.name == name)
+    }
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec3 a_color;
+
+layout(location=1) out vec4 special_color;
+
+void main() {
+    special_color = vec4(a_position, 1.0);
+    gl_Position = special_color;
+}
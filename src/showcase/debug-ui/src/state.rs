@@ -0,0 +1,448 @@
+use std::iter;
+use std::time::Instant;
+
+use framework::{
+    sprite::TextureId,
+    text::Font,
+    texture,
+    ui::{Panel, Ui},
+};
+use image::GenericImageView;
+use wgpu::util::DeviceExt;
+use winit::{event::*, window::Window};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float3,
+                },
+            ],
+        }
+    }
+}
+
+/// tutorial4's pentagon.
+const PENTAGON: &[[f32; 3]] = &[
+    [-0.0868241, 0.49240386, 0.0],
+    [-0.49513406, 0.06958647, 0.0],
+    [-0.21918549, -0.44939706, 0.0],
+    [0.35966998, -0.3473291, 0.0],
+    [0.44147372, 0.2347359, 0.0],
+];
+const PENTAGON_INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
+
+/// tutorial3's triangle.
+const TRIANGLE: &[[f32; 3]] = &[[0.0, 0.5, 0.0], [-0.5, -0.5, 0.0], [0.5, -0.5, 0.0]];
+const TRIANGLE_INDICES: &[u16] = &[0, 1, 2];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Shape {
+    Pentagon,
+    /// tutorial4-buffer-challenge's grey pentagon.
+    AltPentagon,
+    Triangle,
+}
+
+impl Shape {
+    const ALL: [Shape; 3] = [Shape::Pentagon, Shape::AltPentagon, Shape::Triangle];
+
+    fn name(self) -> &'static str {
+        match self {
+            Shape::Pentagon => "Pentagon",
+            Shape::AltPentagon => "Alt pentagon",
+            Shape::Triangle => "Triangle",
+        }
+    }
+
+    fn mesh(self) -> (Vec<Vertex>, &'static [u16]) {
+        let (positions, indices, color) = match self {
+            Shape::Pentagon => (PENTAGON, PENTAGON_INDICES, [0.5, 0.0, 0.5]),
+            Shape::AltPentagon => (PENTAGON, PENTAGON_INDICES, [0.5, 0.5, 0.5]),
+            Shape::Triangle => (TRIANGLE, TRIANGLE_INDICES, [0.9, 0.6, 0.1]),
+        };
+        let vertices = positions
+            .iter()
+            .map(|position| Vertex {
+                position: *position,
+                color,
+            })
+            .collect();
+        (vertices, indices)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Pipeline {
+    /// Colors from the vertex buffer.
+    Color,
+    /// tutorial3-pipeline-challenge's shader, coloring by position.
+    Challenge,
+}
+
+struct Mesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+/// A texture shown in the texture panel.
+struct LoadedTexture {
+    name: &'static str,
+    size: [u32; 2],
+    id: TextureId,
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    clear_color: wgpu::Color,
+    /// Sets the clear color from the cursor position, like tutorial2-swapchain-challenge.
+    follow_cursor: bool,
+
+    color_pipeline: wgpu::RenderPipeline,
+    challenge_pipeline: wgpu::RenderPipeline,
+    pipeline: Pipeline,
+    meshes: Vec<Mesh>,
+    shape: Shape,
+
+    ui: Ui,
+    adapter_info: wgpu::AdapterInfo,
+    textures: Vec<LoadedTexture>,
+    frame_time: f32,
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let adapter_info = adapter.get_info();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, vs_module, fs_module| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: vs_module,
+                    entry_point: "main",
+                    buffers: &[Vertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: fs_module,
+                    entry_point: "main",
+                    targets: &[wgpu::ColorTargetState {
+                        format: sc_desc.format,
+                        alpha_blend: wgpu::BlendState::REPLACE,
+                        color_blend: wgpu::BlendState::REPLACE,
+                        write_mask: wgpu::ColorWrite::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: wgpu::CullMode::Back,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+            })
+        };
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("shader.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("shader.frag.spv"));
+        let color_pipeline = create_pipeline("Color Pipeline", &vs_module, &fs_module);
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("challenge.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("challenge.frag.spv"));
+        let challenge_pipeline = create_pipeline("Challenge Pipeline", &vs_module, &fs_module);
+
+        let meshes = Shape::ALL
+            .iter()
+            .map(|shape| {
+                let (vertices, indices) = shape.mesh();
+                Mesh {
+                    vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Vertex Buffer"),
+                        contents: bytemuck::cast_slice(&vertices),
+                        usage: wgpu::BufferUsage::VERTEX,
+                    }),
+                    index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Index Buffer"),
+                        contents: bytemuck::cast_slice(indices),
+                        usage: wgpu::BufferUsage::INDEX,
+                    }),
+                    num_indices: indices.len() as u32,
+                }
+            })
+            .collect();
+
+        let font = Font::from_bytes(include_bytes!("DejaVuSans.ttf").to_vec()).unwrap();
+        let mut ui = Ui::new(&device, &queue, sc_desc.format, font);
+        let images: [(&'static str, &[u8]); 2] = [
+            ("happy-tree.png", include_bytes!("happy-tree.png")),
+            (
+                "happy-tree-cartoon.png",
+                include_bytes!("happy-tree-cartoon.png"),
+            ),
+        ];
+        let textures = images
+            .iter()
+            .map(|(name, bytes)| {
+                let img = image::load_from_memory(bytes).unwrap();
+                let texture =
+                    texture::Texture::from_image(&device, &queue, &img, Some(name), false).unwrap();
+                LoadedTexture {
+                    name,
+                    size: [img.width(), img.height()],
+                    id: ui.add_texture(&device, &texture),
+                }
+            })
+            .collect();
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            clear_color: wgpu::Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 1.0,
+            },
+            follow_cursor: false,
+            color_pipeline,
+            challenge_pipeline,
+            pipeline: Pipeline::Color,
+            meshes,
+            shape: Shape::Pentagon,
+            ui,
+            adapter_info,
+            textures,
+            frame_time: 0.0,
+            last_update: Instant::now(),
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        if self.ui.handle_event(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::CursorMoved { position, .. } if self.follow_cursor => {
+                if !self.ui.is_pointer_over() {
+                    self.clear_color.r = (position.x / self.size.width as f64).clamp(0.0, 1.0);
+                    self.clear_color.g = (position.y / self.size.height as f64).clamp(0.0, 1.0);
+                    self.clear_color.b = 1.0;
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F1),
+                        ..
+                    },
+                ..
+            } => {
+                self.ui.visible = !self.ui.visible;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = (now - self.last_update).as_secs_f32();
+        self.last_update = now;
+        // Smoothed so the number is readable
+        self.frame_time += (dt - self.frame_time) * 0.05;
+
+        self.ui
+            .begin_frame(self.size.width as f32, self.size.height as f32);
+        let ui = &mut self.ui;
+
+        let clear_color = &mut self.clear_color;
+        let follow_cursor = &mut self.follow_cursor;
+        ui.panel("Clear color", |panel| {
+            panel.color_edit("clear_color", clear_color);
+            panel.checkbox("Follow cursor", follow_cursor);
+        });
+
+        let pipeline = &mut self.pipeline;
+        let shape = &mut self.shape;
+        ui.panel("Scene", |panel| {
+            panel.label("Pipeline");
+            panel.radio("Vertex colors", pipeline, Pipeline::Color);
+            panel.radio("Challenge (position colors)", pipeline, Pipeline::Challenge);
+            panel.separator();
+            panel.label("Shape");
+            for option in Shape::ALL.iter() {
+                panel.radio(option.name(), shape, *option);
+            }
+        });
+
+        let textures = &self.textures;
+        ui.panel("Textures", |panel| {
+            for texture in textures {
+                panel.label(&format!(
+                    "{} ({}x{})",
+                    texture.name, texture.size[0], texture.size[1]
+                ));
+                panel.image(texture.id, texture.size);
+            }
+        });
+
+        let info = &self.adapter_info;
+        let sc_desc = &self.sc_desc;
+        let frame_time = self.frame_time;
+        ui.panel("Adapter", |panel| {
+            adapter_panel(panel, info, sc_desc, frame_time)
+        });
+
+        self.ui.end_frame(&self.device, &self.queue);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(match self.pipeline {
+                Pipeline::Color => &self.color_pipeline,
+                Pipeline::Challenge => &self.challenge_pipeline,
+            });
+            let mesh = &self.meshes[Shape::ALL
+                .iter()
+                .position(|shape| *shape == self.shape)
+                .unwrap()];
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+
+            // The overlay goes last so it ends up over the scene
+            self.ui.render(&mut render_pass);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
+
+fn adapter_panel(
+    panel: &mut Panel,
+    info: &wgpu::AdapterInfo,
+    sc_desc: &wgpu::SwapChainDescriptor,
+    frame_time: f32,
+) {
+    panel.label(&info.name);
+    panel.label(&format!("{:?} on {:?}", info.device_type, info.backend));
+    panel.label(&format!(
+        "Vendor 0x{:04x}, device 0x{:04x}",
+        info.vendor, info.device
+    ));
+    panel.separator();
+    panel.label(&format!(
+        "Swap chain {}x{} {:?}, {:?}",
+        sc_desc.width, sc_desc.height, sc_desc.format, sc_desc.present_mode
+    ));
+    panel.label(&format!(
+        "{:.2} ms ({:.0} FPS)",
+        frame_time * 1000.0,
+        1.0 / frame_time.max(1e-6)
+    ));
+}
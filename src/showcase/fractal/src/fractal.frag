@@ -0,0 +1,145 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform View {
+    // Double-single values store the high floats in xy and the low floats in zw
+    vec4 u_center;
+    vec4 u_julia;
+    vec2 u_scale;
+    // Size of the whole image and where this draw sits in it, for tiled exports
+    vec2 u_resolution;
+    vec2 u_offset;
+    int u_max_iterations;
+    int u_julia_mode;
+    int u_palette;
+    int u_emulated;
+    int _padding0;
+    int _padding1;
+};
+
+const float BAILOUT = 256.0;
+const float TAU = 6.28318530718;
+
+// Emulated double precision, each value is an unevaluated sum of a high and a low float.
+// The error terms only survive if the compiler evaluates every operation exactly as
+// written. Without `precise` drivers may fuse or simplify float math, and folding
+// `t - (t - a)` or `b - (s - a)` would quietly leave single precision, so every
+// intermediate value here is `precise`.
+vec2 quick_two_sum(float a, float b) {
+    precise float s = a + b;
+    precise float error = b - (s - a);
+    return vec2(s, error);
+}
+
+vec2 two_sum(float a, float b) {
+    precise float s = a + b;
+    precise float v = s - a;
+    precise float error = (a - (s - v)) + (b - v);
+    return vec2(s, error);
+}
+
+vec2 split(float a) {
+    precise float t = a * 4097.0;
+    precise float high = t - (t - a);
+    precise float low = a - high;
+    return vec2(high, low);
+}
+
+vec2 two_product(float a, float b) {
+    precise float p = a * b;
+    vec2 sa = split(a);
+    vec2 sb = split(b);
+    precise float error = ((sa.x * sb.x - p) + sa.x * sb.y + sa.y * sb.x) + sa.y * sb.y;
+    return vec2(p, error);
+}
+
+vec2 ds_add(vec2 a, vec2 b) {
+    vec2 s = two_sum(a.x, b.x);
+    precise float low = s.y + a.y + b.y;
+    return quick_two_sum(s.x, low);
+}
+
+vec2 ds_mul(vec2 a, vec2 b) {
+    vec2 p = two_product(a.x, b.x);
+    precise float low = p.y + a.x * b.y + a.y * b.x;
+    return quick_two_sum(p.x, low);
+}
+
+// Returns the iteration count, or -1 for points inside the set
+float iterate_single(vec2 z, vec2 c, out float magnitude2) {
+    for (int i = 0; i < u_max_iterations; i++) {
+        magnitude2 = dot(z, z);
+        if (magnitude2 > BAILOUT) {
+            return float(i);
+        }
+        z = vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+    }
+    return -1.0;
+}
+
+float iterate_emulated(vec2 zx, vec2 zy, vec2 cx, vec2 cy, out float magnitude2) {
+    for (int i = 0; i < u_max_iterations; i++) {
+        vec2 x2 = ds_mul(zx, zx);
+        vec2 y2 = ds_mul(zy, zy);
+        magnitude2 = x2.x + y2.x;
+        if (magnitude2 > BAILOUT) {
+            return float(i);
+        }
+        // Doubling is exact, so it can skip the emulated multiply
+        zy = ds_add(2.0 * ds_mul(zx, zy), cy);
+        zx = ds_add(ds_add(x2, -y2), cx);
+    }
+    return -1.0;
+}
+
+vec3 palette(float t) {
+    if (u_palette == 1) {
+        // Fire
+        return 0.5 + 0.5 * cos(TAU * (vec3(1.0, 1.0, 0.5) * t + vec3(0.8, 0.9, 0.3)));
+    } else if (u_palette == 2) {
+        // Ocean
+        return vec3(0.2, 0.4, 0.6) + vec3(0.3, 0.3, 0.4) * cos(TAU * (t + vec3(0.0, 0.1, 0.2)));
+    } else if (u_palette == 3) {
+        // Grayscale
+        return vec3(0.5 + 0.5 * cos(TAU * t));
+    }
+    // Rainbow
+    return 0.5 + 0.5 * cos(TAU * (t + vec3(0.0, 0.33, 0.67)));
+}
+
+void main() {
+    // Pixel position relative to the center with y pointing up the imaginary axis
+    vec2 pixel = gl_FragCoord.xy + u_offset - 0.5 * u_resolution;
+    pixel.y = -pixel.y;
+
+    float magnitude2 = 0.0;
+    float iterations;
+    if (u_emulated != 0) {
+        vec2 scale = u_scale;
+        vec2 x = ds_add(vec2(u_center.x, u_center.z), ds_mul(vec2(pixel.x, 0.0), scale));
+        vec2 y = ds_add(vec2(u_center.y, u_center.w), ds_mul(vec2(pixel.y, 0.0), scale));
+        if (u_julia_mode != 0) {
+            iterations = iterate_emulated(x, y, vec2(u_julia.x, u_julia.z), vec2(u_julia.y, u_julia.w), magnitude2);
+        } else {
+            iterations = iterate_emulated(vec2(0.0), vec2(0.0), x, y, magnitude2);
+        }
+    } else {
+        vec2 point = u_center.xy + pixel * u_scale.x;
+        if (u_julia_mode != 0) {
+            iterations = iterate_single(point, u_julia.xy, magnitude2);
+        } else {
+            iterations = iterate_single(vec2(0.0), point, magnitude2);
+        }
+    }
+
+    vec3 color = vec3(0.0);
+    if (iterations >= 0.0) {
+        // Fractional escape count so the bands blend smoothly
+        float smooth_iterations = iterations + 1.0 - log2(0.5 * log2(magnitude2));
+        color = palette(smooth_iterations * 0.02);
+    }
+    // The palettes are picked in sRGB, the target encodes the output again
+    f_color = vec4(pow(color, vec3(2.2)), 1.0);
+}
@@ -0,0 +1,257 @@
+use std::iter;
+
+use anyhow::*;
+use bytemuck::Zeroable;
+use framework::{fullscreen, texture};
+use wgpu::util::DeviceExt;
+
+pub const PALETTES: &[&str] = &["Rainbow", "Fire", "Ocean", "Grayscale"];
+
+/// Below this many units per pixel single precision floats can't tell neighbouring pixels
+/// apart any more and the image turns blocky.
+pub const EMULATED_SCALE: f64 = 1e-5;
+/// Emulated doubles carry about 48 bits of mantissa, deeper zooms break up as well.
+pub const MIN_SCALE: f64 = 1e-13;
+
+const EXPORT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+/// Exports are rendered in squares of this size so their resolution isn't limited by the
+/// maximum texture size.
+const TILE_SIZE: u32 = 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Kind {
+    Mandelbrot,
+    /// The Julia set for the constant `c`.
+    Julia {
+        c: [f64; 2],
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Precision {
+    /// Switches to emulated doubles once the zoom needs them.
+    Auto,
+    Single,
+    Emulated,
+}
+
+impl Precision {
+    pub fn next(self) -> Self {
+        match self {
+            Precision::Auto => Precision::Single,
+            Precision::Single => Precision::Emulated,
+            Precision::Emulated => Precision::Auto,
+        }
+    }
+}
+
+/// Everything that decides what the fractal looks like.
+#[derive(Copy, Clone, Debug)]
+pub struct View {
+    pub kind: Kind,
+    pub center: [f64; 2],
+    /// Distance in the complex plane between neighbouring pixels.
+    pub scale: f64,
+    pub max_iterations: u32,
+    pub palette: u32,
+    pub precision: Precision,
+}
+
+impl View {
+    pub fn emulated(&self) -> bool {
+        match self.precision {
+            Precision::Auto => self.scale < EMULATED_SCALE,
+            Precision::Single => false,
+            Precision::Emulated => true,
+        }
+    }
+}
+
+/// Splits a double into a high float and the float closest to what it missed.
+fn split(value: f64) -> (f32, f32) {
+    let high = value as f32;
+    (high, (value - high as f64) as f32)
+}
+
+/// Mirrors the `View` block in fractal.frag.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ViewUniform {
+    center: [f32; 4],
+    julia: [f32; 4],
+    scale: [f32; 2],
+    resolution: [f32; 2],
+    offset: [f32; 2],
+    max_iterations: i32,
+    julia_mode: i32,
+    palette: i32,
+    emulated: i32,
+    _padding: [i32; 2],
+}
+
+impl ViewUniform {
+    fn new(view: &View, resolution: (u32, u32), offset: (u32, u32)) -> Self {
+        let double_single = |[x, y]: [f64; 2]| {
+            let (x_high, x_low) = split(x);
+            let (y_high, y_low) = split(y);
+            [x_high, y_high, x_low, y_low]
+        };
+        let (julia_mode, julia) = match view.kind {
+            Kind::Mandelbrot => (0, [0.0; 4]),
+            Kind::Julia { c } => (1, double_single(c)),
+        };
+        let (scale_high, scale_low) = split(view.scale);
+        Self {
+            center: double_single(view.center),
+            julia,
+            scale: [scale_high, scale_low],
+            resolution: [resolution.0 as f32, resolution.1 as f32],
+            offset: [offset.0 as f32, offset.1 as f32],
+            max_iterations: view.max_iterations as i32,
+            julia_mode,
+            palette: view.palette as i32,
+            emulated: view.emulated() as i32,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Draws the Mandelbrot or a Julia set over the whole target with a fullscreen triangle.
+pub struct Fractal {
+    view_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    export_pipeline: wgpu::RenderPipeline,
+}
+
+impl Fractal {
+    pub fn new(device: &wgpu::Device, color_format: wgpu::TextureFormat) -> Self {
+        let view_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("View Buffer"),
+            contents: bytemuck::bytes_of(&ViewUniform::zeroed()),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[fullscreen::uniform_entry(0)],
+            label: Some("view_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: view_buffer.as_entire_binding(),
+            }],
+            label: Some("view_bind_group"),
+        });
+
+        let vs_module = fullscreen::vertex_module(device);
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("fractal.frag.spv"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Fractal Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, format| {
+            fullscreen::create_fullscreen_pipeline(
+                device,
+                label,
+                &layout,
+                &vs_module,
+                &fs_module,
+                format,
+                wgpu::BlendState::REPLACE,
+            )
+        };
+
+        Self {
+            view_buffer,
+            bind_group,
+            render_pipeline: create_pipeline("Fractal Pipeline", color_format),
+            export_pipeline: create_pipeline("Fractal Export Pipeline", EXPORT_FORMAT),
+        }
+    }
+
+    /// Draws `view` over all of `target`, which must have the format given to `new`.
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        view: &View,
+        size: (u32, u32),
+    ) {
+        let uniform = ViewUniform::new(view, size, (0, 0));
+        queue.write_buffer(&self.view_buffer, 0, bytemuck::bytes_of(&uniform));
+        fullscreen::fullscreen_pass(
+            encoder,
+            "Fractal Pass",
+            target,
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            &self.render_pipeline,
+            &self.bind_group,
+        );
+    }
+
+    /// Renders `view` at `width` x `height` one tile at a time, reading each back before
+    /// starting the next.
+    pub async fn export(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &View,
+        width: u32,
+        height: u32,
+    ) -> Result<image::RgbaImage> {
+        let tile = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Export Tile"),
+            size: wgpu::Extent3d {
+                width: TILE_SIZE,
+                height: TILE_SIZE,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: EXPORT_FORMAT,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+        });
+        let tile_view = tile.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut output = image::RgbaImage::new(width, height);
+        for tile_y in (0..height).step_by(TILE_SIZE as usize) {
+            for tile_x in (0..width).step_by(TILE_SIZE as usize) {
+                let uniform = ViewUniform::new(view, (width, height), (tile_x, tile_y));
+                queue.write_buffer(&self.view_buffer, 0, bytemuck::bytes_of(&uniform));
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Export Encoder"),
+                });
+                fullscreen::fullscreen_pass(
+                    &mut encoder,
+                    "Fractal Export Pass",
+                    &tile_view,
+                    wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    &self.export_pipeline,
+                    &self.bind_group,
+                );
+                queue.submit(iter::once(encoder.finish()));
+
+                let pixels = texture::read_to_image(
+                    device,
+                    queue,
+                    &tile,
+                    EXPORT_FORMAT,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                )
+                .await?;
+                // Tiles on the right and bottom edges hang over the image
+                for (x, y, pixel) in pixels.enumerate_pixels() {
+                    if tile_x + x < width && tile_y + y < height {
+                        output.put_pixel(tile_x + x, tile_y + y, *pixel);
+                    }
+                }
+            }
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,70 @@
+#![allow(clippy::collapsible_match)]
+#![allow(clippy::single_match)]
+
+use winit::{
+    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+mod fractal;
+mod state;
+use state::{Options, State};
+
+fn run(event_loop: EventLoop<()>, window: Window, new_state: state::State) {
+    let mut state = new_state;
+
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent { ref event, .. } if !state.input(event) => match event {
+            WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+            WindowEvent::Resized(size) => state.resize(*size),
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                state.resize(**new_inner_size)
+            }
+            WindowEvent::KeyboardInput { input, .. } => match input {
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Escape),
+                    ..
+                } => *control_flow = ControlFlow::Exit,
+                _ => {}
+            },
+            _ => {}
+        },
+        Event::RedrawRequested(_) => {
+            state.update();
+            match state.render() {
+                Ok(_) => {}
+                // Recreate the swap_chain if lost
+                Err(wgpu::SwapChainError::Lost) => state.resize(state.size()),
+                // The system is out of memory, we should probably quit
+                Err(wgpu::SwapChainError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+                // All other errors (Outdated, Timeout) should be resolved by the next frame
+                Err(e) => eprintln!("{:?}", e),
+            }
+        }
+        Event::RedrawEventsCleared => {
+            window.request_redraw();
+        }
+        _ => {}
+    });
+}
+
+fn main() {
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            eprintln!("Usage: fractal [--julia X,Y] [--iterations 128] [--export-size 3840x2160]");
+            std::process::exit(1);
+        }
+    };
+
+    let event_loop = EventLoop::new();
+    let window = winit::window::Window::new(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+    let state: State = block_on(State::new(&window, options));
+
+    run(event_loop, window, state);
+}
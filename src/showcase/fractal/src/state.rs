@@ -0,0 +1,367 @@
+use std::iter;
+use std::path::PathBuf;
+
+use anyhow::*;
+use winit::{event::*, window::Window};
+
+use crate::fractal::{Fractal, Kind, Precision, View, MIN_SCALE, PALETTES};
+
+/// Height of the initial view in the complex plane.
+const INITIAL_SPAN: f64 = 3.0;
+const DEFAULT_ITERATIONS: u32 = 128;
+const MAX_ITERATIONS: u32 = 100_000;
+/// How much one notch of the mouse wheel zooms.
+const ZOOM_STEP: f64 = 1.25;
+/// Pixel scroll deltas, from touchpads, per wheel notch.
+const PIXELS_PER_LINE: f64 = 40.0;
+
+pub struct Options {
+    /// Starts on the Julia set for this constant instead of the Mandelbrot set.
+    pub julia: Option<[f64; 2]>,
+    pub iterations: u32,
+    /// Resolution of screenshots taken with S.
+    pub export_size: (u32, u32),
+}
+
+impl Options {
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut options = Self {
+            julia: None,
+            iterations: DEFAULT_ITERATIONS,
+            export_size: (3840, 2160),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("{} needs a value", arg))
+            };
+            match arg.as_str() {
+                "--julia" => {
+                    let value = value()?;
+                    let parts = value
+                        .split(',')
+                        .map(|part| part.trim().parse::<f64>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .ok()
+                        .filter(|parts| parts.len() == 2)
+                        .with_context(|| {
+                            format!("Invalid Julia constant {:?}, expected X,Y", value)
+                        })?;
+                    options.julia = Some([parts[0], parts[1]]);
+                }
+                "--iterations" => {
+                    let value = value()?;
+                    options.iterations = value
+                        .parse()
+                        .ok()
+                        .filter(|iterations| *iterations > 0)
+                        .with_context(|| format!("Invalid iteration count {:?}", value))?;
+                }
+                "--export-size" => {
+                    let value = value()?;
+                    options.export_size = value
+                        .split_once('x')
+                        .and_then(|(width, height)| {
+                            Some((width.parse().ok()?, height.parse().ok()?))
+                        })
+                        .filter(|&(width, height)| width > 0 && height > 0)
+                        .with_context(|| format!("Invalid size {:?}, expected WxH", value))?;
+                }
+                _ => bail!("Unknown argument {:?}", arg),
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    fractal: Fractal,
+    kind: Kind,
+    center: [f64; 2],
+    /// Height of the view in the complex plane, kept when the window resizes.
+    span: f64,
+    /// Iterations at the initial zoom, the limit grows as the view zooms in.
+    base_iterations: u32,
+    palette: u32,
+    precision: Precision,
+    export_size: (u32, u32),
+    was_emulated: bool,
+
+    cursor_position: winit::dpi::PhysicalPosition<f64>,
+    dragging: bool,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window, options: Options) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let fractal = Fractal::new(&device, sc_desc.format);
+        let (kind, center) = match options.julia {
+            Some(c) => (Kind::Julia { c }, [0.0, 0.0]),
+            None => (Kind::Mandelbrot, [-0.5, 0.0]),
+        };
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            fractal,
+            kind,
+            center,
+            span: INITIAL_SPAN,
+            base_iterations: options.iterations,
+            palette: 0,
+            precision: Precision::Auto,
+            export_size: options.export_size,
+            was_emulated: false,
+            cursor_position: winit::dpi::PhysicalPosition::new(0.0, 0.0),
+            dragging: false,
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+    }
+
+    /// The view for a target `height` pixels tall.
+    fn view(&self, height: u32) -> View {
+        let scale = self.span / height.max(1) as f64;
+        // Detail near the boundary needs more iterations the deeper the zoom goes
+        let zoom = (INITIAL_SPAN / self.span).max(1.0);
+        let max_iterations =
+            (self.base_iterations as f64 * (1.0 + 0.25 * zoom.log2())).min(MAX_ITERATIONS as f64);
+        View {
+            kind: self.kind,
+            center: self.center,
+            scale,
+            max_iterations: max_iterations as u32,
+            palette: self.palette,
+            precision: self.precision,
+        }
+    }
+
+    /// The point in the complex plane under the cursor.
+    fn cursor_point(&self) -> [f64; 2] {
+        let scale = self.span / self.size.height.max(1) as f64;
+        [
+            self.center[0] + (self.cursor_position.x - self.size.width as f64 * 0.5) * scale,
+            self.center[1] - (self.cursor_position.y - self.size.height as f64 * 0.5) * scale,
+        ]
+    }
+
+    /// Zooms by `factor` while keeping the point under the cursor in place.
+    fn zoom(&mut self, factor: f64) {
+        let min_span = MIN_SCALE * self.size.height.max(1) as f64;
+        let new_span = (self.span * factor).clamp(min_span, INITIAL_SPAN * 4.0);
+        let factor = new_span / self.span;
+        let [x, y] = self.cursor_point();
+        self.center = [
+            x + (self.center[0] - x) * factor,
+            y + (self.center[1] - y) * factor,
+        ];
+        self.span = new_span;
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                if self.dragging {
+                    let scale = self.span / self.size.height.max(1) as f64;
+                    self.center[0] -= (position.x - self.cursor_position.x) * scale;
+                    self.center[1] += (position.y - self.cursor_position.y) * scale;
+                }
+                self.cursor_position = *position;
+                true
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.dragging = *state == ElementState::Pressed;
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y as f64,
+                    MouseScrollDelta::PixelDelta(position) => position.y / PIXELS_PER_LINE,
+                };
+                self.zoom(ZOOM_STEP.powf(-lines));
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => self.key_pressed(*keycode),
+            _ => false,
+        }
+    }
+
+    fn key_pressed(&mut self, keycode: VirtualKeyCode) -> bool {
+        match keycode {
+            VirtualKeyCode::J => {
+                // The Julia set for the point under the cursor, framed from the start
+                self.kind = match self.kind {
+                    Kind::Mandelbrot => {
+                        let c = self.cursor_point();
+                        println!("Julia set for {} + {}i", c[0], c[1]);
+                        Kind::Julia { c }
+                    }
+                    Kind::Julia { .. } => {
+                        println!("Mandelbrot set");
+                        Kind::Mandelbrot
+                    }
+                };
+                self.reset_view();
+            }
+            VirtualKeyCode::P => {
+                self.palette = (self.palette + 1) % PALETTES.len() as u32;
+                println!("Palette: {}", PALETTES[self.palette as usize]);
+            }
+            VirtualKeyCode::D => {
+                self.precision = self.precision.next();
+                println!("Precision: {:?}", self.precision);
+            }
+            VirtualKeyCode::LBracket => {
+                self.base_iterations = (self.base_iterations / 2).max(16);
+                println!("{} base iterations", self.base_iterations);
+            }
+            VirtualKeyCode::RBracket => {
+                self.base_iterations = (self.base_iterations * 2).min(MAX_ITERATIONS);
+                println!("{} base iterations", self.base_iterations);
+            }
+            VirtualKeyCode::R => self.reset_view(),
+            VirtualKeyCode::S => {
+                if let Err(e) = self.export() {
+                    eprintln!("Couldn't export the screenshot: {:#}", e);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn reset_view(&mut self) {
+        self.center = match self.kind {
+            Kind::Mandelbrot => [-0.5, 0.0],
+            Kind::Julia { .. } => [0.0, 0.0],
+        };
+        self.span = INITIAL_SPAN;
+    }
+
+    /// Saves the current view at the export size to the first free fractal-N.png.
+    fn export(&self) -> Result<()> {
+        let (width, height) = self.export_size;
+        let path = (1..)
+            .map(|n| PathBuf::from(format!("fractal-{}.png", n)))
+            .find(|path| !path.exists())
+            .unwrap();
+        println!("Exporting {}x{} to {:?}", width, height, path);
+
+        let view = self.view(height);
+        let image = futures::executor::block_on(self.fractal.export(
+            &self.device,
+            &self.queue,
+            &view,
+            width,
+            height,
+        ))?;
+        image
+            .save(&path)
+            .with_context(|| format!("Couldn't write {:?}", path))?;
+        println!("Saved {:?}", path);
+        Ok(())
+    }
+
+    pub fn update(&mut self) {
+        let emulated = self.view(self.size.height).emulated();
+        if emulated != self.was_emulated {
+            println!(
+                "Using {} precision",
+                if emulated {
+                    "emulated double"
+                } else {
+                    "single"
+                }
+            );
+            self.was_emulated = emulated;
+        }
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        let view = self.view(self.size.height);
+        self.fractal.render(
+            &self.queue,
+            &mut encoder,
+            &frame.view,
+            &view,
+            (self.size.width, self.size.height),
+        );
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,514 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::*;
+use framework::{fullscreen, texture};
+use wgpu::util::DeviceExt;
+
+/// Invocations per workgroup side, must match `local_size_x/y` in the compute shaders.
+const WORKGROUP_SIZE: u32 = 8;
+const CELL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+pub const PRESETS: &[(&str, &str)] = &[
+    ("Life", "B3/S23"),
+    ("Brian's Brain", "B2/S/C3"),
+    ("HighLife", "B36/S23"),
+    ("Seeds", "B2/S"),
+    ("Day & Night", "B3678/S34678"),
+];
+
+/// An outer totalistic rule in B/S notation, with an optional `/C<n>` suffix giving the
+/// number of states for Generations rules like Brian's Brain.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    /// Bit n is set when a dead cell with n live neighbours comes alive.
+    pub birth: u32,
+    /// Bit n is set when a live cell with n live neighbours stays alive.
+    pub survive: u32,
+    /// 2 for plain rules, more makes cells that don't survive fade through
+    /// `states - 2` dying states, which count as dead neighbours.
+    pub states: u32,
+}
+
+impl Rule {
+    pub fn life() -> Self {
+        Self {
+            birth: 1 << 3,
+            survive: 1 << 2 | 1 << 3,
+            states: 2,
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(notation: &str) -> Result<Self> {
+        fn neighbour_counts(digits: &str, notation: &str) -> Result<u32> {
+            digits
+                .chars()
+                .try_fold(0, |mask, digit| match digit.to_digit(10) {
+                    Some(count) if count <= 8 => Ok(mask | 1 << count),
+                    _ => bail!("Invalid neighbour count {:?} in rule {:?}", digit, notation),
+                })
+        }
+
+        let mut rule = Self {
+            birth: 0,
+            survive: 0,
+            states: 2,
+        };
+        let mut has_birth = false;
+        let mut has_survive = false;
+        for part in notation.trim().split('/') {
+            let mut chars = part.chars();
+            match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') => {
+                    rule.birth = neighbour_counts(chars.as_str(), notation)?;
+                    has_birth = true;
+                }
+                Some('S') => {
+                    rule.survive = neighbour_counts(chars.as_str(), notation)?;
+                    has_survive = true;
+                }
+                Some('C') | Some('G') => {
+                    rule.states = chars
+                        .as_str()
+                        .parse()
+                        .ok()
+                        .filter(|states| *states >= 2)
+                        .with_context(|| format!("Invalid state count in rule {:?}", notation))?;
+                }
+                _ => bail!(
+                    "Invalid rule {:?}, expected something like B3/S23",
+                    notation
+                ),
+            }
+        }
+        if !has_birth || !has_survive {
+            bail!("Rule {:?} needs both a B and an S part", notation);
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = |mask: u32| -> String {
+            (0..=8)
+                .filter(|count| mask & 1 << count != 0)
+                .map(|count| std::char::from_digit(count, 10).unwrap())
+                .collect()
+        };
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survive))?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+/// Mirrors the `Rule` block in life.comp and draw.frag.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct RuleUniform {
+    birth: u32,
+    survive: u32,
+    states: u32,
+    _padding: u32,
+}
+
+impl From<Rule> for RuleUniform {
+    fn from(rule: Rule) -> Self {
+        Self {
+            birth: rule.birth,
+            survive: rule.survive,
+            states: rule.states,
+            _padding: 0,
+        }
+    }
+}
+
+fn storage_texture_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStage,
+    access: wgpu::StorageTextureAccess,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::StorageTexture {
+            access,
+            format: CELL_FORMAT,
+            view_dimension: wgpu::TextureViewDimension::D2,
+        },
+        count: None,
+    }
+}
+
+fn uniform_entry(binding: u32, visibility: wgpu::ShaderStage) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// A grid of cells stored in two `R32Uint` storage textures. Each step reads one and
+/// writes the other, then they swap roles. A cell holds 0 when dead, 1 when alive and
+/// 2 and up while dying.
+pub struct Automaton {
+    width: u32,
+    height: u32,
+    rule: Rule,
+    rule_buffer: wgpu::Buffer,
+    cells: [wgpu::Texture; 2],
+    views: [wgpu::TextureView; 2],
+    // step_bind_groups[i] reads cells[i] and writes the other one.
+    step_bind_groups: [wgpu::BindGroup; 2],
+    draw_bind_groups: [wgpu::BindGroup; 2],
+    current: usize,
+    step_pipeline: wgpu::ComputePipeline,
+    seed_bind_group_layout: wgpu::BindGroupLayout,
+    seed_pipeline: wgpu::ComputePipeline,
+    draw_pipeline: wgpu::RenderPipeline,
+}
+
+impl Automaton {
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        rule: Rule,
+        color_format: wgpu::TextureFormat,
+    ) -> Self {
+        let rule_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rule Buffer"),
+            contents: bytemuck::cast_slice(&[RuleUniform::from(rule)]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let create_cells = |label| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: CELL_FORMAT,
+                usage: wgpu::TextureUsage::STORAGE | wgpu::TextureUsage::COPY_DST,
+            })
+        };
+        let cells = [create_cells("Cells A"), create_cells("Cells B")];
+        let views = [
+            cells[0].create_view(&wgpu::TextureViewDescriptor::default()),
+            cells[1].create_view(&wgpu::TextureViewDescriptor::default()),
+        ];
+
+        let step_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    storage_texture_entry(
+                        0,
+                        wgpu::ShaderStage::COMPUTE,
+                        wgpu::StorageTextureAccess::ReadOnly,
+                    ),
+                    storage_texture_entry(
+                        1,
+                        wgpu::ShaderStage::COMPUTE,
+                        wgpu::StorageTextureAccess::WriteOnly,
+                    ),
+                    uniform_entry(2, wgpu::ShaderStage::COMPUTE),
+                ],
+                label: Some("step_bind_group_layout"),
+            });
+        let create_step_bind_group = |from: usize| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &step_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[from]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&views[1 - from]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: rule_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("step_bind_group"),
+            })
+        };
+        let step_bind_groups = [create_step_bind_group(0), create_step_bind_group(1)];
+
+        let step_pipeline = {
+            let module = device.create_shader_module(&wgpu::include_spirv!("life.comp.spv"));
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Step Pipeline Layout"),
+                bind_group_layouts: &[&step_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Step Pipeline"),
+                layout: Some(&layout),
+                module: &module,
+                entry_point: "main",
+            })
+        };
+
+        let seed_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::COMPUTE,
+                        ty: wgpu::BindingType::Sampler {
+                            comparison: false,
+                            filtering: true,
+                        },
+                        count: None,
+                    },
+                    storage_texture_entry(
+                        2,
+                        wgpu::ShaderStage::COMPUTE,
+                        wgpu::StorageTextureAccess::WriteOnly,
+                    ),
+                ],
+                label: Some("seed_bind_group_layout"),
+            });
+        let seed_pipeline = {
+            let module = device.create_shader_module(&wgpu::include_spirv!("seed.comp.spv"));
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Seed Pipeline Layout"),
+                bind_group_layouts: &[&seed_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Seed Pipeline"),
+                layout: Some(&layout),
+                module: &module,
+                entry_point: "main",
+            })
+        };
+
+        let draw_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    storage_texture_entry(
+                        0,
+                        wgpu::ShaderStage::FRAGMENT,
+                        wgpu::StorageTextureAccess::ReadOnly,
+                    ),
+                    uniform_entry(1, wgpu::ShaderStage::FRAGMENT),
+                ],
+                label: Some("draw_bind_group_layout"),
+            });
+        let create_draw_bind_group = |index: usize| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &draw_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[index]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: rule_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("draw_bind_group"),
+            })
+        };
+        let draw_bind_groups = [create_draw_bind_group(0), create_draw_bind_group(1)];
+
+        let draw_pipeline = {
+            let vs_module = fullscreen::vertex_module(device);
+            let fs_module = device.create_shader_module(&wgpu::include_spirv!("draw.frag.spv"));
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Draw Pipeline Layout"),
+                bind_group_layouts: &[&draw_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            fullscreen::create_fullscreen_pipeline(
+                device,
+                "Draw Pipeline",
+                &layout,
+                &vs_module,
+                &fs_module,
+                color_format,
+                wgpu::BlendState::REPLACE,
+            )
+        };
+
+        Self {
+            width,
+            height,
+            rule,
+            rule_buffer,
+            cells,
+            views,
+            step_bind_groups,
+            draw_bind_groups,
+            current: 0,
+            step_pipeline,
+            seed_bind_group_layout,
+            seed_pipeline,
+            draw_pipeline,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn set_rule(&mut self, queue: &wgpu::Queue, rule: Rule) {
+        self.rule = rule;
+        queue.write_buffer(
+            &self.rule_buffer,
+            0,
+            bytemuck::cast_slice(&[RuleUniform::from(rule)]),
+        );
+    }
+
+    /// Replaces every cell, `cells` holds one state per cell in rows from the top.
+    pub fn write_cells(&self, queue: &wgpu::Queue, cells: &[u32]) {
+        assert_eq!(cells.len(), (self.width * self.height) as usize);
+        self.write_region(queue, 0, 0, self.width, self.height, cells);
+    }
+
+    /// Sets the square of cells reaching `radius` cells out from (`x`, `y`) alive, or dead
+    /// when `alive` is false.
+    pub fn paint(&self, queue: &wgpu::Queue, x: u32, y: u32, radius: u32, alive: bool) {
+        let left = x.saturating_sub(radius);
+        let top = y.saturating_sub(radius);
+        let right = (x + radius + 1).min(self.width);
+        let bottom = (y + radius + 1).min(self.height);
+        if left >= right || top >= bottom {
+            return;
+        }
+
+        let width = right - left;
+        let height = bottom - top;
+        let value = if alive { 1 } else { 0 };
+        let cells = vec![value; (width * height) as usize];
+        self.write_region(queue, left, top, width, height, &cells);
+    }
+
+    fn write_region(
+        &self,
+        queue: &wgpu::Queue,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        cells: &[u32],
+    ) {
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &self.cells[self.current],
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+            },
+            bytemuck::cast_slice(cells),
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * width,
+                rows_per_image: height,
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        );
+    }
+
+    /// Sets the cells from an image, dark opaque pixels become live cells. The image is
+    /// stretched to cover the grid.
+    pub fn seed_from_texture(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        seed: &texture::Texture,
+    ) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.seed_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&seed.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&seed.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&self.views[self.current]),
+                },
+            ],
+            label: Some("seed_bind_group"),
+        });
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Seed Pass"),
+        });
+        compute_pass.set_pipeline(&self.seed_pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.dispatch(self.workgroups_x(), self.workgroups_y(), 1);
+    }
+
+    /// Advances the simulation one generation.
+    pub fn step(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Step Pass"),
+            });
+            compute_pass.set_pipeline(&self.step_pipeline);
+            compute_pass.set_bind_group(0, &self.step_bind_groups[self.current], &[]);
+            compute_pass.dispatch(self.workgroups_x(), self.workgroups_y(), 1);
+        }
+        self.current = 1 - self.current;
+    }
+
+    fn workgroups_x(&self) -> u32 {
+        self.width.div_ceil(WORKGROUP_SIZE)
+    }
+
+    fn workgroups_y(&self) -> u32 {
+        self.height.div_ceil(WORKGROUP_SIZE)
+    }
+
+    /// Draws the current generation stretched over the whole target.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.draw_pipeline);
+        render_pass.set_bind_group(0, &self.draw_bind_groups[self.current], &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
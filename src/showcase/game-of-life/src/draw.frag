@@ -0,0 +1,32 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0, r32ui) uniform readonly uimage2D u_cells;
+layout(set=0, binding=1) uniform Rule {
+    uint u_birth;
+    uint u_survive;
+    uint u_states;
+    uint _padding;
+};
+
+const vec3 DEAD_COLOR = vec3(0.05, 0.05, 0.08);
+const vec3 ALIVE_COLOR = vec3(0.95, 0.95, 0.85);
+const vec3 DYING_COLOR = vec3(0.2, 0.4, 1.0);
+
+void main() {
+    ivec2 size = imageSize(u_cells);
+    ivec2 cell = min(ivec2(v_tex_coords * vec2(size)), size - 1);
+    uint state = imageLoad(u_cells, cell).x;
+
+    vec3 color = DEAD_COLOR;
+    if (state == 1) {
+        color = ALIVE_COLOR;
+    } else if (state > 1) {
+        // Dying cells fade out over the remaining generations
+        float fade = float(state - 1) / float(u_states - 1);
+        color = mix(DYING_COLOR, DEAD_COLOR, fade);
+    }
+    f_color = vec4(color, 1.0);
+}
@@ -0,0 +1,357 @@
+use std::iter;
+use std::time::{Duration, Instant};
+
+use anyhow::*;
+use framework::texture;
+use winit::{event::*, window::Window};
+
+use crate::automaton::{Automaton, Rule, PRESETS};
+
+const DEFAULT_SIZE: u32 = 256;
+const BRUSH_RADIUS: u32 = 1;
+const MIN_SPEED: f32 = 1.0;
+const MAX_SPEED: f32 = 960.0;
+/// Caps the generations run in a single frame so a slow frame can't snowball.
+const MAX_STEPS_PER_FRAME: u32 = 32;
+
+pub struct Options {
+    pub rule: Rule,
+    /// Width and height of the grid in cells.
+    pub size: u32,
+    /// Contents of the image given with `--seed`.
+    pub seed_image: Option<Vec<u8>>,
+}
+
+impl Options {
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut options = Self {
+            rule: Rule::life(),
+            size: DEFAULT_SIZE,
+            seed_image: None,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("{} needs a value", arg))
+            };
+            match arg.as_str() {
+                "--rule" => options.rule = value()?.parse()?,
+                "--size" => {
+                    let size = value()?;
+                    options.size = size
+                        .parse()
+                        .ok()
+                        .filter(|size| *size > 0)
+                        .with_context(|| format!("Invalid grid size {:?}", size))?;
+                }
+                "--seed" => {
+                    let path = value()?;
+                    let bytes = std::fs::read(&path)
+                        .with_context(|| format!("Couldn't read seed image {:?}", path))?;
+                    options.seed_image = Some(bytes);
+                }
+                _ => bail!("Unknown argument {:?}", arg),
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    automaton: Automaton,
+    seed_texture: texture::Texture,
+    reseed: bool,
+    rng: u32,
+
+    paused: bool,
+    /// Generations per second.
+    speed: f32,
+    step_accumulator: f32,
+    pending_steps: u32,
+
+    cursor_position: winit::dpi::PhysicalPosition<f64>,
+    // Some(true) while painting live cells, Some(false) while erasing.
+    painting: Option<bool>,
+
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window, options: Options) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let automaton = Automaton::new(
+            &device,
+            options.size,
+            options.size,
+            options.rule,
+            sc_desc.format,
+        );
+        println!("Rule {}", automaton.rule());
+
+        let seed_bytes = options
+            .seed_image
+            .as_deref()
+            .unwrap_or(include_bytes!("happy-tree.png"));
+        let seed_texture = texture::Texture::from_bytes(&device, &queue, seed_bytes, "seed", false)
+            .unwrap_or_else(|e| {
+                eprintln!(
+                    "Couldn't load the seed image, using happy-tree.png: {:#}",
+                    e
+                );
+                texture::Texture::from_bytes(
+                    &device,
+                    &queue,
+                    include_bytes!("happy-tree.png"),
+                    "happy-tree.png",
+                    false,
+                )
+                .unwrap()
+            });
+
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(1, |time| time.subsec_nanos() | 1);
+        let mut state = Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            automaton,
+            seed_texture,
+            reseed: options.seed_image.is_some(),
+            rng: seed,
+            paused: false,
+            speed: 30.0,
+            step_accumulator: 0.0,
+            pending_steps: 0,
+            cursor_position: winit::dpi::PhysicalPosition::new(0.0, 0.0),
+            painting: None,
+            last_update: Instant::now(),
+        };
+        if !state.reseed {
+            state.randomize();
+        }
+        state
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                if let Some(alive) = self.painting {
+                    self.paint(alive);
+                }
+                true
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let alive = match button {
+                    MouseButton::Left => true,
+                    MouseButton::Right => false,
+                    _ => return false,
+                };
+                if *state == ElementState::Pressed {
+                    self.painting = Some(alive);
+                    self.paint(alive);
+                } else {
+                    self.painting = None;
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => self.key_pressed(*keycode),
+            _ => false,
+        }
+    }
+
+    fn key_pressed(&mut self, keycode: VirtualKeyCode) -> bool {
+        match keycode {
+            VirtualKeyCode::Space => {
+                self.paused = !self.paused;
+                self.step_accumulator = 0.0;
+            }
+            VirtualKeyCode::N => {
+                if self.paused {
+                    self.pending_steps += 1;
+                }
+            }
+            VirtualKeyCode::Up => {
+                self.speed = (self.speed * 2.0).min(MAX_SPEED);
+                println!("{} generations per second", self.speed);
+            }
+            VirtualKeyCode::Down => {
+                self.speed = (self.speed * 0.5).max(MIN_SPEED);
+                println!("{} generations per second", self.speed);
+            }
+            VirtualKeyCode::R => self.randomize(),
+            VirtualKeyCode::C => {
+                let (width, height) = self.automaton.size();
+                let cells = vec![0; (width * height) as usize];
+                self.automaton.write_cells(&self.queue, &cells);
+            }
+            VirtualKeyCode::I => self.reseed = true,
+            _ => {
+                let preset = match keycode {
+                    VirtualKeyCode::Key1 => 0,
+                    VirtualKeyCode::Key2 => 1,
+                    VirtualKeyCode::Key3 => 2,
+                    VirtualKeyCode::Key4 => 3,
+                    VirtualKeyCode::Key5 => 4,
+                    _ => return false,
+                };
+                let (name, notation) = PRESETS[preset];
+                let rule = notation.parse().unwrap();
+                self.automaton.set_rule(&self.queue, rule);
+                println!("Rule {} ({})", rule, name);
+            }
+        }
+        true
+    }
+
+    /// Paints at the cell under the cursor.
+    fn paint(&self, alive: bool) {
+        if self.size.width == 0 || self.size.height == 0 {
+            return;
+        }
+        let (width, height) = self.automaton.size();
+        let x = self.cursor_position.x / self.size.width as f64 * width as f64;
+        let y = self.cursor_position.y / self.size.height as f64 * height as f64;
+        if x < 0.0 || y < 0.0 {
+            return;
+        }
+        self.automaton
+            .paint(&self.queue, x as u32, y as u32, BRUSH_RADIUS, alive);
+    }
+
+    /// Fills the grid with roughly a quarter of the cells alive.
+    fn randomize(&mut self) {
+        let (width, height) = self.automaton.size();
+        let cells = (0..width * height)
+            .map(|_| {
+                // Xorshift
+                self.rng ^= self.rng << 13;
+                self.rng ^= self.rng >> 17;
+                self.rng ^= self.rng << 5;
+                if self.rng >> 30 == 0 {
+                    1
+                } else {
+                    0
+                }
+            })
+            .collect::<Vec<u32>>();
+        self.automaton.write_cells(&self.queue, &cells);
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = (now - self.last_update).min(Duration::from_millis(100));
+        self.last_update = now;
+
+        if !self.paused {
+            self.step_accumulator += dt.as_secs_f32() * self.speed;
+            let steps = self.step_accumulator.floor();
+            self.step_accumulator -= steps;
+            self.pending_steps += steps as u32;
+        }
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        if self.reseed {
+            self.automaton
+                .seed_from_texture(&self.device, &mut encoder, &self.seed_texture);
+            self.reseed = false;
+        }
+
+        for _ in 0..self.pending_steps.min(MAX_STEPS_PER_FRAME) {
+            self.automaton.step(&mut encoder);
+        }
+        self.pending_steps = 0;
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            self.automaton.draw(&mut render_pass);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
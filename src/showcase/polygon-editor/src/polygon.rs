@@ -0,0 +1,320 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::*;
+use cgmath::{InnerSpace, Vector2};
+use framework::json::{self, json, Fields, Value};
+
+pub const MIN_VERTICES: usize = 3;
+/// The GPU buffers are sized for this many vertices up front.
+pub const MAX_VERTICES: usize = 64;
+
+/// tutorial4's pentagon, in clip space.
+const PENTAGON: &[[f32; 2]] = &[
+    [-0.0868241, 0.49240386],
+    [-0.49513406, 0.06958647],
+    [-0.21918549, -0.44939706],
+    [0.35966998, -0.3473291],
+    [0.44147372, 0.2347359],
+];
+
+/// A simple polygon outline in clip space, filled with one color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    pub points: Vec<Vector2<f32>>,
+    pub color: [f32; 3],
+}
+
+impl Polygon {
+    pub fn pentagon() -> Self {
+        Self {
+            points: PENTAGON.iter().map(|&point| point.into()).collect(),
+            color: [0.5, 0.0, 0.5],
+        }
+    }
+
+    /// The vertex closest to `point` and how far away it is. Distances are measured
+    /// after multiplying by `scale`, so passing pixels per clip unit gives pixels.
+    pub fn nearest_vertex(&self, point: Vector2<f32>, scale: Vector2<f32>) -> Option<(usize, f32)> {
+        self.points
+            .iter()
+            .map(|vertex| mul(vertex - point, scale).magnitude())
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Inserts `point` into the edge closest to it and returns its index, or `None` when
+    /// the polygon is already full.
+    pub fn insert(&mut self, point: Vector2<f32>, scale: Vector2<f32>) -> Option<usize> {
+        if self.points.len() >= MAX_VERTICES {
+            return None;
+        }
+        let (edge, _) = (0..self.points.len())
+            .map(|i| {
+                let a = mul(self.points[i], scale);
+                let b = mul(self.points[(i + 1) % self.points.len()], scale);
+                (i, distance_to_segment(mul(point, scale), a, b))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        self.points.insert(edge + 1, point);
+        Some(edge + 1)
+    }
+
+    /// Removes a vertex unless that would leave less than a triangle.
+    pub fn remove(&mut self, index: usize) -> bool {
+        if self.points.len() <= MIN_VERTICES || index >= self.points.len() {
+            return false;
+        }
+        self.points.remove(index);
+        true
+    }
+
+    /// Splits the outline into counter-clockwise triangles by clipping ears, so concave
+    /// outlines work too. Outlines that cross themselves have no ears left at some point,
+    /// and whatever remains is fanned out instead.
+    pub fn triangulate(&self) -> Vec<u16> {
+        let points = &self.points;
+        let mut remaining = (0..points.len()).collect::<Vec<_>>();
+        if signed_area(points) < 0.0 {
+            remaining.reverse();
+        }
+
+        let mut indices = Vec::with_capacity(points.len().saturating_sub(2) * 3);
+        while remaining.len() > 3 {
+            let count = remaining.len();
+            let corner = |i: usize| {
+                (
+                    remaining[(i + count - 1) % count],
+                    remaining[i],
+                    remaining[(i + 1) % count],
+                )
+            };
+            let ear = (0..count).find(|&i| {
+                let (a, b, c) = corner(i);
+                let (pa, pb, pc) = (points[a], points[b], points[c]);
+                // Reflex and flat corners can't be cut off
+                cross(pb - pa, pc - pb) > 0.0
+                    && remaining
+                        .iter()
+                        .filter(|&&j| j != a && j != b && j != c)
+                        .all(|&j| !in_triangle(points[j], pa, pb, pc))
+            });
+            let i = match ear {
+                Some(i) => i,
+                None => break,
+            };
+            let (a, b, c) = corner(i);
+            indices.extend_from_slice(&[a as u16, b as u16, c as u16]);
+            remaining.remove(i);
+        }
+        for i in 1..remaining.len().saturating_sub(1) {
+            indices.extend_from_slice(&[
+                remaining[0] as u16,
+                remaining[i] as u16,
+                remaining[i + 1] as u16,
+            ]);
+        }
+        indices
+    }
+
+    /// The scene file: the outline and color, plus the triangles for loaders that don't
+    /// want to triangulate it themselves.
+    pub fn to_json(&self) -> String {
+        let vertices = self
+            .points
+            .iter()
+            .map(|point| [point.x, point.y])
+            .collect::<Vec<_>>();
+        let scene = json!({
+            "color": self.color,
+            "vertices": vertices,
+            "indices": self.triangulate(),
+        });
+        format!("{:#}\n", scene)
+    }
+
+    /// Reads a scene file. The stored triangles are ignored, they are rebuilt from the outline.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let root = json::parse(text)?;
+        let numbers = |value: &Value, len: usize| -> Result<Vec<f32>> {
+            let values = value.as_array().context("Expected an array")?;
+            ensure!(values.len() == len, "Expected {} numbers", len);
+            values
+                .iter()
+                .map(|value| {
+                    value
+                        .as_f64()
+                        .map(|n| n as f32)
+                        .context("Expected a number")
+                })
+                .collect()
+        };
+
+        let color = numbers(root.field("color")?, 3).context("Bad color")?;
+        let points = root
+            .field_array("vertices")?
+            .iter()
+            .enumerate()
+            .map(|(index, vertex)| {
+                let xy = numbers(vertex, 2).with_context(|| format!("Bad vertex {}", index))?;
+                Ok(Vector2::new(xy[0], xy[1]))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(
+            (MIN_VERTICES..=MAX_VERTICES).contains(&points.len()),
+            "Polygons need {} to {} vertices, found {}",
+            MIN_VERTICES,
+            MAX_VERTICES,
+            points.len()
+        );
+
+        Ok(Self {
+            points,
+            color: [color[0], color[1], color[2]],
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Couldn't read {:?}", path))?;
+        Self::from_json(&text).with_context(|| format!("Couldn't parse {:?}", path))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_json()).with_context(|| format!("Couldn't write {:?}", path))
+    }
+
+    /// The polygon as `VERTICES` and `INDICES` constants that can be pasted over tutorial4's.
+    pub fn to_rust(&self) -> String {
+        let mut source = String::from("const VERTICES: &[Vertex] = &[\n");
+        for (index, point) in self.points.iter().enumerate() {
+            write!(
+                source,
+                "    Vertex {{\n        position: [{:?}, {:?}, 0.0],\n        color: {:?},\n    }},",
+                point.x, point.y, self.color
+            )
+            .unwrap();
+            // tutorial4 names its vertices A to E
+            if index < 26 {
+                write!(source, " // {}", (b'A' + index as u8) as char).unwrap();
+            }
+            source.push('\n');
+        }
+        source.push_str("];\n\n");
+
+        let indices = self
+            .triangulate()
+            .iter()
+            .map(|index| index.to_string())
+            .collect::<Vec<_>>();
+        writeln!(source, "const INDICES: &[u16] = &[{}];", indices.join(", ")).unwrap();
+        source
+    }
+}
+
+fn mul(a: Vector2<f32>, b: Vector2<f32>) -> Vector2<f32> {
+    Vector2::new(a.x * b.x, a.y * b.y)
+}
+
+fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Positive for counter-clockwise outlines with y up.
+fn signed_area(points: &[Vector2<f32>]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        area += cross(*a, points[(i + 1) % points.len()]);
+    }
+    area / 2.0
+}
+
+/// Whether `p` is inside or on the edge of the counter-clockwise triangle `abc`.
+fn in_triangle(p: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>) -> bool {
+    cross(b - a, p - a) >= 0.0 && cross(c - b, p - b) >= 0.0 && cross(a - c, p - c) >= 0.0
+}
+
+fn distance_to_segment(p: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    let ab = b - a;
+    let length2 = ab.magnitude2();
+    let t = if length2 > 0.0 {
+        ((p - a).dot(ab) / length2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (a + ab * t - p).magnitude()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polygon(points: &[[f32; 2]]) -> Polygon {
+        Polygon {
+            points: points.iter().map(|&point| point.into()).collect(),
+            color: [1.0, 0.0, 0.0],
+        }
+    }
+
+    fn triangles(polygon: &Polygon) -> Vec<[Vector2<f32>; 3]> {
+        let indices = polygon.triangulate();
+        assert_eq!(indices.len(), (polygon.points.len() - 2) * 3);
+        indices
+            .chunks(3)
+            .map(|triangle| {
+                let point = |i: usize| polygon.points[triangle[i] as usize];
+                [point(0), point(1), point(2)]
+            })
+            .collect()
+    }
+
+    fn area([a, b, c]: [Vector2<f32>; 3]) -> f32 {
+        cross(b - a, c - a) / 2.0
+    }
+
+    #[test]
+    fn concave() {
+        // A U, open at the top
+        let mut u = polygon(&[
+            [0.0, 0.0],
+            [3.0, 0.0],
+            [3.0, 3.0],
+            [2.0, 3.0],
+            [2.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 3.0],
+            [0.0, 3.0],
+        ]);
+        for _ in 0..2 {
+            let triangles = triangles(&u);
+            assert!(triangles.iter().all(|&triangle| area(triangle) > 0.0));
+            // Triangles that stay inside the outline cover it exactly once
+            let total: f32 = triangles.iter().map(|&triangle| area(triangle)).sum();
+            assert!((total - 7.0).abs() < 1e-5, "covered {}", total);
+            // Clockwise outlines come out counter-clockwise too
+            u.points.reverse();
+        }
+    }
+
+    #[test]
+    fn self_intersecting() {
+        let bowtie = polygon(&[[0.0, 0.0], [1.0, 1.0], [1.0, 0.0], [0.0, 1.0]]);
+        let indices = bowtie.triangulate();
+        assert_eq!(indices.len(), 6);
+        assert!(indices
+            .iter()
+            .all(|&index| (index as usize) < bowtie.points.len()));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let pentagon = Polygon::pentagon();
+        assert_eq!(Polygon::from_json(&pentagon.to_json()).unwrap(), pentagon);
+        assert!(
+            Polygon::from_json(r#"{"color": [1, 0, 0], "vertices": [[0, 0], [1, 1]]}"#).is_err()
+        );
+        assert!(Polygon::from_json(r#"{"color": [1, 0], "vertices": []}"#).is_err());
+    }
+}
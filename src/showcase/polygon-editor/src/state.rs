@@ -0,0 +1,479 @@
+use std::iter;
+use std::path::PathBuf;
+
+use anyhow::*;
+use cgmath::Vector2;
+use framework::{
+    sprite::screen_projection,
+    vector::{
+        FillRule, GpuTessellation, LineJoin, Path, StrokeStyle, Tessellation, Tessellator,
+        VectorRenderer,
+    },
+};
+use winit::{event::*, window::Window};
+
+use crate::polygon::{Polygon, MAX_VERTICES};
+
+/// How close in pixels a click has to be to grab a vertex.
+const PICK_RADIUS: f32 = 16.0;
+const HANDLE_RADIUS: f32 = 5.0;
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const YELLOW: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float3,
+                },
+            ],
+        }
+    }
+}
+
+/// The cursor position as a fraction of the window size, like tutorial2-swapchain-challenge.
+fn normalize(number: f64, max: u32) -> f64 {
+    number / f64::from(max)
+}
+
+pub struct Options {
+    /// Loaded on startup when it exists, and where S saves to.
+    pub scene: PathBuf,
+}
+
+impl Options {
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut scene = None;
+        for arg in args {
+            match arg.as_str() {
+                _ if arg.starts_with("--") => bail!("Unknown argument {:?}", arg),
+                _ if scene.is_none() => scene = Some(PathBuf::from(arg)),
+                _ => bail!("Only one scene can be edited at a time"),
+            }
+        }
+        Ok(Self {
+            scene: scene.unwrap_or_else(|| PathBuf::from("polygon.json")),
+        })
+    }
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+
+    overlay: VectorRenderer,
+    overlay_tessellation: Tessellation,
+    overlay_mesh: Option<GpuTessellation>,
+    /// Set whenever the outline or handles have to be tessellated again.
+    dirty: bool,
+
+    scene: PathBuf,
+    polygon: Polygon,
+    /// In clip space, like the polygon.
+    cursor: Vector2<f32>,
+    selected: Option<usize>,
+    dragging: bool,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window, options: Options) -> Result<Self> {
+        let polygon = if options.scene.exists() {
+            Polygon::load(&options.scene)?
+        } else {
+            Polygon::pentagon()
+        };
+        window.set_title(&format!("{}", options.scene.display()));
+
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .context("No suitable adapter")?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await?;
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("shader.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("shader.frag.spv"));
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[],
+                push_constant_ranges: &[],
+            });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &vs_module,
+                entry_point: "main",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fs_module,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format: sc_desc.format,
+                    alpha_blend: wgpu::BlendState::REPLACE,
+                    color_blend: wgpu::BlendState::REPLACE,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        // Sized for the largest polygon, so edits only ever need write_buffer
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Vertex Buffer"),
+            size: (MAX_VERTICES * std::mem::size_of::<Vertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Index Buffer"),
+            size: (MAX_VERTICES * 3 * std::mem::size_of::<u16>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::INDEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let overlay = VectorRenderer::new(&device, sc_desc.format);
+
+        let mut state = Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: 0,
+            overlay,
+            overlay_tessellation: Tessellation::new(),
+            overlay_mesh: None,
+            dirty: true,
+            scene: options.scene,
+            polygon,
+            cursor: Vector2::new(0.0, 0.0),
+            selected: None,
+            dragging: false,
+        };
+        state.write_vertices();
+        state.write_indices();
+        Ok(state)
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        // The handles are sized in pixels
+        self.dirty = true;
+    }
+
+    /// Pixels per clip space unit along each axis.
+    fn pixel_scale(&self) -> Vector2<f32> {
+        Vector2::new(self.size.width as f32 / 2.0, self.size.height as f32 / 2.0)
+    }
+
+    fn to_pixels(&self, point: Vector2<f32>) -> Vector2<f32> {
+        let scale = self.pixel_scale();
+        Vector2::new((point.x + 1.0) * scale.x, (1.0 - point.y) * scale.y)
+    }
+
+    /// The vertex close enough to the cursor to be grabbed.
+    fn hovered(&self) -> Option<usize> {
+        self.polygon
+            .nearest_vertex(self.cursor, self.pixel_scale())
+            .filter(|(_, distance)| *distance <= PICK_RADIUS)
+            .map(|(index, _)| index)
+    }
+
+    fn vertex(&self, index: usize) -> Vertex {
+        let point = self.polygon.points[index];
+        Vertex {
+            position: [point.x, point.y, 0.0],
+            color: self.polygon.color,
+        }
+    }
+
+    fn write_vertices(&mut self) {
+        let vertices = (0..self.polygon.points.len())
+            .map(|index| self.vertex(index))
+            .collect::<Vec<_>>();
+        self.queue
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.dirty = true;
+    }
+
+    /// Updates just the one vertex that is being dragged.
+    fn write_vertex(&mut self, index: usize) {
+        let offset = (index * std::mem::size_of::<Vertex>()) as wgpu::BufferAddress;
+        self.queue.write_buffer(
+            &self.vertex_buffer,
+            offset,
+            bytemuck::cast_slice(&[self.vertex(index)]),
+        );
+        self.dirty = true;
+    }
+
+    fn write_indices(&mut self) {
+        let mut indices = self.polygon.triangulate();
+        self.num_indices = indices.len() as u32;
+        // Copies have to be a multiple of 4 bytes
+        if indices.len() % 2 == 1 {
+            indices.push(0);
+        }
+        self.queue
+            .write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&indices));
+    }
+
+    fn save(&self) -> Result<()> {
+        self.polygon.save(&self.scene)?;
+        println!("Saved {}", self.scene.display());
+        Ok(())
+    }
+
+    fn export(&self) -> Result<()> {
+        let path = self.scene.with_extension("rs");
+        std::fs::write(&path, self.polygon.to_rust())
+            .with_context(|| format!("Couldn't write {:?}", path))?;
+        println!("Exported {}", path.display());
+        Ok(())
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let x = normalize(position.x, self.size.width) as f32;
+                let y = normalize(position.y, self.size.height) as f32;
+                // Flip y to match clip space
+                self.cursor = Vector2::new(x * 2.0 - 1.0, 1.0 - y * 2.0);
+                match self.selected {
+                    Some(index) if self.dragging => {
+                        self.polygon.points[index] = self.cursor;
+                        self.write_vertex(index);
+                        // Dragging can make the outline concave
+                        self.write_indices();
+                    }
+                    // Only the hover highlight changes
+                    _ => self.dirty = true,
+                }
+                true
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.dragging = *state == ElementState::Pressed;
+                if self.dragging {
+                    self.selected = self.hovered();
+                    self.dirty = true;
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => {
+                let result = match keycode {
+                    VirtualKeyCode::A | VirtualKeyCode::Insert => {
+                        match self.polygon.insert(self.cursor, self.pixel_scale()) {
+                            Some(index) => {
+                                self.selected = Some(index);
+                                self.write_vertices();
+                                self.write_indices();
+                            }
+                            None => {
+                                eprintln!("Polygons can have at most {} vertices", MAX_VERTICES)
+                            }
+                        }
+                        Ok(())
+                    }
+                    VirtualKeyCode::Delete | VirtualKeyCode::Back => {
+                        if let Some(index) = self.selected {
+                            if self.polygon.remove(index) {
+                                self.selected = None;
+                                self.dragging = false;
+                                self.write_vertices();
+                                self.write_indices();
+                            }
+                        }
+                        Ok(())
+                    }
+                    VirtualKeyCode::S => self.save(),
+                    VirtualKeyCode::E => self.export(),
+                    _ => return false,
+                };
+                if let Err(e) = result {
+                    eprintln!("{:#}", e);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+
+        // The outline and handles are drawn in pixels on top of the polygon
+        let tessellator = Tessellator::default();
+        let points = self
+            .polygon
+            .points
+            .iter()
+            .map(|point| self.to_pixels(*point))
+            .collect::<Vec<_>>();
+        let hovered = self.hovered();
+        let output = &mut self.overlay_tessellation;
+        output.clear();
+
+        let outline = StrokeStyle {
+            width: 2.0,
+            join: LineJoin::Round,
+            ..Default::default()
+        };
+        tessellator.stroke(output, &Path::polyline(&points, true), &outline, WHITE);
+
+        for (index, point) in points.iter().enumerate() {
+            let (radius, color) = if Some(index) == self.selected {
+                (HANDLE_RADIUS * 1.5, YELLOW)
+            } else if Some(index) == hovered {
+                (HANDLE_RADIUS * 1.5, WHITE)
+            } else {
+                (HANDLE_RADIUS, WHITE)
+            };
+            tessellator.fill(
+                output,
+                &Path::circle(*point, radius),
+                FillRule::NonZero,
+                color,
+            );
+        }
+
+        self.overlay_mesh = Some(self.overlay_tessellation.upload(&self.device));
+        let projection = screen_projection(self.size.width as f32, self.size.height as f32);
+        self.overlay.set_projection(&self.queue, projection);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+
+            if let Some(mesh) = &self.overlay_mesh {
+                self.overlay.draw(&mut render_pass, mesh);
+            }
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
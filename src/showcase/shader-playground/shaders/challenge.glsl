@@ -0,0 +1,5 @@
+// tutorial3-pipeline-challenge's challenge.frag: the color is the clip space position
+// the vertex stage passes on, so the window goes from black to red, green and yellow.
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = special_color;
+}
@@ -0,0 +1,16 @@
+// Classic plasma. Hold the left mouse button to move its center, iChannel0 tints it.
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.y;
+    vec2 center = iMouse.z > 0.0 ? iMouse.xy / iResolution.y : vec2(0.5 * iResolution.x / iResolution.y, 0.5);
+
+    float value = sin(uv.x * 10.0 + iTime)
+        + sin((uv.y * 10.0 + iTime) * 0.5)
+        + sin(length(uv - center) * 12.0 - iTime * 2.0);
+    vec3 color = 0.5 + 0.5 * cos(value * 3.14159 + vec3(0.0, 2.0, 4.0));
+
+    vec3 tint = texture(iChannel0, fragCoord / iResolution.xy).rgb;
+    if (iChannelResolution[0].x > 0.0) {
+        color *= tint;
+    }
+    fragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,303 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use anyhow::*;
+use framework::{fullscreen, texture};
+use wgpu::util::DeviceExt;
+
+pub const CHANNEL_COUNT: usize = 4;
+
+/// Declarations put in front of the user's shader. Channels are declared as separate
+/// textures and samplers since wgpu doesn't take combined image samplers, the defines let
+/// Shadertoy code keep calling `texture(iChannel0, uv)`. `special_color` comes from
+/// playground.vert, like in tutorial3-pipeline-challenge.
+const HEADER: &str = r#"#version 450
+
+layout(set=0, binding=0) uniform Playground {
+    vec3 iResolution;
+    float iTime;
+    vec4 iMouse;
+    vec3 iChannelResolution[4];
+    float iTimeDelta;
+    int iFrame;
+};
+
+layout(set=0, binding=1) uniform texture2D t_channel0;
+layout(set=0, binding=2) uniform sampler s_channel0;
+layout(set=0, binding=3) uniform texture2D t_channel1;
+layout(set=0, binding=4) uniform sampler s_channel1;
+layout(set=0, binding=5) uniform texture2D t_channel2;
+layout(set=0, binding=6) uniform sampler s_channel2;
+layout(set=0, binding=7) uniform texture2D t_channel3;
+layout(set=0, binding=8) uniform sampler s_channel3;
+#define iChannel0 sampler2D(t_channel0, s_channel0)
+#define iChannel1 sampler2D(t_channel1, s_channel1)
+#define iChannel2 sampler2D(t_channel2, s_channel2)
+#define iChannel3 sampler2D(t_channel3, s_channel3)
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec4 special_color;
+layout(location=0) out vec4 f_color;
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord);
+
+void main() {
+    // Shadertoy puts the origin in the bottom left corner
+    vec2 frag_coord = vec2(gl_FragCoord.x, iResolution.y - gl_FragCoord.y);
+    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(color, frag_coord);
+    f_color = vec4(color.rgb, 1.0);
+}
+
+// Errors in the user's code report their own line numbers
+#line 1
+"#;
+
+/// Mirrors the `Playground` block the header declares.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PlaygroundUniform {
+    /// Width, height and pixel aspect ratio, which is always 1.
+    pub resolution: [f32; 3],
+    /// Seconds since the start.
+    pub time: f32,
+    /// xy is the cursor while a button is held, zw where it was pressed. z and w are
+    /// negative while no button is held.
+    pub mouse: [f32; 4],
+    /// Arrays of vec3 have a 16 byte stride in uniform blocks, the fourth value is unused.
+    pub channel_resolution: [[f32; 4]; CHANNEL_COUNT],
+    pub time_delta: f32,
+    pub frame: i32,
+    pub _padding: [f32; 2],
+}
+
+/// Compiles the shader at `path` to SPIR-V with glslc, after wrapping it in `HEADER`.
+/// Compile errors come back as the error with glslc's output.
+pub fn compile(glslc: &Path, path: &Path) -> Result<Vec<u8>> {
+    let source =
+        std::fs::read_to_string(path).with_context(|| format!("Couldn't read {:?}", path))?;
+    let temp_dir = std::env::temp_dir();
+    let wrapped_path = temp_dir.join(format!("shader-playground-{}.frag", std::process::id()));
+    let spirv_path = wrapped_path.with_extension("frag.spv");
+    let run = || -> Result<Vec<u8>> {
+        std::fs::write(&wrapped_path, format!("{}{}", HEADER, source))
+            .with_context(|| format!("Couldn't write {:?}", wrapped_path))?;
+        let output = Command::new(glslc)
+            .arg("-fshader-stage=frag")
+            .arg(&wrapped_path)
+            .arg("-o")
+            .arg(&spirv_path)
+            .output()
+            .with_context(|| format!("Couldn't run {:?}, pass --glslc with its path", glslc))?;
+        if !output.status.success() {
+            // Point the errors at the user's file rather than the wrapped copy
+            let errors = String::from_utf8_lossy(&output.stderr)
+                .replace(&*wrapped_path.to_string_lossy(), &path.to_string_lossy());
+            bail!("{}", errors.trim_end());
+        }
+        std::fs::read(&spirv_path).with_context(|| format!("Couldn't read {:?}", spirv_path))
+    };
+    let spirv = run();
+    // Clean up whether or not glslc succeeded
+    let _ = std::fs::remove_file(&wrapped_path);
+    let _ = std::fs::remove_file(&spirv_path);
+    let spirv = spirv?;
+    // make_spirv panics on anything that doesn't look like SPIR-V
+    ensure!(
+        spirv.chunks_exact(4).remainder().is_empty()
+            && spirv.starts_with(&0x0723_0203u32.to_le_bytes()),
+        "glslc didn't produce SPIR-V"
+    );
+    Ok(spirv)
+}
+
+/// wgpu 0.7 has no error scopes, so this stands in for one with the device's uncaptured
+/// error handler. Errors are collected between `push` and `pop`. The rest of the time
+/// they are only printed, as a shader glslc accepts can still break a later draw and the
+/// next save should get a chance to fix it.
+#[derive(Clone, Default)]
+struct ErrorScope {
+    errors: Arc<Mutex<Option<Vec<String>>>>,
+}
+
+impl ErrorScope {
+    fn install(device: &wgpu::Device) -> Self {
+        let scope = Self::default();
+        let errors = scope.errors.clone();
+        device.on_uncaptured_error(move |error| {
+            if let Some(errors) = errors.lock().unwrap().as_mut() {
+                errors.push(error.to_string());
+                return;
+            }
+            eprintln!("wgpu error: {}", error);
+        });
+        scope
+    }
+
+    fn push(&self) {
+        *self.errors.lock().unwrap() = Some(Vec::new());
+    }
+
+    fn pop(&self) -> Vec<String> {
+        self.errors.lock().unwrap().take().unwrap_or_default()
+    }
+}
+
+/// The user's shader drawn over the whole target, with the uniforms and channels
+/// Shadertoy provides.
+pub struct Playground {
+    path: PathBuf,
+    glslc: PathBuf,
+    color_format: wgpu::TextureFormat,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
+    vs_module: wgpu::ShaderModule,
+    errors: ErrorScope,
+    /// None until the shader has compiled once.
+    pipeline: Option<wgpu::RenderPipeline>,
+}
+
+impl Playground {
+    /// `channels` are bound in order, missing ones are black.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: PathBuf,
+        glslc: PathBuf,
+        channels: &[texture::Texture],
+        color_format: wgpu::TextureFormat,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Playground Buffer"),
+            contents: bytemuck::bytes_of(&PlaygroundUniform::default()),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let mut entries = vec![fullscreen::uniform_entry(0)];
+        for channel in 0..CHANNEL_COUNT as u32 {
+            entries.push(fullscreen::texture_entry(1 + channel * 2));
+            entries.push(fullscreen::sampler_entry(2 + channel * 2));
+        }
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("playground_bind_group_layout"),
+        });
+
+        let black = texture::Texture::from_color(device, queue, [0, 0, 0, 255], "black", false);
+        let channel = |index: usize| channels.get(index).unwrap_or(&black);
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniform_buffer.as_entire_binding(),
+        }];
+        for index in 0..CHANNEL_COUNT {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1 + index as u32 * 2,
+                resource: wgpu::BindingResource::TextureView(&channel(index).view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 + index as u32 * 2,
+                resource: wgpu::BindingResource::Sampler(&channel(index).sampler),
+            });
+        }
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &entries,
+            label: Some("playground_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Playground Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let mut playground = Self {
+            path,
+            glslc,
+            color_format,
+            uniform_buffer,
+            bind_group,
+            pipeline_layout,
+            vs_module: device.create_shader_module(&wgpu::include_spirv!("playground.vert.spv")),
+            errors: ErrorScope::install(device),
+            pipeline: None,
+        };
+        playground.reload(device);
+        playground
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Recompiles the shader. When that fails, or wgpu rejects the result, the error is
+    /// printed and the last working version stays in use.
+    pub fn reload(&mut self, device: &wgpu::Device) {
+        let spirv = match compile(&self.glslc, &self.path) {
+            Ok(spirv) => spirv,
+            Err(e) => {
+                eprintln!("{:#}", e);
+                return;
+            }
+        };
+        self.errors.push();
+        let fs_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Playground Shader"),
+            source: wgpu::util::make_spirv(&spirv),
+            flags: wgpu::ShaderFlags::VALIDATION,
+        });
+        let pipeline = fullscreen::create_fullscreen_pipeline(
+            device,
+            "Playground Pipeline",
+            &self.pipeline_layout,
+            &self.vs_module,
+            &fs_module,
+            self.color_format,
+            wgpu::BlendState::REPLACE,
+        );
+        let errors = self.errors.pop();
+        if !errors.is_empty() {
+            eprintln!("{}", errors.join("\n"));
+            return;
+        }
+        self.pipeline = Some(pipeline);
+        println!("Compiled {:?}", self.path);
+    }
+
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        uniform: &PlaygroundUniform,
+    ) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(uniform));
+        match &self.pipeline {
+            Some(pipeline) => fullscreen::fullscreen_pass(
+                encoder,
+                "Playground Pass",
+                target,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                pipeline,
+                &self.bind_group,
+            ),
+            // Nothing compiled yet, just clear
+            None => {
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Playground Pass"),
+                    color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                        attachment: target,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: true,
+                        },
+                    }],
+                    depth_stencil_attachment: None,
+                });
+            }
+        }
+    }
+}
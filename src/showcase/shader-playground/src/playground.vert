@@ -0,0 +1,19 @@
+#version 450
+
+// tutorial3-pipeline-challenge's challenge.vert, with the triangle grown until it
+// covers the whole window. special_color is still the clip space position, so
+// shaders can color by position the way challenge.frag does.
+const vec2 positions[3] = vec2[3] (
+    vec2(-1.0, 3.0),
+    vec2(-1.0, -1.0),
+    vec2(3.0, -1.0)
+);
+
+layout(location=0) out vec2 v_tex_coords;
+layout(location=1) out vec4 special_color;
+
+void main() {
+    special_color = vec4(positions[gl_VertexIndex], 0.0, 1.0);
+    v_tex_coords = special_color.xy * vec2(0.5, -0.5) + 0.5;
+    gl_Position = special_color;
+}
@@ -0,0 +1,276 @@
+use std::iter;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::*;
+use framework::texture;
+use image::GenericImageView;
+use winit::{event::*, window::Window};
+
+use crate::playground::{Playground, PlaygroundUniform, CHANNEL_COUNT};
+
+/// How often the shader file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct Options {
+    pub shader: PathBuf,
+    /// Images for iChannel0 to iChannel3, in order.
+    pub channels: [Option<PathBuf>; CHANNEL_COUNT],
+    pub glslc: PathBuf,
+}
+
+impl Options {
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut shader = None;
+        let mut channels: [Option<PathBuf>; CHANNEL_COUNT] = Default::default();
+        let mut glslc = PathBuf::from("glslc");
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("{} needs a value", arg))
+            };
+            match arg.as_str() {
+                "--glslc" => glslc = value()?.into(),
+                _ if arg.starts_with("--channel") => {
+                    let index = arg["--channel".len()..]
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|index| *index < CHANNEL_COUNT)
+                        .with_context(|| format!("Unknown argument {:?}", arg))?;
+                    channels[index] = Some(value()?.into());
+                }
+                _ if arg.starts_with("--") => bail!("Unknown argument {:?}", arg),
+                _ if shader.is_none() => shader = Some(arg.into()),
+                _ => bail!("Unexpected argument {:?}", arg),
+            }
+        }
+        Ok(Self {
+            shader: shader.context("No shader given")?,
+            channels,
+            glslc,
+        })
+    }
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    playground: Playground,
+    uniform: PlaygroundUniform,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+
+    paused: bool,
+    cursor_position: winit::dpi::PhysicalPosition<f64>,
+    mouse_down: bool,
+
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window, options: Options) -> Result<Self> {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let mut uniform = PlaygroundUniform::default();
+        let mut channels = Vec::new();
+        for (index, path) in options.channels.iter().enumerate() {
+            let texture = match path {
+                Some(path) => {
+                    let img =
+                        image::open(path).with_context(|| format!("Couldn't open {:?}", path))?;
+                    let (width, height) = img.dimensions();
+                    uniform.channel_resolution[index] = [width as f32, height as f32, 1.0, 0.0];
+                    let label = format!("iChannel{}", index);
+                    texture::Texture::from_image(&device, &queue, &img, Some(&label), false)?
+                }
+                None => {
+                    texture::Texture::from_color(&device, &queue, [0, 0, 0, 255], "black", false)
+                }
+            };
+            channels.push(texture);
+        }
+
+        let modified = modified_time(&options.shader);
+        let playground = Playground::new(
+            &device,
+            &queue,
+            options.shader,
+            options.glslc,
+            &channels,
+            sc_desc.format,
+        );
+
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            playground,
+            uniform,
+            modified,
+            last_poll: Instant::now(),
+            paused: false,
+            cursor_position: winit::dpi::PhysicalPosition::new(0.0, 0.0),
+            mouse_down: false,
+            last_update: Instant::now(),
+        })
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+    }
+
+    /// The cursor in Shadertoy's pixel coordinates, with y pointing up.
+    fn mouse_position(&self) -> [f32; 2] {
+        [
+            self.cursor_position.x as f32,
+            self.size.height as f32 - self.cursor_position.y as f32,
+        ]
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                if self.mouse_down {
+                    let [x, y] = self.mouse_position();
+                    self.uniform.mouse[0] = x;
+                    self.uniform.mouse[1] = y;
+                }
+                true
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.mouse_down = *state == ElementState::Pressed;
+                if self.mouse_down {
+                    let [x, y] = self.mouse_position();
+                    self.uniform.mouse = [x, y, x, y];
+                } else {
+                    self.uniform.mouse[2] = -self.uniform.mouse[2].abs();
+                    self.uniform.mouse[3] = -self.uniform.mouse[3].abs();
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Space => {
+                    self.paused = !self.paused;
+                    true
+                }
+                VirtualKeyCode::R => {
+                    self.uniform.time = 0.0;
+                    self.uniform.frame = 0;
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = now - self.last_update;
+        self.last_update = now;
+
+        if now - self.last_poll >= POLL_INTERVAL {
+            self.last_poll = now;
+            let modified = modified_time(self.playground.path());
+            if modified != self.modified {
+                self.modified = modified;
+                if modified.is_some() {
+                    self.playground.reload(&self.device);
+                }
+            }
+        }
+
+        self.uniform.resolution = [self.size.width as f32, self.size.height as f32, 1.0];
+        if self.paused {
+            self.uniform.time_delta = 0.0;
+        } else {
+            self.uniform.time_delta = dt.as_secs_f32();
+            self.uniform.time += self.uniform.time_delta;
+            self.uniform.frame += 1;
+        }
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.playground
+            .render(&self.queue, &mut encoder, &frame.view, &self.uniform);
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
+
+/// None while the file is missing, which editors that save by renaming cause briefly.
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,227 @@
+use std::iter;
+use std::path::PathBuf;
+
+use anyhow::*;
+use cgmath::Matrix4;
+use framework::{
+    sprite::screen_projection,
+    svg::Svg,
+    vector::{GpuTessellation, Tessellator, VectorRenderer},
+};
+use winit::{event::*, window::Window};
+
+/// Empty space kept around the drawing, in window pixels.
+const MARGIN: f32 = 16.0;
+
+pub struct Options {
+    pub drawing: PathBuf,
+}
+
+impl Options {
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut drawing = None;
+        for arg in args {
+            match arg.as_str() {
+                _ if arg.starts_with("--") => bail!("Unknown argument {:?}", arg),
+                _ if drawing.is_none() => drawing = Some(PathBuf::from(arg)),
+                _ => bail!("Only one drawing can be shown at a time"),
+            }
+        }
+        Ok(Self {
+            drawing: drawing.context("Expected a drawing")?,
+        })
+    }
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    path: PathBuf,
+    svg: Svg,
+    renderer: VectorRenderer,
+    mesh: GpuTessellation,
+    anti_aliased: bool,
+    /// Set whenever the drawing has to be tessellated again.
+    dirty: bool,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window, options: Options) -> Result<Self> {
+        let svg = Svg::load(&options.drawing)?;
+        window.set_title(&format!(
+            "{} ({} shapes)",
+            options.drawing.display(),
+            svg.shapes.len()
+        ));
+
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .context("No suitable adapter")?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await?;
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let renderer = VectorRenderer::new(&device, sc_desc.format);
+        // Tessellated again by the first update, at the window's scale
+        let mesh = svg.tessellate(&Tessellator::default()).upload(&device);
+
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            path: options.drawing,
+            svg,
+            renderer,
+            mesh,
+            anti_aliased: true,
+            dirty: true,
+        })
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        // The fringe is a pixel wide, so it depends on the scale
+        self.dirty = true;
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::R => {
+                    // Keep showing the old drawing if the edited one doesn't load
+                    match Svg::load(&self.path) {
+                        Ok(svg) => {
+                            self.svg = svg;
+                            self.dirty = true;
+                        }
+                        Err(e) => eprintln!("{:#}", e),
+                    }
+                    true
+                }
+                VirtualKeyCode::Space => {
+                    self.anti_aliased = !self.anti_aliased;
+                    self.dirty = true;
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+
+        let width = self.size.width as f32;
+        let height = self.size.height as f32;
+        let scale = ((width - MARGIN * 2.0) / self.svg.width)
+            .min((height - MARGIN * 2.0) / self.svg.height)
+            .max(f32::EPSILON);
+
+        // Tessellate in document units with a fringe one window pixel wide
+        let pixel = 1.0 / scale;
+        let tessellator = Tessellator {
+            tolerance: pixel * 0.25,
+            feather: if self.anti_aliased { pixel } else { 0.0 },
+        };
+        self.mesh = self.svg.tessellate(&tessellator).upload(&self.device);
+
+        // Center the drawing in the window
+        let offset = [
+            (width - self.svg.width * scale) / 2.0,
+            (height - self.svg.height * scale) / 2.0,
+            0.0,
+        ];
+        let projection = screen_projection(width, height)
+            * Matrix4::from_translation(offset.into())
+            * Matrix4::from_scale(scale);
+        self.renderer.set_projection(&self.queue, projection);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            self.renderer.draw(&mut render_pass, &self.mesh);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,26 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.5" name="terrain" tilewidth="16" tileheight="16" tilecount="32" columns="8">
+ <image source="terrain.png" width="128" height="64"/>
+ <tile id="4">
+  <animation>
+   <frame tileid="4" duration="250"/>
+   <frame tileid="5" duration="250"/>
+   <frame tileid="6" duration="250"/>
+   <frame tileid="7" duration="250"/>
+  </animation>
+ </tile>
+ <tile id="17">
+  <animation>
+   <frame tileid="17" duration="400"/>
+   <frame tileid="18" duration="400"/>
+  </animation>
+ </tile>
+ <tile id="24">
+  <animation>
+   <frame tileid="24" duration="120"/>
+   <frame tileid="25" duration="120"/>
+   <frame tileid="26" duration="120"/>
+   <frame tileid="27" duration="120"/>
+  </animation>
+ </tile>
+</tileset>
@@ -0,0 +1,308 @@
+use std::iter;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::*;
+use cgmath::Vector2;
+use framework::{
+    camera::Camera2d,
+    tiled::{Layer, Map},
+    tilemap::Tilemap,
+};
+use winit::{event::*, window::Window};
+
+/// Screen pixels per second when panning with the keyboard.
+const PAN_SPEED: f32 = 600.0;
+const ZOOM_STEP: f32 = 1.25;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 16.0;
+/// Pixel scroll deltas, from touchpads, per wheel notch.
+const PIXELS_PER_LINE: f32 = 40.0;
+const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+
+pub struct Options {
+    pub map: PathBuf,
+}
+
+impl Options {
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut map = None;
+        for arg in args {
+            match arg.as_str() {
+                _ if arg.starts_with("--") => bail!("Unknown argument {:?}", arg),
+                _ if map.is_none() => map = Some(PathBuf::from(arg)),
+                _ => bail!("Only one map can be shown at a time"),
+            }
+        }
+        Ok(Self {
+            map: map.context("Expected a map")?,
+        })
+    }
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    tilemap: Tilemap,
+    layer_names: Vec<String>,
+    camera: Camera2d,
+    /// Left, right, up and down.
+    panning: [bool; 4],
+    dragging: bool,
+    cursor_position: Vector2<f32>,
+    /// Last reported number of visible chunks, printed whenever it changes.
+    visible_chunks: usize,
+
+    start: Instant,
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window, options: Options) -> Result<Self> {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .context("No suitable GPU adapter")?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await?;
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let map = Map::load(&options.map)?;
+        let tilemap = Tilemap::new(&device, &queue, sc_desc.format, &map)?;
+        println!(
+            "{}x{} tiles in {} chunks",
+            map.width,
+            map.height,
+            tilemap.chunk_count()
+        );
+        let layer_names = tilemap
+            .layer_names()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        for (index, name) in layer_names.iter().enumerate().take(9) {
+            println!("{}: toggle {:?}", index + 1, name);
+        }
+
+        // Start on the spawn point if the map has one, otherwise in the middle
+        let mut camera = Camera2d::new(size.width as f32, size.height as f32);
+        camera.zoom = 2.0;
+        camera.position = Vector2::new(
+            (map.width * map.tile_width) as f32 / 2.0,
+            (map.height * map.tile_height) as f32 / 2.0,
+        );
+        for layer in &map.layers {
+            if let Layer::Objects(layer) = layer {
+                for object in &layer.objects {
+                    println!(
+                        "{:?} {:?} at ({}, {}){}",
+                        object.kind,
+                        object.name,
+                        object.x,
+                        object.y,
+                        object
+                            .properties
+                            .iter()
+                            .map(|(name, value)| format!(" {}={}", name, value))
+                            .collect::<String>()
+                    );
+                    if object.kind == "spawn" {
+                        camera.position = Vector2::new(object.x, object.y);
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            tilemap,
+            layer_names,
+            camera,
+            panning: [false; 4],
+            dragging: false,
+            cursor_position: Vector2::new(0.0, 0.0),
+            visible_chunks: 0,
+            start: Instant::now(),
+            last_update: Instant::now(),
+        })
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.camera.viewport = Vector2::new(new_size.width as f32, new_size.height as f32);
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = Vector2::new(position.x as f32, position.y as f32);
+                if self.dragging {
+                    self.camera.position -= (position - self.cursor_position) / self.camera.zoom;
+                }
+                self.cursor_position = position;
+                true
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.dragging = *state == ElementState::Pressed;
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+                };
+                // Keep the point under the cursor where it is
+                let before = self.camera.screen_to_world(self.cursor_position);
+                self.camera.zoom =
+                    (self.camera.zoom * ZOOM_STEP.powf(lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+                let after = self.camera.screen_to_world(self.cursor_position);
+                self.camera.position += before - after;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => {
+                let pressed = *state == ElementState::Pressed;
+                let direction = match keycode {
+                    VirtualKeyCode::A | VirtualKeyCode::Left => Some(0),
+                    VirtualKeyCode::D | VirtualKeyCode::Right => Some(1),
+                    VirtualKeyCode::W | VirtualKeyCode::Up => Some(2),
+                    VirtualKeyCode::S | VirtualKeyCode::Down => Some(3),
+                    _ => None,
+                };
+                if let Some(direction) = direction {
+                    self.panning[direction] = pressed;
+                    return true;
+                }
+
+                let layer = match keycode {
+                    VirtualKeyCode::Key1 => 0,
+                    VirtualKeyCode::Key2 => 1,
+                    VirtualKeyCode::Key3 => 2,
+                    VirtualKeyCode::Key4 => 3,
+                    VirtualKeyCode::Key5 => 4,
+                    VirtualKeyCode::Key6 => 5,
+                    VirtualKeyCode::Key7 => 6,
+                    VirtualKeyCode::Key8 => 7,
+                    VirtualKeyCode::Key9 => 8,
+                    _ => return false,
+                };
+                if pressed {
+                    if let Some(name) = self.layer_names.get(layer) {
+                        let visible = !self.tilemap.is_layer_visible(name);
+                        self.tilemap.set_layer_visible(name, visible);
+                        println!("{:?} {}", name, if visible { "shown" } else { "hidden" });
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = (now - self.last_update).min(MAX_FRAME_TIME).as_secs_f32();
+        self.last_update = now;
+
+        let axis = |negative: bool, positive: bool| positive as i32 as f32 - negative as i32 as f32;
+        let pan = Vector2::new(
+            axis(self.panning[0], self.panning[1]),
+            axis(self.panning[2], self.panning[3]),
+        );
+        self.camera.position += pan * PAN_SPEED * dt / self.camera.zoom;
+
+        self.tilemap
+            .update(&self.device, &self.queue, &self.camera, now - self.start);
+        if self.tilemap.visible_chunks() != self.visible_chunks {
+            self.visible_chunks = self.tilemap.visible_chunks();
+            println!("{} chunks visible", self.visible_chunks);
+        }
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            self.tilemap.render(&mut render_pass);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
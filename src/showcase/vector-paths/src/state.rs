@@ -0,0 +1,322 @@
+use std::iter;
+
+use cgmath::Vector2;
+use framework::{
+    sprite::screen_projection,
+    vector::{
+        FillRule, GpuTessellation, LineCap, LineJoin, Path, StrokeStyle, Tessellation, Tessellator,
+        VectorRenderer,
+    },
+};
+use winit::{event::*, window::Window};
+
+/// The scene is laid out in this many units and scaled to fit the window.
+const SCENE_SIZE: Vector2<f32> = Vector2::new(800.0, 600.0);
+const MIN_WIDTH: f32 = 1.0;
+const MAX_WIDTH: f32 = 48.0;
+
+/// tutorial4's pentagon, in clip space.
+const PENTAGON: &[[f32; 2]] = &[
+    [-0.0868241, 0.49240386],
+    [-0.49513406, 0.06958647],
+    [-0.21918549, -0.44939706],
+    [0.35966998, -0.3473291],
+    [0.44147372, 0.2347359],
+];
+
+const YELLOW: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    renderer: VectorRenderer,
+    tessellation: Tessellation,
+    mesh: Option<GpuTessellation>,
+    /// Set whenever something changes and the scene has to be tessellated again.
+    dirty: bool,
+
+    stroke_width: f32,
+    cap: LineCap,
+    fill_rule: FillRule,
+    anti_aliased: bool,
+    /// Control point of the quadratic curve, in scene units.
+    control: Vector2<f32>,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let renderer = VectorRenderer::new(&device, sc_desc.format);
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            renderer,
+            tessellation: Tessellation::new(),
+            mesh: None,
+            dirty: true,
+            stroke_width: 16.0,
+            cap: LineCap::Butt,
+            fill_rule: FillRule::EvenOdd,
+            anti_aliased: true,
+            control: Vector2::new(220.0, 380.0),
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        // The fringe is a pixel wide, so it depends on the scale
+        self.dirty = true;
+    }
+
+    /// Window pixels per scene unit.
+    fn scale(&self) -> f32 {
+        (self.size.width as f32 / SCENE_SIZE.x)
+            .min(self.size.height as f32 / SCENE_SIZE.y)
+            .max(f32::EPSILON)
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let scale = self.scale();
+                self.control = Vector2::new(position.x as f32, position.y as f32) / scale;
+                self.dirty = true;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => {
+                match keycode {
+                    VirtualKeyCode::Up => {
+                        self.stroke_width = (self.stroke_width + 2.0).min(MAX_WIDTH)
+                    }
+                    VirtualKeyCode::Down => {
+                        self.stroke_width = (self.stroke_width - 2.0).max(MIN_WIDTH)
+                    }
+                    VirtualKeyCode::C => {
+                        self.cap = match self.cap {
+                            LineCap::Butt => LineCap::Round,
+                            LineCap::Round => LineCap::Square,
+                            LineCap::Square => LineCap::Butt,
+                        }
+                    }
+                    VirtualKeyCode::F => {
+                        self.fill_rule = match self.fill_rule {
+                            FillRule::NonZero => FillRule::EvenOdd,
+                            FillRule::EvenOdd => FillRule::NonZero,
+                        }
+                    }
+                    VirtualKeyCode::Space => self.anti_aliased = !self.anti_aliased,
+                    _ => return false,
+                }
+                self.dirty = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+
+        let scale = self.scale();
+        // A pixel in scene units, for both the fringe and the curve tolerance
+        let pixel = 1.0 / scale;
+        let tessellator = Tessellator {
+            tolerance: pixel * 0.25,
+            feather: if self.anti_aliased { pixel } else { 0.0 },
+        };
+        let output = &mut self.tessellation;
+        output.clear();
+
+        // One zigzag per join, with a hairline along the path they follow
+        let joins = [LineJoin::Miter, LineJoin::Round, LineJoin::Bevel];
+        for (i, join) in joins.iter().enumerate() {
+            let x = 60.0 + i as f32 * 250.0;
+            let zigzag = Path::polyline(
+                &[
+                    Vector2::new(x, 140.0),
+                    Vector2::new(x + 50.0, 60.0),
+                    Vector2::new(x + 100.0, 140.0),
+                    Vector2::new(x + 150.0, 80.0),
+                    Vector2::new(x + 180.0, 140.0),
+                ],
+                false,
+            );
+            let style = StrokeStyle {
+                width: self.stroke_width,
+                join: *join,
+                cap: self.cap,
+                ..Default::default()
+            };
+            tessellator.stroke(output, &zigzag, &style, YELLOW);
+            tessellator.stroke(output, &zigzag, &StrokeStyle::default(), BLACK);
+        }
+
+        // A pentagram overlaps itself, so the fill rule decides whether its center is filled
+        let star = (0..5)
+            .map(|i| {
+                let angle = i as f32 * 4.0 * std::f32::consts::PI / 5.0;
+                Vector2::new(150.0 + angle.sin() * 110.0, 320.0 - angle.cos() * 110.0)
+            })
+            .collect::<Vec<_>>();
+        let star = Path::polyline(&star, true);
+        tessellator.fill(output, &star, self.fill_rule, [0.9, 0.3, 0.3, 1.0]);
+
+        // tutorial4's pentagon, scaled up from clip space and flipped to y down
+        let pentagon = PENTAGON
+            .iter()
+            .map(|[x, y]| Vector2::new(400.0 + x * 220.0, 320.0 - y * 220.0))
+            .collect::<Vec<_>>();
+        let pentagon = Path::polyline(&pentagon, true);
+        tessellator.fill(output, &pentagon, FillRule::NonZero, [0.5, 0.0, 0.5, 1.0]);
+        let outline = StrokeStyle {
+            width: 4.0,
+            join: LineJoin::Round,
+            ..Default::default()
+        };
+        tessellator.stroke(output, &pentagon, &outline, WHITE);
+
+        let mut heart = Path::new();
+        heart
+            .move_to(Vector2::new(650.0, 420.0))
+            .cubic_to(
+                Vector2::new(530.0, 330.0),
+                Vector2::new(570.0, 200.0),
+                Vector2::new(650.0, 260.0),
+            )
+            .cubic_to(
+                Vector2::new(730.0, 200.0),
+                Vector2::new(770.0, 330.0),
+                Vector2::new(650.0, 420.0),
+            )
+            .close();
+        tessellator.fill(output, &heart, FillRule::NonZero, [0.8, 0.2, 0.6, 1.0]);
+        tessellator.stroke(output, &heart, &outline, WHITE);
+
+        // A quadratic curve bending towards the cursor, with its control polygon
+        let from = Vector2::new(60.0, 540.0);
+        let to = Vector2::new(740.0, 540.0);
+        let mut curve = Path::new();
+        curve.move_to(from).quadratic_to(self.control, to);
+        let style = StrokeStyle {
+            width: self.stroke_width / 2.0,
+            cap: self.cap,
+            ..Default::default()
+        };
+        tessellator.stroke(output, &curve, &style, [0.3, 0.6, 1.0, 1.0]);
+        let hull = Path::polyline(&[from, self.control, to], false);
+        tessellator.stroke(output, &hull, &StrokeStyle::default(), WHITE);
+        tessellator.fill(
+            output,
+            &Path::circle(self.control, 5.0),
+            FillRule::NonZero,
+            WHITE,
+        );
+
+        self.mesh = Some(self.tessellation.upload(&self.device));
+        let projection = screen_projection(
+            self.size.width as f32 / scale,
+            self.size.height as f32 / scale,
+        );
+        self.renderer.set_projection(&self.queue, projection);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            if let Some(mesh) = &self.mesh {
+                self.renderer.draw(&mut render_pass, mesh);
+            }
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::*;
+use framework::atlas::{Manifest, Page, Region};
+use image::{GenericImageView, RgbaImage};
+
+mod packer;
+use packer::{pack_pages, Item};
+
+const USAGE: &str = "Usage: atlas-packer <input dir> <output dir> [--name atlas] [--max-size 2048] [--padding 2] [--extrude 1]";
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "tga"];
+
+struct Options {
+    input: PathBuf,
+    output: PathBuf,
+    name: String,
+    /// Largest page side, a power of two.
+    max_size: u32,
+    /// Empty pixels between neighbouring images.
+    padding: u32,
+    /// How many times each image's border pixels are repeated outwards.
+    extrude: u32,
+}
+
+impl Options {
+    fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut name = "atlas".to_string();
+        let mut max_size = 2048;
+        let mut padding = 2;
+        let mut extrude = 1;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("{} needs a value", arg))
+            };
+            let number = |value: String| -> Result<u32> {
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid number {:?}", value))
+            };
+            match arg.as_str() {
+                "--name" => name = value()?,
+                "--max-size" => {
+                    max_size = number(value()?)?;
+                    ensure!(
+                        max_size.is_power_of_two(),
+                        "--max-size must be a power of two"
+                    );
+                }
+                "--padding" => padding = number(value()?)?,
+                "--extrude" => extrude = number(value()?)?,
+                _ if arg.starts_with("--") => bail!("Unknown argument {:?}", arg),
+                _ => positional.push(arg),
+            }
+        }
+
+        ensure!(
+            positional.len() == 2,
+            "Expected an input and an output directory"
+        );
+        Ok(Self {
+            input: positional[0].clone().into(),
+            output: positional[1].clone().into(),
+            name,
+            max_size,
+            padding,
+            extrude,
+        })
+    }
+}
+
+/// Every image below `directory`, named by its relative path without the extension and
+/// with forward slashes, sorted by name.
+fn find_images(directory: &Path) -> Result<Vec<(String, PathBuf)>> {
+    fn visit(directory: &Path, prefix: &str, images: &mut Vec<(String, PathBuf)>) -> Result<()> {
+        let entries = std::fs::read_dir(directory)
+            .with_context(|| format!("Couldn't read {:?}", directory))?;
+        for entry in entries {
+            let path = entry?.path();
+            let stem = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(stem) => stem,
+                None => continue,
+            };
+            if path.is_dir() {
+                let file_name = path.file_name().unwrap().to_string_lossy();
+                visit(&path, &format!("{}{}/", prefix, file_name), images)?;
+                continue;
+            }
+            let is_image = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+                });
+            if is_image {
+                images.push((format!("{}{}", prefix, stem), path));
+            }
+        }
+        Ok(())
+    }
+
+    let mut images = Vec::new();
+    visit(directory, "", &mut images)?;
+    images.sort();
+    for pair in images.windows(2) {
+        ensure!(
+            pair[0].0 != pair[1].0,
+            "{:?} and {:?} would both be called {:?}",
+            pair[0].1,
+            pair[1].1,
+            pair[0].0
+        );
+    }
+    Ok(images)
+}
+
+/// Copies `source` into `page` with its top left corner at (x, y), repeating its edge
+/// pixels `extrude` times on every side.
+fn blit_extruded(page: &mut RgbaImage, source: &RgbaImage, x: u32, y: u32, extrude: u32) {
+    let (width, height) = source.dimensions();
+    let extrude = extrude as i64;
+    for dy in -extrude..height as i64 + extrude {
+        for dx in -extrude..width as i64 + extrude {
+            let (target_x, target_y) = (x as i64 + dx, y as i64 + dy);
+            if target_x < 0
+                || target_y < 0
+                || target_x >= page.width() as i64
+                || target_y >= page.height() as i64
+            {
+                continue;
+            }
+            let pixel = source.get_pixel(
+                dx.clamp(0, width as i64 - 1) as u32,
+                dy.clamp(0, height as i64 - 1) as u32,
+            );
+            page.put_pixel(target_x as u32, target_y as u32, *pixel);
+        }
+    }
+}
+
+fn run(options: Options) -> Result<()> {
+    let images = find_images(&options.input)?;
+    ensure!(!images.is_empty(), "No images found in {:?}", options.input);
+
+    let mut loaded = Vec::new();
+    for (name, path) in &images {
+        let img = image::open(path).with_context(|| format!("Couldn't open {:?}", path))?;
+        ensure!(img.width() > 0 && img.height() > 0, "{:?} is empty", path);
+        loaded.push((name.clone(), img.to_rgba8()));
+    }
+
+    let border = options.extrude * 2;
+    let items = loaded
+        .iter()
+        .enumerate()
+        .map(|(index, (_, img))| Item {
+            index,
+            width: img.width() + border,
+            height: img.height() + border,
+        })
+        .collect::<Vec<_>>();
+    let (pages, too_large) = pack_pages(&items, options.max_size, options.padding);
+    if let Some(item) = too_large.first() {
+        bail!(
+            "{:?} doesn't fit in a {}x{} page",
+            images[item.index].1,
+            options.max_size,
+            options.max_size
+        );
+    }
+
+    std::fs::create_dir_all(&options.output)
+        .with_context(|| format!("Couldn't create {:?}", options.output))?;
+    let mut manifest = Manifest::default();
+    for (page_index, packed) in pages.iter().enumerate() {
+        let mut page = RgbaImage::new(packed.width, packed.height);
+        for placement in &packed.placements {
+            let (name, img) = &loaded[placement.index];
+            let x = placement.x + options.extrude;
+            let y = placement.y + options.extrude;
+            blit_extruded(&mut page, img, x, y, options.extrude);
+            manifest.regions.push(Region {
+                name: name.clone(),
+                page: page_index,
+                x,
+                y,
+                width: img.width(),
+                height: img.height(),
+            });
+        }
+
+        let file_name = format!("{}-{}.png", options.name, page_index);
+        let path = options.output.join(&file_name);
+        page.save(&path)
+            .with_context(|| format!("Couldn't write {:?}", path))?;
+        println!(
+            "{:?}: {}x{} with {} images",
+            path,
+            packed.width,
+            packed.height,
+            packed.placements.len()
+        );
+        manifest.pages.push(Page {
+            image: file_name,
+            width: packed.width,
+            height: packed.height,
+        });
+    }
+    manifest.regions.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let path = options.output.join(format!("{}.json", options.name));
+    std::fs::write(&path, manifest.to_json())
+        .with_context(|| format!("Couldn't write {:?}", path))?;
+    println!("{:?}: {} images", path, manifest.regions.len());
+    Ok(())
+}
+
+fn main() {
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = run(options) {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,212 @@
+/// A rectangle to place, identified by its index in the caller's list.
+#[derive(Copy, Clone, Debug)]
+pub struct Item {
+    pub index: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Placement {
+    pub index: usize,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// One atlas image and what went on it.
+#[derive(Clone, Debug)]
+pub struct PackedPage {
+    pub width: u32,
+    pub height: u32,
+    pub placements: Vec<Placement>,
+}
+
+/// Places items in rows, tallest first, within a `width` x `height` page. `spacing` is
+/// kept between neighbouring items but not along the page's edges. Returns what fit and
+/// what didn't.
+pub fn pack_shelves(
+    items: &[Item],
+    width: u32,
+    height: u32,
+    spacing: u32,
+) -> (Vec<Placement>, Vec<Item>) {
+    let mut sorted = items.to_vec();
+    sorted.sort_by(|a, b| {
+        b.height
+            .cmp(&a.height)
+            .then(b.width.cmp(&a.width))
+            .then(a.index.cmp(&b.index))
+    });
+
+    let mut placed = Vec::new();
+    let mut leftover = Vec::new();
+    let mut x = 0;
+    let mut y = 0;
+    let mut shelf_height = 0;
+    for item in sorted {
+        if item.width > width || item.height > height {
+            leftover.push(item);
+            continue;
+        }
+        if x + item.width > width {
+            // Start the next row
+            x = 0;
+            y += shelf_height + spacing;
+            shelf_height = 0;
+        }
+        if y + item.height > height {
+            leftover.push(item);
+            continue;
+        }
+        placed.push(Placement {
+            index: item.index,
+            x,
+            y,
+        });
+        x += item.width + spacing;
+        shelf_height = shelf_height.max(item.height);
+    }
+    (placed, leftover)
+}
+
+/// Splits `items` into pages no larger than `max_size` on a side. Every page is as small
+/// as it can be while staying a power of two, which the last page benefits from most.
+/// Items larger than `max_size` are returned separately.
+pub fn pack_pages(items: &[Item], max_size: u32, spacing: u32) -> (Vec<PackedPage>, Vec<Item>) {
+    let (mut remaining, too_large): (Vec<_>, Vec<_>) = items
+        .iter()
+        .copied()
+        .partition(|item| item.width <= max_size && item.height <= max_size);
+
+    let mut pages = Vec::new();
+    while !remaining.is_empty() {
+        let largest = remaining
+            .iter()
+            .map(|item| item.width.max(item.height))
+            .max()
+            .unwrap_or(1)
+            .next_power_of_two();
+
+        // The smallest page holding everything left, trying the flatter shape first
+        let mut size = largest;
+        let fitting = loop {
+            let (width, height) = (size, size / 2);
+            if height >= largest {
+                let (placed, leftover) = pack_shelves(&remaining, width, height, spacing);
+                if leftover.is_empty() {
+                    break Some(PackedPage {
+                        width,
+                        height,
+                        placements: placed,
+                    });
+                }
+            }
+            let (placed, leftover) = pack_shelves(&remaining, size, size, spacing);
+            if leftover.is_empty() {
+                break Some(PackedPage {
+                    width: size,
+                    height: size,
+                    placements: placed,
+                });
+            }
+            if size >= max_size {
+                break None;
+            }
+            size *= 2;
+        };
+
+        match fitting {
+            Some(page) => {
+                pages.push(page);
+                remaining.clear();
+            }
+            None => {
+                // Fill a page of the largest size and carry on with the rest
+                let (placed, leftover) = pack_shelves(&remaining, max_size, max_size, spacing);
+                pages.push(PackedPage {
+                    width: max_size,
+                    height: max_size,
+                    placements: placed,
+                });
+                remaining = leftover;
+            }
+        }
+    }
+    (pages, too_large)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Items of assorted sizes from a fixed linear congruential sequence.
+    fn items(count: usize) -> Vec<Item> {
+        let mut seed = 12345u32;
+        let mut next = |max: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            1 + (seed >> 16) % max
+        };
+        (0..count)
+            .map(|index| Item {
+                index,
+                width: next(100),
+                height: next(100),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pages_have_no_overlaps() {
+        let items = items(200);
+        let spacing = 2;
+        let (pages, too_large) = pack_pages(&items, 512, spacing);
+        assert!(too_large.is_empty());
+
+        let mut placed = vec![false; items.len()];
+        for page in &pages {
+            let rects = page
+                .placements
+                .iter()
+                .map(|placement| {
+                    let item = items[placement.index];
+                    assert!(placement.x + item.width <= page.width);
+                    assert!(placement.y + item.height <= page.height);
+                    assert!(!placed[placement.index], "item placed twice");
+                    placed[placement.index] = true;
+                    (placement.x, placement.y, item.width, item.height)
+                })
+                .collect::<Vec<_>>();
+            for (i, a) in rects.iter().enumerate() {
+                for b in &rects[i + 1..] {
+                    let apart = a.0 + a.2 + spacing <= b.0
+                        || b.0 + b.2 + spacing <= a.0
+                        || a.1 + a.3 + spacing <= b.1
+                        || b.1 + b.3 + spacing <= a.1;
+                    assert!(apart, "{:?} and {:?} overlap", a, b);
+                }
+            }
+        }
+        assert!(placed.iter().all(|&placed| placed));
+    }
+
+    #[test]
+    fn large_items_are_left_out() {
+        let items = [
+            Item {
+                index: 0,
+                width: 600,
+                height: 10,
+            },
+            Item {
+                index: 1,
+                width: 10,
+                height: 10,
+            },
+        ];
+        let (pages, too_large) = pack_pages(&items, 512, 0);
+        assert_eq!(too_large.len(), 1);
+        assert_eq!(too_large[0].index, 0);
+        assert_eq!(pages.len(), 1);
+        assert_eq!((pages[0].width, pages[0].height), (16, 16));
+    }
+}
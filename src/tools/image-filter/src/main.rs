@@ -0,0 +1,161 @@
+use std::iter;
+use std::path::PathBuf;
+
+use anyhow::*;
+use framework::{
+    filter::{Filter, ImageFilters},
+    texture,
+};
+use image::GenericImageView;
+
+const USAGE: &str =
+    "Usage: image-filter <input> <output> <filter>... [--compare reference.png] [--tolerance 1]";
+
+struct Options {
+    input: PathBuf,
+    output: PathBuf,
+    filters: Vec<Filter>,
+    compare: Option<PathBuf>,
+    /// Largest per channel difference `--compare` accepts.
+    tolerance: u8,
+}
+
+impl Options {
+    fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut compare = None;
+        let mut tolerance = 1;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("{} needs a value", arg))
+            };
+            match arg.as_str() {
+                "--compare" => compare = Some(value()?.into()),
+                "--tolerance" => {
+                    let value = value()?;
+                    tolerance = value
+                        .parse()
+                        .with_context(|| format!("Invalid tolerance {:?}", value))?;
+                }
+                _ if arg.starts_with("--") => bail!("Unknown argument {:?}", arg),
+                _ => positional.push(arg),
+            }
+        }
+
+        ensure!(
+            positional.len() >= 3,
+            "Expected an input, an output and at least one filter"
+        );
+        let filters = positional[2..]
+            .iter()
+            .map(|filter| filter.parse())
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            input: positional[0].clone().into(),
+            output: positional[1].clone().into(),
+            filters,
+            compare,
+            tolerance,
+        })
+    }
+}
+
+async fn run(options: Options) -> Result<()> {
+    let img = image::open(&options.input)
+        .with_context(|| format!("Couldn't open {:?}", options.input))?;
+    let (width, height) = img.dimensions();
+
+    // No surface to present to, so any adapter will do
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+        })
+        .await
+        .context("No suitable GPU adapter")?;
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None, // Trace path
+        )
+        .await?;
+
+    let input = texture::Texture::from_image(&device, &queue, &img, Some("input"), false)?;
+    let filters = ImageFilters::new(&device);
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Filter Encoder"),
+    });
+    let mut output = None;
+    for filter in &options.filters {
+        let source = output.as_ref().unwrap_or(&input);
+        let filtered = filters.apply(&device, &mut encoder, source, width, height, filter);
+        output = Some(filtered);
+    }
+    queue.submit(iter::once(encoder.finish()));
+
+    let output = output.unwrap();
+    let result = texture::read_to_image(
+        &device,
+        &queue,
+        &output.texture,
+        ImageFilters::FORMAT,
+        width,
+        height,
+    )
+    .await?;
+    result
+        .save(&options.output)
+        .with_context(|| format!("Couldn't write {:?}", options.output))?;
+
+    if let Some(path) = &options.compare {
+        let reference = image::open(path)
+            .with_context(|| format!("Couldn't open {:?}", path))?
+            .to_rgba8();
+        ensure!(
+            reference.dimensions() == result.dimensions(),
+            "{:?} is {:?} but the result is {:?}",
+            path,
+            reference.dimensions(),
+            result.dimensions()
+        );
+        let difference = reference
+            .pixels()
+            .zip(result.pixels())
+            .flat_map(|(a, b)| a.0.iter().zip(b.0.iter()).map(|(a, b)| a.max(b) - a.min(b)))
+            .max()
+            .unwrap_or(0);
+        ensure!(
+            difference <= options.tolerance,
+            "Result differs from {:?} by up to {}, more than the tolerance of {}",
+            path,
+            difference,
+            options.tolerance
+        );
+        println!("Matches {:?} within {}", path, difference);
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    use futures::executor::block_on;
+    if let Err(e) = block_on(run(options)) {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,427 @@
+use std::iter;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use cgmath::prelude::*;
+use framework::{
+    camera::{Camera, CameraBinding, CameraController},
+    instance::{Instance, InstanceRaw},
+    light::{DirectionalLight, LightBinding, Lights},
+    material::{DefaultTextures, Material, MaterialFactors, MaterialMaps},
+    mesh::{GpuMesh, Mesh, ModelVertex},
+    skybox::Skybox,
+    texture,
+};
+use wgpu::util::DeviceExt;
+use winit::{event::*, window::Window};
+
+use crate::sky;
+
+const SPHERE_COUNT: usize = 5;
+const SPHERE_SPACING: f32 = 1.2;
+const FACE_SIZE: u32 = 512;
+
+struct SceneObject {
+    mesh: GpuMesh,
+    instances: Vec<Instance>,
+    instance_buffer: wgpu::Buffer,
+}
+
+impl SceneObject {
+    fn new(device: &wgpu::Device, mesh: Mesh, instances: Vec<Instance>) -> Self {
+        let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Instance Buffer", mesh.name)),
+            contents: bytemuck::cast_slice(&instance_data),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+
+        Self {
+            mesh: mesh.upload(device),
+            instances,
+            instance_buffer,
+        }
+    }
+
+    fn write_instances(&self, queue: &wgpu::Queue) {
+        let instance_data = self
+            .instances
+            .iter()
+            .map(Instance::to_raw)
+            .collect::<Vec<_>>();
+        queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&instance_data),
+        );
+    }
+}
+
+/// Loads the sky from the command line arguments: one `.hdr` panorama or six face
+/// images. Anything else, or a file that fails to load, falls back to the procedural sky.
+fn load_sky(device: &wgpu::Device, queue: &wgpu::Queue, paths: &[PathBuf]) -> texture::Texture {
+    let loaded = match paths.len() {
+        0 => None,
+        1 => std::fs::read(&paths[0])
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| {
+                texture::Texture::cube_from_hdr_bytes(device, queue, &bytes, FACE_SIZE, "sky")
+            })
+            .map_err(|e| eprintln!("Couldn't load {}: {}", paths[0].display(), e))
+            .ok(),
+        6 => paths
+            .iter()
+            .map(image::open)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(anyhow::Error::from)
+            .and_then(|faces| texture::Texture::cube_from_faces(device, queue, &faces, "sky"))
+            .map_err(|e| eprintln!("Couldn't load the sky faces: {}", e))
+            .ok(),
+        n => {
+            eprintln!("Expected 1 panorama or 6 face images, got {} paths", n);
+            None
+        }
+    };
+
+    loaded.unwrap_or_else(|| {
+        texture::Texture::cube_from_equirectangular(
+            device,
+            queue,
+            sky::WIDTH,
+            sky::HEIGHT,
+            &sky::panorama(),
+            FACE_SIZE,
+            "procedural_sky",
+        )
+    })
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline: wgpu::RenderPipeline,
+    depth_texture: texture::Texture,
+
+    camera: Camera,
+    camera_controller: CameraController,
+    camera_binding: CameraBinding,
+
+    light_binding: LightBinding,
+
+    #[allow(dead_code)]
+    sky_texture: texture::Texture,
+    environment_bind_group: wgpu::BindGroup,
+    skybox: Skybox,
+
+    /// One instance per sphere, drawn one at a time since each has its own material.
+    spheres: SceneObject,
+    sphere_materials: Vec<Material>,
+    pentagon: SceneObject,
+    pentagon_material: Material,
+
+    start_time: Instant,
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    /// `sky_paths` are the command line arguments, see `load_sky`.
+    pub async fn new(window: &Window, sky_paths: &[PathBuf]) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let depth_texture =
+            texture::Texture::create_depth_texture(&device, &sc_desc, "depth_texture");
+
+        let material_bind_group_layout = Material::bind_group_layout(&device);
+        let default_textures = DefaultTextures::new(&device, &queue);
+
+        // Polished metal on the left through to fully rough on the right.
+        let sphere_materials = (0..SPHERE_COUNT)
+            .map(|i| {
+                Material::new(
+                    &device,
+                    &material_bind_group_layout,
+                    &default_textures,
+                    &format!("sphere_{}", i),
+                    MaterialFactors {
+                        base_color: [0.95, 0.93, 0.88, 1.0],
+                        metallic: 1.0,
+                        roughness: i as f32 / (SPHERE_COUNT - 1) as f32,
+                        ..Default::default()
+                    },
+                    MaterialMaps::default(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // A dielectric only reflects much at grazing angles.
+        let pentagon_material = Material::new(
+            &device,
+            &material_bind_group_layout,
+            &default_textures,
+            "pentagon",
+            MaterialFactors {
+                base_color: [0.6, 0.05, 0.05, 1.0],
+                metallic: 0.0,
+                roughness: 0.1,
+                ..Default::default()
+            },
+            MaterialMaps::default(),
+        );
+
+        let sky_texture = load_sky(&device, &queue, sky_paths);
+        let environment_bind_group_layout = texture::Texture::cube_bind_group_layout(&device);
+        let environment_bind_group = sky_texture.bind_group(
+            &device,
+            &environment_bind_group_layout,
+            Some("environment_bind_group"),
+        );
+
+        let camera = Camera::new(
+            (0.0, 1.0, 6.0).into(),
+            (0.0, 0.0, 0.0).into(),
+            sc_desc.width as f32 / sc_desc.height as f32,
+        );
+        let camera_controller = CameraController::new(4.0);
+        let camera_binding = CameraBinding::new(&device, &camera);
+
+        let mut lights = Lights::new();
+        lights.directional_lights.push(DirectionalLight {
+            direction: -sky::sun_direction(),
+            color: (1.0, 0.9, 0.8).into(),
+            intensity: 3.0,
+        });
+        let light_binding = LightBinding::new(&device, &lights);
+
+        let skybox = Skybox::new(
+            &device,
+            &camera_binding.bind_group_layout,
+            &sky_texture,
+            sc_desc.format,
+            texture::Texture::DEPTH_FORMAT,
+        );
+
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("shader.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("shader.frag.spv"));
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &material_bind_group_layout,
+                    &camera_binding.bind_group_layout,
+                    &light_binding.bind_group_layout,
+                    &environment_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &vs_module,
+                entry_point: "main",
+                buffers: &[ModelVertex::desc(), InstanceRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fs_module,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format: sc_desc.format,
+                    alpha_blend: wgpu::BlendState::REPLACE,
+                    color_blend: wgpu::BlendState::REPLACE,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+                clamp_depth: false,
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        let offset = (SPHERE_COUNT - 1) as f32 * SPHERE_SPACING * 0.5;
+        let spheres = SceneObject::new(
+            &device,
+            Mesh::uv_sphere(0.5, 48, 24),
+            (0..SPHERE_COUNT)
+                .map(|i| Instance::new((i as f32 * SPHERE_SPACING - offset, 0.0, 0.0).into()))
+                .collect(),
+        );
+
+        let pentagon = SceneObject::new(
+            &device,
+            Mesh::pentagon_prism(0.2),
+            vec![Instance::new((0.0, 1.4, -1.0).into())],
+        );
+
+        let now = Instant::now();
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline,
+            depth_texture,
+            camera,
+            camera_controller,
+            camera_binding,
+            light_binding,
+            sky_texture,
+            environment_bind_group,
+            skybox,
+            spheres,
+            sphere_materials,
+            pentagon,
+            pentagon_material,
+            start_time: now,
+            last_update: now,
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.depth_texture =
+            texture::Texture::create_depth_texture(&self.device, &self.sc_desc, "depth_texture");
+        self.camera.aspect = self.sc_desc.width as f32 / self.sc_desc.height as f32;
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        self.camera_controller.process_events(event)
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = now - self.last_update;
+        self.last_update = now;
+        let time = (now - self.start_time).as_secs_f32();
+
+        self.camera_controller.update_camera(&mut self.camera, dt);
+        self.camera_binding.update(&self.queue, &self.camera);
+
+        self.pentagon.instances[0].rotation =
+            cgmath::Quaternion::from_angle_y(cgmath::Rad(time * 0.5));
+        self.pentagon.write_instances(&self.queue);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                    attachment: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(1, &self.camera_binding.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.light_binding.bind_group, &[]);
+            render_pass.set_bind_group(3, &self.environment_bind_group, &[]);
+
+            render_pass.set_bind_group(0, &self.pentagon_material.bind_group, &[]);
+            render_pass.set_vertex_buffer(1, self.pentagon.instance_buffer.slice(..));
+            self.pentagon.mesh.draw(&mut render_pass, 0..1);
+
+            render_pass.set_vertex_buffer(1, self.spheres.instance_buffer.slice(..));
+            for (i, material) in self.sphere_materials.iter().enumerate() {
+                let i = i as u32;
+                render_pass.set_bind_group(0, &material.bind_group, &[]);
+                self.spheres.mesh.draw(&mut render_pass, i..i + 1);
+            }
+
+            // Last, so the depth test skips every pixel already covered by the scene
+            self.skybox
+                .draw(&mut render_pass, &self.camera_binding.bind_group);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
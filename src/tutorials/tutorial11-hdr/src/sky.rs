@@ -0,0 +1,53 @@
+//! A procedural sky panorama so the tutorial works without any image files.
+
+use cgmath::prelude::*;
+use cgmath::Vector3;
+
+pub const WIDTH: u32 = 1024;
+pub const HEIGHT: u32 = 512;
+
+/// Direction from the ground towards the sun.
+pub fn sun_direction() -> Vector3<f32> {
+    Vector3::new(0.4, 0.35, -0.8).normalize()
+}
+
+fn mix(a: Vector3<f32>, b: Vector3<f32>, t: f32) -> Vector3<f32> {
+    a * (1.0 - t) + b * t
+}
+
+/// An equirectangular panorama in linear color. The sun is far brighter than 1.0,
+/// which is what makes reflections of it look right.
+pub fn panorama() -> Vec<[f32; 3]> {
+    let zenith = Vector3::new(0.15, 0.35, 0.8);
+    let horizon = Vector3::new(0.75, 0.8, 0.85);
+    let ground = Vector3::new(0.25, 0.22, 0.2);
+    let sun = sun_direction();
+
+    let mut pixels = Vec::with_capacity((WIDTH * HEIGHT) as usize);
+    for y in 0..HEIGHT {
+        let latitude = (0.5 - (y as f32 + 0.5) / HEIGHT as f32) * std::f32::consts::PI;
+        for x in 0..WIDTH {
+            let longitude = ((x as f32 + 0.5) / WIDTH as f32 - 0.5) * 2.0 * std::f32::consts::PI;
+            let direction = Vector3::new(
+                latitude.cos() * longitude.cos(),
+                latitude.sin(),
+                latitude.cos() * longitude.sin(),
+            );
+
+            let mut color = if direction.y >= 0.0 {
+                mix(horizon, zenith, direction.y.powf(0.5))
+            } else {
+                mix(horizon, ground, (-direction.y * 8.0).min(1.0))
+            };
+
+            let sun_amount = direction.dot(sun).max(0.0);
+            color += Vector3::new(1.0, 0.8, 0.5) * sun_amount.powf(64.0) * 0.5;
+            if sun_amount > 0.9995 {
+                color += Vector3::new(40.0, 36.0, 30.0);
+            }
+
+            pixels.push(color.into());
+        }
+    }
+    pixels
+}
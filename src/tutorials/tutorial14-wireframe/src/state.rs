@@ -0,0 +1,484 @@
+use std::iter;
+use std::time::Instant;
+
+use cgmath::prelude::*;
+use framework::{
+    camera::{Camera, CameraBinding, CameraController},
+    instance::{Instance, InstanceRaw},
+    light::{DirectionalLight, LightBinding, LightGizmos, Lights, PointLight, SpotLight},
+    mesh::{GpuMesh, Mesh, ModelVertex},
+    texture,
+    wireframe::{polygon_mode_features, DrawMode},
+};
+use wgpu::util::DeviceExt;
+use winit::{event::*, window::Window};
+
+struct SceneObject {
+    mesh: GpuMesh,
+    instances: Vec<Instance>,
+    instance_buffer: wgpu::Buffer,
+}
+
+impl SceneObject {
+    fn new(device: &wgpu::Device, mesh: Mesh, instances: Vec<Instance>) -> Self {
+        let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Instance Buffer", mesh.name)),
+            contents: bytemuck::cast_slice(&instance_data),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+
+        Self {
+            mesh: mesh.upload(device),
+            instances,
+            instance_buffer,
+        }
+    }
+
+    fn write_instances(&self, queue: &wgpu::Queue) {
+        let instance_data = self
+            .instances
+            .iter()
+            .map(Instance::to_raw)
+            .collect::<Vec<_>>();
+        queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&instance_data),
+        );
+    }
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    render_pipeline: wgpu::RenderPipeline,
+    draw_mode: DrawMode,
+    // Whether the device was created with Features::NON_FILL_POLYGON_MODE.
+    supports_polygon_mode: bool,
+    // Use the line-list fallback even when polygon modes are supported.
+    force_fallback: bool,
+    depth_texture: texture::Texture,
+
+    #[allow(dead_code)]
+    diffuse_texture: texture::Texture,
+    diffuse_bind_group: wgpu::BindGroup,
+
+    camera: Camera,
+    camera_controller: CameraController,
+    camera_binding: CameraBinding,
+
+    lights: Lights,
+    light_binding: LightBinding,
+    light_gizmos: LightGizmos,
+    show_gizmos: bool,
+
+    ground: SceneObject,
+    pentagons: SceneObject,
+    props: SceneObject,
+    spheres: SceneObject,
+
+    start_time: Instant,
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        // Only ask for what the adapter has, requesting an unsupported feature fails.
+        let features = polygon_mode_features(&adapter);
+        let supports_polygon_mode = features.contains(wgpu::Features::NON_FILL_POLYGON_MODE);
+        if !supports_polygon_mode {
+            println!("NON_FILL_POLYGON_MODE is not supported, drawing wireframes with line lists");
+        }
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features,
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let depth_texture =
+            texture::Texture::create_depth_texture(&device, &sc_desc, "depth_texture");
+
+        let diffuse_bytes = include_bytes!("happy-tree.png");
+        let diffuse_texture =
+            texture::Texture::from_bytes(&device, &queue, diffuse_bytes, "happy-tree.png", false)
+                .unwrap();
+        let texture_bind_group_layout = texture::Texture::bind_group_layout(&device);
+        let diffuse_bind_group = diffuse_texture.bind_group(
+            &device,
+            &texture_bind_group_layout,
+            Some("diffuse_bind_group"),
+        );
+
+        let camera = Camera::new(
+            (0.0, 3.0, 6.0).into(),
+            (0.0, 0.0, 0.0).into(),
+            sc_desc.width as f32 / sc_desc.height as f32,
+        );
+        let camera_controller = CameraController::new(4.0);
+        let camera_binding = CameraBinding::new(&device, &camera);
+
+        let mut lights = Lights::new();
+        lights.point_lights.push(PointLight {
+            position: (2.0, 1.0, 0.0).into(),
+            color: (1.0, 0.3, 0.2).into(),
+            intensity: 4.0,
+            range: 6.0,
+        });
+        lights.point_lights.push(PointLight {
+            position: (-2.0, 1.0, 0.0).into(),
+            color: (0.2, 0.4, 1.0).into(),
+            intensity: 4.0,
+            range: 6.0,
+        });
+        lights.directional_lights.push(DirectionalLight {
+            direction: (-0.4, -1.0, -0.3).into(),
+            color: (1.0, 0.95, 0.8).into(),
+            intensity: 0.4,
+        });
+        lights.spot_lights.push(SpotLight {
+            position: (0.0, 3.0, 1.5).into(),
+            direction: (0.0, -1.0, -0.5).into(),
+            color: (1.0, 1.0, 1.0).into(),
+            intensity: 8.0,
+            range: 10.0,
+            inner_angle: cgmath::Deg(15.0).into(),
+            outer_angle: cgmath::Deg(25.0).into(),
+        });
+        let light_binding = LightBinding::new(&device, &lights);
+        let light_gizmos = LightGizmos::new(
+            &device,
+            &camera_binding.bind_group_layout,
+            sc_desc.format,
+            Some(texture::Texture::DEPTH_FORMAT),
+            1,
+        );
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &texture_bind_group_layout,
+                    &camera_binding.bind_group_layout,
+                    &light_binding.bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let draw_mode = DrawMode::Fill;
+        let render_pipeline = create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            sc_desc.format,
+            draw_mode.primitive_state(supports_polygon_mode),
+        );
+
+        let ground = SceneObject::new(
+            &device,
+            Mesh::plane(12.0, 6.0),
+            vec![Instance::new((0.0, -0.5, 0.0).into())],
+        );
+
+        let pentagons = SceneObject::new(
+            &device,
+            Mesh::pentagon_prism(0.2),
+            (0..3)
+                .map(|i| Instance::new((i as f32 * 1.5 - 1.5, 0.5, 0.0).into()))
+                .collect(),
+        );
+
+        let props = SceneObject::new(
+            &device,
+            Mesh::cube(0.5),
+            vec![
+                Instance::new((-1.5, -0.25, 1.5).into()),
+                Instance::new((1.5, -0.25, 1.5).into()),
+                Instance::new((0.0, -0.25, -1.5).into()),
+            ],
+        );
+
+        let spheres = SceneObject::new(
+            &device,
+            Mesh::uv_sphere(0.35, 32, 16),
+            vec![
+                Instance::new((-1.5, 0.35, -1.0).into()),
+                Instance::new((1.5, 0.35, -1.0).into()),
+            ],
+        );
+
+        let now = Instant::now();
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline_layout,
+            render_pipeline,
+            draw_mode,
+            supports_polygon_mode,
+            force_fallback: false,
+            depth_texture,
+            diffuse_texture,
+            diffuse_bind_group,
+            camera,
+            camera_controller,
+            camera_binding,
+            lights,
+            light_binding,
+            light_gizmos,
+            show_gizmos: true,
+            ground,
+            pentagons,
+            props,
+            spheres,
+            start_time: now,
+            last_update: now,
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.depth_texture =
+            texture::Texture::create_depth_texture(&self.device, &self.sc_desc, "depth_texture");
+        self.camera.aspect = self.sc_desc.width as f32 / self.sc_desc.height as f32;
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        if self.camera_controller.process_events(event) {
+            return true;
+        }
+
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::G => {
+                    self.show_gizmos = !self.show_gizmos;
+                    true
+                }
+                VirtualKeyCode::F => {
+                    self.draw_mode = self.draw_mode.next();
+                    self.rebuild_pipeline();
+                    true
+                }
+                VirtualKeyCode::L => {
+                    if self.supports_polygon_mode {
+                        self.force_fallback = !self.force_fallback;
+                        self.rebuild_pipeline();
+                    }
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// True when the rasterizer draws lines and points, false when the meshes have to be
+    /// drawn with their edge indices or as a point list instead.
+    fn native_polygon_mode(&self) -> bool {
+        self.supports_polygon_mode && !self.force_fallback
+    }
+
+    fn rebuild_pipeline(&mut self) {
+        let native = self.native_polygon_mode();
+        self.render_pipeline = create_render_pipeline(
+            &self.device,
+            &self.render_pipeline_layout,
+            self.sc_desc.format,
+            self.draw_mode.primitive_state(native),
+        );
+        println!(
+            "Drawing {} using {}",
+            self.draw_mode.name(),
+            if native || self.draw_mode == DrawMode::Fill {
+                "the polygon mode"
+            } else {
+                "the fallback topology"
+            }
+        );
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = now - self.last_update;
+        self.last_update = now;
+        let time = (now - self.start_time).as_secs_f32();
+
+        self.camera_controller.update_camera(&mut self.camera, dt);
+        self.camera_binding.update(&self.queue, &self.camera);
+
+        // Orbit the point lights around the pentagons on opposite sides.
+        for (i, light) in self.lights.point_lights.iter_mut().enumerate() {
+            let angle = time * 0.8 + i as f32 * std::f32::consts::PI;
+            light.position = (2.5 * angle.cos(), 1.0, 2.5 * angle.sin()).into();
+        }
+
+        for (i, instance) in self.pentagons.instances.iter_mut().enumerate() {
+            instance.rotation =
+                cgmath::Quaternion::from_angle_y(cgmath::Rad(time * 0.5 + i as f32));
+        }
+        self.pentagons.write_instances(&self.queue);
+
+        self.light_binding.update(&self.queue, &self.lights);
+        self.light_gizmos.update(&self.queue, &self.lights);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                    attachment: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.camera_binding.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.light_binding.bind_group, &[]);
+
+            let native = self.native_polygon_mode();
+            for object in [&self.ground, &self.pentagons, &self.props, &self.spheres].iter() {
+                render_pass.set_vertex_buffer(1, object.instance_buffer.slice(..));
+                let instances = 0..object.instances.len() as u32;
+                match self.draw_mode {
+                    DrawMode::Wireframe if !native => {
+                        object.mesh.draw_edges(&mut render_pass, instances)
+                    }
+                    DrawMode::Points if !native => {
+                        object.mesh.draw_points(&mut render_pass, instances)
+                    }
+                    _ => object.mesh.draw(&mut render_pass, instances),
+                }
+            }
+
+            if self.show_gizmos {
+                self.light_gizmos
+                    .draw(&mut render_pass, &self.camera_binding.bind_group);
+            }
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    primitive: wgpu::PrimitiveState,
+) -> wgpu::RenderPipeline {
+    let vs_module = device.create_shader_module(&wgpu::include_spirv!("shader.vert.spv"));
+    let fs_module = device.create_shader_module(&wgpu::include_spirv!("shader.frag.spv"));
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: "main",
+            buffers: &[ModelVertex::desc(), InstanceRaw::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: "main",
+            targets: &[wgpu::ColorTargetState {
+                format: color_format,
+                alpha_blend: wgpu::BlendState::REPLACE,
+                color_blend: wgpu::BlendState::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+        }),
+        primitive,
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+            clamp_depth: false,
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+    })
+}
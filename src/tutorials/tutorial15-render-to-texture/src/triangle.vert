@@ -0,0 +1,22 @@
+#version 450
+
+// The tutorial3-pipeline-challenge triangle, spun around its center.
+const vec2 positions[3] = vec2[3] (
+    vec2(0.0, 0.5),
+    vec2(-0.5, -0.5),
+    vec2(0.5, -0.5)
+);
+
+layout(set=0, binding=0) uniform Spin {
+    float u_angle;
+};
+
+layout(location=1) out vec4 special_color;
+
+void main() {
+    float c = cos(u_angle);
+    float s = sin(u_angle);
+    vec2 position = mat2(c, s, -s, c) * positions[gl_VertexIndex];
+    special_color = vec4(position, 0.0, 1.0);
+    gl_Position = special_color;
+}
@@ -0,0 +1,280 @@
+use std::iter;
+use std::time::{Duration, Instant};
+
+use cgmath::prelude::*;
+use framework::{
+    camera::{Camera, CameraBinding, CameraController},
+    particles::{Emitter, ParticleSystem},
+};
+use winit::{event::*, window::Window};
+
+const MAX_PARTICLES: u32 = 32 * 1024;
+const BURST_SIZE: u32 = 500;
+/// Long frames, like the first one or after dragging the window, are clamped so the
+/// simulation doesn't take one huge step.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    camera: Camera,
+    camera_controller: CameraController,
+    camera_binding: CameraBinding,
+
+    particles: ParticleSystem,
+    emitters: Vec<Emitter>,
+    emitters_enabled: bool,
+    burst_emitter: Emitter,
+    cursor_position: winit::dpi::PhysicalPosition<f64>,
+
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let camera = Camera::new(
+            (0.0, 4.0, 10.0).into(),
+            (0.0, 1.5, 0.0).into(),
+            sc_desc.width as f32 / sc_desc.height as f32,
+        );
+        let camera_controller = CameraController::new(4.0);
+        let camera_binding = CameraBinding::new(&device, &camera);
+
+        let emitters = vec![
+            // Fountain
+            Emitter {
+                position: (-2.0, 0.0, 0.0).into(),
+                direction: cgmath::Vector3::unit_y(),
+                spread: 0.15,
+                speed: 7.0,
+                rate: 2000.0,
+                lifetime: 2.5,
+                size: 0.04,
+                color: [0.3, 0.5, 1.0, 0.8],
+                variance: 0.15,
+            },
+            // Fire, pushed upwards against gravity by a fast start and drag
+            Emitter {
+                position: (2.0, 0.0, 0.0).into(),
+                direction: cgmath::Vector3::unit_y(),
+                spread: 0.5,
+                speed: 2.0,
+                rate: 1500.0,
+                lifetime: 0.8,
+                size: 0.12,
+                color: [1.0, 0.4, 0.1, 0.6],
+                variance: 0.4,
+            },
+        ];
+        let burst_emitter = Emitter {
+            spread: std::f32::consts::PI,
+            speed: 5.0,
+            lifetime: 1.5,
+            size: 0.06,
+            color: [1.0, 0.9, 0.4, 1.0],
+            variance: 0.5,
+            ..Default::default()
+        };
+
+        let mut particles = ParticleSystem::new(
+            &device,
+            &camera_binding.bind_group_layout,
+            MAX_PARTICLES,
+            sc_desc.format,
+            None,
+        );
+        particles.emitters = emitters.clone();
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            camera,
+            camera_controller,
+            camera_binding,
+            particles,
+            emitters,
+            emitters_enabled: true,
+            burst_emitter,
+            cursor_position: winit::dpi::PhysicalPosition::new(0.0, 0.0),
+            last_update: Instant::now(),
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.camera.aspect = self.sc_desc.width as f32 / self.sc_desc.height as f32;
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        if self.camera_controller.process_events(event) {
+            return true;
+        }
+
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                true
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.burst_emitter.position = self.cursor_on_ground();
+                let burst_emitter = self.burst_emitter.clone();
+                self.particles.burst(&burst_emitter, BURST_SIZE);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::E => {
+                    self.emitters_enabled = !self.emitters_enabled;
+                    self.particles.emitters = if self.emitters_enabled {
+                        self.emitters.clone()
+                    } else {
+                        Vec::new()
+                    };
+                    true
+                }
+                VirtualKeyCode::C => {
+                    self.particles.clear(&self.queue);
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Where the ray through the cursor hits the y = 0 plane, or a point in front of the
+    /// camera when the ray points away from it.
+    fn cursor_on_ground(&self) -> cgmath::Point3<f32> {
+        let x = (self.cursor_position.x as f32 / self.size.width as f32) * 2.0 - 1.0;
+        let y = 1.0 - (self.cursor_position.y as f32 / self.size.height as f32) * 2.0;
+        let inverse = self
+            .camera
+            .build_view_projection_matrix()
+            .invert()
+            .unwrap_or_else(cgmath::Matrix4::identity);
+        let unproject = |z: f32| {
+            let point = inverse * cgmath::Vector4::new(x, y, z, 1.0);
+            cgmath::Point3::from_homogeneous(point)
+        };
+        let near = unproject(0.0);
+        let direction = (unproject(1.0) - near).normalize();
+
+        if direction.y < -f32::EPSILON {
+            let distance = -near.y / direction.y;
+            near + direction * distance
+        } else {
+            near + direction * 5.0
+        }
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = now - self.last_update;
+        self.last_update = now;
+
+        self.camera_controller.update_camera(&mut self.camera, dt);
+        self.camera_binding.update(&self.queue, &self.camera);
+
+        self.particles
+            .update(&self.queue, &self.camera, dt.min(MAX_FRAME_TIME));
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.particles.simulate(&mut encoder);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.02,
+                            g: 0.02,
+                            b: 0.04,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            self.particles
+                .draw(&mut render_pass, &self.camera_binding.bind_group);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+use std::iter;
+use std::time::{Duration, Instant};
+
+use cgmath::{Rad, Vector2};
+use framework::{
+    sprite::{screen_projection, Rect, Sprite, SpriteBatch, TextureId},
+    texture,
+};
+use winit::{event::*, window::Window};
+
+const INITIAL_SPRITES: usize = 4096;
+const MAX_SPRITES: usize = 1 << 20;
+const SPRITE_SIZE: f32 = 48.0;
+const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// A sprite bouncing around the window.
+struct Mover {
+    sprite: Sprite,
+    velocity: Vector2<f32>,
+    spin: f32,
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    sprite_batch: SpriteBatch,
+    tree: TextureId,
+    cartoon: TextureId,
+    movers: Vec<Mover>,
+    /// Puts every other sprite on its own layer, which splits the batch into many draws.
+    interleave_layers: bool,
+    /// Last reported number of draw calls, printed whenever it changes.
+    draw_calls: usize,
+    rng: u32,
+
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let mut sprite_batch = SpriteBatch::new(&device, sc_desc.format, INITIAL_SPRITES);
+        let tree_texture = texture::Texture::from_bytes(
+            &device,
+            &queue,
+            include_bytes!("happy-tree.png"),
+            "happy-tree.png",
+            false,
+        )
+        .unwrap();
+        let cartoon_texture = texture::Texture::from_bytes(
+            &device,
+            &queue,
+            include_bytes!("happy-tree-cartoon.png"),
+            "happy-tree-cartoon.png",
+            false,
+        )
+        .unwrap();
+        let tree = sprite_batch.add_texture(&device, &tree_texture);
+        let cartoon = sprite_batch.add_texture(&device, &cartoon_texture);
+
+        let mut state = Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            sprite_batch,
+            tree,
+            cartoon,
+            movers: Vec::new(),
+            interleave_layers: false,
+            draw_calls: 0,
+            rng: 0x2545_f491,
+            last_update: Instant::now(),
+        };
+        state.set_sprite_count(INITIAL_SPRITES);
+        state
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Up => {
+                    self.set_sprite_count((self.movers.len() * 2).min(MAX_SPRITES));
+                    true
+                }
+                VirtualKeyCode::Down => {
+                    self.set_sprite_count((self.movers.len() / 2).max(1));
+                    true
+                }
+                VirtualKeyCode::L => {
+                    self.interleave_layers = !self.interleave_layers;
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn random(&mut self) -> f32 {
+        // Xorshift
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Adds or removes sprites until there are `count`.
+    fn set_sprite_count(&mut self, count: usize) {
+        self.movers.truncate(count);
+        while self.movers.len() < count {
+            let index = self.movers.len();
+            // Every fifth sprite shows the whole photo, the rest a quarter of the cartoon
+            let mut sprite = match (index % 5) as u32 {
+                0 => Sprite::new(self.tree),
+                quarter => {
+                    let quarter = quarter - 1;
+                    let mut sprite = Sprite::new(self.cartoon);
+                    sprite.source =
+                        Rect::from_pixels(quarter % 2 * 128, quarter / 2 * 128, 128, 128, 256, 256);
+                    sprite
+                }
+            };
+            sprite.position = Vector2::new(
+                self.random() * self.size.width as f32,
+                self.random() * self.size.height as f32,
+            );
+            sprite.size = Vector2::new(SPRITE_SIZE, SPRITE_SIZE);
+            sprite.color = [
+                0.5 + 0.5 * self.random(),
+                0.5 + 0.5 * self.random(),
+                0.5 + 0.5 * self.random(),
+                1.0,
+            ];
+            let angle = self.random() * std::f32::consts::PI * 2.0;
+            let speed = 50.0 + self.random() * 200.0;
+            let mover = Mover {
+                sprite,
+                velocity: Vector2::new(angle.cos(), angle.sin()) * speed,
+                spin: (self.random() - 0.5) * 4.0,
+            };
+            self.movers.push(mover);
+        }
+        println!("{} sprites", self.movers.len());
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = (now - self.last_update).min(MAX_FRAME_TIME).as_secs_f32();
+        self.last_update = now;
+
+        let width = self.size.width as f32;
+        let height = self.size.height as f32;
+        for (index, mover) in self.movers.iter_mut().enumerate() {
+            let sprite = &mut mover.sprite;
+            sprite.position += mover.velocity * dt;
+            sprite.rotation += Rad(mover.spin * dt);
+            if sprite.position.x < 0.0 || sprite.position.x > width {
+                mover.velocity.x = -mover.velocity.x;
+                sprite.position.x = sprite.position.x.clamp(0.0, width);
+            }
+            if sprite.position.y < 0.0 || sprite.position.y > height {
+                mover.velocity.y = -mover.velocity.y;
+                sprite.position.y = sprite.position.y.clamp(0.0, height);
+            }
+            sprite.layer = if self.interleave_layers {
+                index as i32
+            } else {
+                0
+            };
+            self.sprite_batch.draw(*sprite);
+        }
+
+        self.sprite_batch
+            .set_projection(&self.queue, screen_projection(width, height));
+        self.sprite_batch.prepare(&self.device, &self.queue);
+        if self.sprite_batch.draw_calls() != self.draw_calls {
+            self.draw_calls = self.sprite_batch.draw_calls();
+            println!("{} draw calls", self.draw_calls);
+        }
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            self.sprite_batch.render(&mut render_pass);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+use std::iter;
+use std::time::{Duration, Instant};
+
+use cgmath::Vector2;
+use framework::{
+    animation::{Animation, Grid, PlayMode},
+    sprite::{screen_projection, Sprite, SpriteBatch},
+    texture,
+};
+use image::{Rgba, RgbaImage};
+use winit::{event::*, window::Window};
+
+const CELL_SIZE: u32 = 64;
+const COLUMNS: u32 = 8;
+const ROWS: u32 = 3;
+const SPRITE_SIZE: f32 = 192.0;
+const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// Draws the sheet the clips play from: a turning wheel, a pulsing dot and a burst,
+/// one row each.
+fn sprite_sheet() -> RgbaImage {
+    let center = CELL_SIZE as f32 / 2.0;
+    RgbaImage::from_fn(CELL_SIZE * COLUMNS, CELL_SIZE * ROWS, |x, y| {
+        let frame = (x / CELL_SIZE) as f32;
+        let t = frame / COLUMNS as f32;
+        let dx = (x % CELL_SIZE) as f32 + 0.5 - center;
+        let dy = (y % CELL_SIZE) as f32 + 0.5 - center;
+        let distance = (dx * dx + dy * dy).sqrt();
+        let inside = match y / CELL_SIZE {
+            0 => {
+                // A rim and a spoke turning an eighth of a turn per frame
+                let angle = t * std::f32::consts::PI * 2.0;
+                let along = dx * angle.cos() + dy * angle.sin();
+                let across = -dx * angle.sin() + dy * angle.cos();
+                let spoke = along.abs() < 24.0 && across.abs() < 3.0;
+                let rim = (distance - 26.0).abs() < 3.0;
+                if spoke || rim {
+                    Some([240, 200, 60])
+                } else {
+                    None
+                }
+            }
+            1 => {
+                if distance < 8.0 + 20.0 * t {
+                    Some([80, 200, 240])
+                } else {
+                    None
+                }
+            }
+            _ => {
+                let radius = 6.0 + 24.0 * t;
+                if (distance - radius).abs() < 4.0 * (1.0 - t) + 1.0 {
+                    Some([240, 90, 90])
+                } else {
+                    None
+                }
+            }
+        };
+        match inside {
+            Some([r, g, b]) => Rgba([r, g, b, 255]),
+            None => Rgba([0, 0, 0, 0]),
+        }
+    })
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+
+    sprite_batch: SpriteBatch,
+    /// The wheel, the dot and the burst, each with its own copy of the clips.
+    sprites: Vec<(Sprite, Animation)>,
+
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let mut sprite_batch = SpriteBatch::new(&device, sc_desc.format, 16);
+        let sheet_texture = texture::Texture::from_image(
+            &device,
+            &queue,
+            &image::DynamicImage::ImageRgba8(sprite_sheet()),
+            Some("sprite sheet"),
+            false,
+        )
+        .unwrap();
+        let grid = Grid {
+            texture: sprite_batch.add_texture(&device, &sheet_texture),
+            columns: COLUMNS,
+            rows: ROWS,
+        };
+
+        let frame = Duration::from_millis(100);
+        let clips = vec![
+            grid.clip("spin", 0..8, frame, PlayMode::Loop)
+                .with_event(0, "turn"),
+            grid.clip("pulse", 8..16, frame, PlayMode::PingPong)
+                .with_event(7, "biggest"),
+            grid.clip("burst", 16..24, Duration::from_millis(60), PlayMode::Once)
+                .with_event(0, "burst")
+                .with_event(7, "burst done"),
+        ];
+        let sprites = ["spin", "pulse", "burst"]
+            .iter()
+            .map(|name| {
+                let mut animation = Animation::new(clips.clone()).unwrap();
+                animation.play(name).unwrap();
+                let mut sprite = Sprite::new(grid.texture);
+                sprite.size = Vector2::new(SPRITE_SIZE, SPRITE_SIZE);
+                (sprite, animation)
+            })
+            .collect();
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            sprite_batch,
+            sprites,
+            last_update: Instant::now(),
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                    let factor = if *keycode == VirtualKeyCode::Up {
+                        2.0
+                    } else {
+                        0.5
+                    };
+                    for (_, animation) in &mut self.sprites {
+                        animation.speed = (animation.speed * factor).clamp(0.125, 8.0);
+                    }
+                    println!("Speed {}x", self.sprites[0].1.speed);
+                    true
+                }
+                VirtualKeyCode::P => {
+                    for (_, animation) in &mut self.sprites {
+                        animation.paused = !animation.paused;
+                    }
+                    true
+                }
+                VirtualKeyCode::Space => {
+                    for (_, animation) in &mut self.sprites {
+                        if animation.clip().mode == PlayMode::Once {
+                            animation.restart();
+                        }
+                    }
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = (now - self.last_update).min(MAX_FRAME_TIME);
+        self.last_update = now;
+
+        let width = self.size.width as f32;
+        let height = self.size.height as f32;
+        let count = self.sprites.len();
+        for (index, (sprite, animation)) in self.sprites.iter_mut().enumerate() {
+            animation.update(dt);
+            for event in animation.events() {
+                println!("{}: {}", animation.clip().name, event);
+            }
+            animation.apply(sprite);
+            sprite.position = Vector2::new(
+                width * (index + 1) as f32 / (count + 1) as f32,
+                height / 2.0,
+            );
+            self.sprite_batch.draw(*sprite);
+        }
+
+        self.sprite_batch
+            .set_projection(&self.queue, screen_projection(width, height));
+        self.sprite_batch.prepare(&self.device, &self.queue);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            self.sprite_batch.render(&mut render_pass);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use std::iter;
+use std::time::{Duration, Instant};
+
+use framework::{
+    sprite::screen_projection,
+    text::{Align, Font, GlyphMode, Section, TextRenderer},
+};
+use winit::{event::*, window::Window};
+
+const MARGIN: f32 = 12.0;
+const SMALL_SIZE: f32 = 18.0;
+const MIN_LARGE_SIZE: f32 = 12.0;
+const MAX_LARGE_SIZE: f32 = 400.0;
+const SIZE_STEP: f32 = 1.25;
+/// How often the FPS counter is refreshed.
+const FPS_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    clear_color: wgpu::Color,
+
+    text: TextRenderer,
+    large_size: f32,
+    large_mode: GlyphMode,
+    /// Frames rendered since the FPS counter was last refreshed.
+    frames: u32,
+    fps_start: Instant,
+    fps_text: String,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+        let font = Font::from_bytes(include_bytes!("DejaVuSans.ttf").to_vec()).unwrap();
+        let text = TextRenderer::new(&device, sc_desc.format, font);
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            clear_color: wgpu::Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 1.0,
+            },
+            text,
+            large_size: 64.0,
+            large_mode: GlyphMode::Sdf,
+            frames: 0,
+            fps_start: Instant::now(),
+            fps_text: String::new(),
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.clear_color = wgpu::Color {
+                    r: (position.x / self.size.width as f64).clamp(0.0, 1.0),
+                    g: (position.y / self.size.height as f64).clamp(0.0, 1.0),
+                    b: 1.0,
+                    a: 1.0,
+                };
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Up => {
+                    self.large_size = (self.large_size * SIZE_STEP).min(MAX_LARGE_SIZE);
+                    true
+                }
+                VirtualKeyCode::Down => {
+                    self.large_size = (self.large_size / SIZE_STEP).max(MIN_LARGE_SIZE);
+                    true
+                }
+                VirtualKeyCode::Tab => {
+                    self.large_mode = match self.large_mode {
+                        GlyphMode::Sdf => GlyphMode::Bitmap,
+                        GlyphMode::Bitmap => GlyphMode::Sdf,
+                    };
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.frames += 1;
+        let elapsed = self.fps_start.elapsed();
+        if elapsed >= FPS_INTERVAL {
+            let fps = self.frames as f32 / elapsed.as_secs_f32();
+            self.fps_text = format!("{:.0} FPS ({:.2} ms)", fps, 1000.0 / fps);
+            self.frames = 0;
+            self.fps_start = Instant::now();
+        }
+
+        // Dark text on light backgrounds and light text on dark ones
+        let color = self.clear_color;
+        let luminance = 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+        let ink = if luminance > 0.5 {
+            [0.0, 0.0, 0.0, 1.0]
+        } else {
+            [1.0; 4]
+        };
+
+        let width = self.size.width as f32;
+        let height = self.size.height as f32;
+        let line_height = self.text.font().line_height(SMALL_SIZE);
+        let clear_color = format!(
+            "clear_color: r {:.2} g {:.2} b {:.2}",
+            color.r, color.g, color.b
+        );
+        for (index, line) in [self.fps_text.as_str(), clear_color.as_str()]
+            .iter()
+            .enumerate()
+        {
+            self.text.queue(&Section {
+                position: [MARGIN, MARGIN + index as f32 * line_height],
+                size: SMALL_SIZE,
+                color: ink,
+                ..Section::new(line)
+            });
+        }
+
+        // Without a max_width, right aligned lines end at the position
+        let help = "Move the cursor to change the clear color\n\
+            Up/Down resize the text below\n\
+            Tab switches between bitmap and SDF glyphs";
+        let help_height = self.text.measure(&Section {
+            size: SMALL_SIZE,
+            ..Section::new(help)
+        })[1];
+        self.text.queue(&Section {
+            position: [width - MARGIN, height - MARGIN - help_height],
+            size: SMALL_SIZE,
+            color: ink,
+            align: Align::Right,
+            ..Section::new(help)
+        });
+
+        let mode = match self.large_mode {
+            GlyphMode::Bitmap => "bitmap",
+            GlyphMode::Sdf => "signed distance field",
+        };
+        let large = format!(
+            "{}px text drawn with {} glyphs, wrapped to fit the window",
+            self.large_size.round(),
+            mode
+        );
+        let mut section = Section {
+            size: self.large_size,
+            color: ink,
+            max_width: Some(width * 0.8),
+            align: Align::Center,
+            mode: self.large_mode,
+            ..Section::new(&large)
+        };
+        let large_height = self.text.measure(&section)[1];
+        section.position = [width * 0.1, (height - large_height) / 2.0];
+        self.text.queue(&section);
+
+        self.text
+            .set_projection(&self.queue, screen_projection(width, height));
+        self.text.prepare(&self.device, &self.queue);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            self.text.render(&mut render_pass);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+//! Procedural brick textures so the tutorial doesn't need to ship image assets.
+
+const SIZE: u32 = 256;
+const BRICK_WIDTH: u32 = 64;
+const BRICK_HEIGHT: u32 = 32;
+const MORTAR: u32 = 3;
+const BEVEL: f32 = 4.0;
+const BUMP_STRENGTH: f32 = 2.0;
+
+/// Which brick a pixel belongs to and how far it is from the nearest mortar line.
+fn brick_at(x: u32, y: u32) -> (u32, u32, u32) {
+    let row = y / BRICK_HEIGHT;
+    // Every other row is offset by half a brick.
+    let shifted = x + (row % 2) * BRICK_WIDTH / 2;
+    let column = shifted / BRICK_WIDTH;
+    let bx = shifted % BRICK_WIDTH;
+    let by = y % BRICK_HEIGHT;
+    let edge = bx
+        .min(BRICK_WIDTH - 1 - bx)
+        .min(by)
+        .min(BRICK_HEIGHT - 1 - by);
+    (row, column % (SIZE / BRICK_WIDTH), edge)
+}
+
+fn height(x: u32, y: u32) -> f32 {
+    let (_, _, edge) = brick_at(x % SIZE, y % SIZE);
+    if edge < MORTAR {
+        0.0
+    } else {
+        ((edge - MORTAR) as f32 / BEVEL).min(1.0)
+    }
+}
+
+fn hash(row: u32, column: u32) -> f32 {
+    let mut h = row.wrapping_mul(374_761_393) ^ column.wrapping_mul(668_265_263);
+    h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+    (h & 0xffff) as f32 / 65535.0
+}
+
+pub fn diffuse() -> image::DynamicImage {
+    let img = image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        let (row, column, edge) = brick_at(x, y);
+        if edge < MORTAR {
+            image::Rgba([180, 175, 165, 255])
+        } else {
+            let shade = 0.8 + 0.2 * hash(row, column);
+            image::Rgba([
+                (170.0 * shade) as u8,
+                (74.0 * shade) as u8,
+                (52.0 * shade) as u8,
+                255,
+            ])
+        }
+    });
+    image::DynamicImage::ImageRgba8(img)
+}
+
+/// A tangent space normal map of the brick height field with green pointing up.
+pub fn normal_map() -> image::DynamicImage {
+    let img = image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        let left = height((x + SIZE - 1) % SIZE, y);
+        let right = height((x + 1) % SIZE, y);
+        let above = height(x, (y + SIZE - 1) % SIZE);
+        let below = height(x, (y + 1) % SIZE);
+
+        // Image rows grow downward while tangent space Y points up.
+        let dx = (right - left) * 0.5 * BUMP_STRENGTH;
+        let dy = (above - below) * 0.5 * BUMP_STRENGTH;
+        let length = (dx * dx + dy * dy + 1.0).sqrt();
+        let normal = [-dx / length, -dy / length, 1.0 / length];
+
+        image::Rgba([
+            ((normal[0] * 0.5 + 0.5) * 255.0) as u8,
+            ((normal[1] * 0.5 + 0.5) * 255.0) as u8,
+            ((normal[2] * 0.5 + 0.5) * 255.0) as u8,
+            255,
+        ])
+    });
+    image::DynamicImage::ImageRgba8(img)
+}
+
+/// A normal map that leaves the vertex normals untouched.
+pub fn flat_normal_map() -> image::DynamicImage {
+    image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        1,
+        1,
+        image::Rgba([128, 128, 255, 255]),
+    ))
+}
@@ -0,0 +1,187 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec3 v_position;
+layout(location=2) in vec3 v_normal;
+layout(location=3) in vec4 v_tangent;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform Material {
+    vec4 u_base_color_factor;
+    vec3 u_emissive_factor;
+    float u_metallic_factor;
+    float u_roughness_factor;
+    float u_normal_scale;
+    float u_occlusion_strength;
+};
+layout(set=0, binding=1) uniform texture2D t_base_color;
+layout(set=0, binding=2) uniform sampler s_base_color;
+layout(set=0, binding=3) uniform texture2D t_metallic_roughness;
+layout(set=0, binding=4) uniform sampler s_metallic_roughness;
+layout(set=0, binding=5) uniform texture2D t_normal;
+layout(set=0, binding=6) uniform sampler s_normal;
+layout(set=0, binding=7) uniform texture2D t_occlusion;
+layout(set=0, binding=8) uniform sampler s_occlusion;
+layout(set=0, binding=9) uniform texture2D t_emissive;
+layout(set=0, binding=10) uniform sampler s_emissive;
+
+layout(set=1, binding=0) uniform Camera {
+    vec4 u_view_position;
+    mat4 u_view_proj;
+};
+
+struct PointLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    float intensity;
+};
+
+struct DirectionalLight {
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float _padding;
+};
+
+struct SpotLight {
+    vec3 position;
+    float range;
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+// Must match the MAX_* constants in framework::light
+const uint MAX_POINT_LIGHTS = 8;
+const uint MAX_DIRECTIONAL_LIGHTS = 4;
+const uint MAX_SPOT_LIGHTS = 8;
+
+layout(set=2, binding=0) uniform Lights {
+    PointLight u_point_lights[MAX_POINT_LIGHTS];
+    DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+    SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+    vec3 u_ambient_color;
+    float u_ambient_strength;
+    uvec4 u_light_counts;
+};
+
+const float PI = 3.14159265359;
+
+struct Surface {
+    vec3 albedo;
+    float metallic;
+    float roughness;
+    vec3 normal;
+    vec3 view_dir;
+    vec3 f0;
+};
+
+// Trowbridge-Reitz GGX normal distribution
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+// Smith's method with the Schlick-GGX approximation for direct lighting
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float r = roughness + 1.0;
+    float k = (r * r) / 8.0;
+    float ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    float ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return ggx_v * ggx_l;
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Outgoing radiance from one light, light_dir points from the surface towards the light
+vec3 cook_torrance(Surface surface, vec3 light_dir, vec3 radiance) {
+    vec3 half_dir = normalize(surface.view_dir + light_dir);
+    float n_dot_l = max(dot(surface.normal, light_dir), 0.0);
+    float n_dot_v = max(dot(surface.normal, surface.view_dir), 0.0001);
+    float n_dot_h = max(dot(surface.normal, half_dir), 0.0);
+
+    float d = distribution_ggx(n_dot_h, surface.roughness);
+    float g = geometry_smith(n_dot_v, n_dot_l, surface.roughness);
+    vec3 f = fresnel_schlick(max(dot(half_dir, surface.view_dir), 0.0), surface.f0);
+
+    vec3 specular = d * g * f / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    // Metals have no diffuse, and energy reflected specularly can't also be diffused
+    vec3 k_diffuse = (1.0 - f) * (1.0 - surface.metallic);
+
+    return (k_diffuse * surface.albedo / PI + specular) * radiance * n_dot_l;
+}
+
+float range_attenuation(float light_distance, float range) {
+    float ratio = light_distance / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (light_distance * light_distance + 1.0);
+}
+
+vec3 perturb_normal(vec3 normal, vec4 tangent, vec3 tangent_space_normal) {
+    vec3 t = normalize(tangent.xyz - normal * dot(normal, tangent.xyz));
+    vec3 b = cross(normal, t) * tangent.w;
+    mat3 tbn = mat3(t, b, normal);
+    return normalize(tbn * tangent_space_normal);
+}
+
+void main() {
+    vec4 base_color = texture(sampler2D(t_base_color, s_base_color), v_tex_coords) * u_base_color_factor;
+    vec4 metallic_roughness = texture(sampler2D(t_metallic_roughness, s_metallic_roughness), v_tex_coords);
+    float occlusion = texture(sampler2D(t_occlusion, s_occlusion), v_tex_coords).r;
+    vec3 emissive = texture(sampler2D(t_emissive, s_emissive), v_tex_coords).rgb * u_emissive_factor;
+
+    vec3 tangent_space_normal = texture(sampler2D(t_normal, s_normal), v_tex_coords).xyz * 2.0 - 1.0;
+    tangent_space_normal.xy *= u_normal_scale;
+
+    Surface surface;
+    surface.albedo = base_color.rgb;
+    surface.metallic = clamp(metallic_roughness.b * u_metallic_factor, 0.0, 1.0);
+    // Very low roughness makes the highlight vanishingly small, so keep a floor
+    surface.roughness = clamp(metallic_roughness.g * u_roughness_factor, 0.04, 1.0);
+    surface.normal = perturb_normal(normalize(v_normal), v_tangent, normalize(tangent_space_normal));
+    surface.view_dir = normalize(u_view_position.xyz - v_position);
+    // Dielectrics reflect about 4% head on, metals tint the reflection with their albedo
+    surface.f0 = mix(vec3(0.04), surface.albedo, surface.metallic);
+
+    vec3 color = vec3(0.0);
+
+    for (uint i = 0; i < u_light_counts.x; i++) {
+        PointLight light = u_point_lights[i];
+        vec3 to_light = light.position - v_position;
+        float light_distance = length(to_light);
+        vec3 radiance = light.color * light.intensity * range_attenuation(light_distance, light.range);
+        color += cook_torrance(surface, to_light / light_distance, radiance);
+    }
+
+    for (uint i = 0; i < u_light_counts.y; i++) {
+        DirectionalLight light = u_directional_lights[i];
+        color += cook_torrance(surface, -light.direction, light.color * light.intensity);
+    }
+
+    for (uint i = 0; i < u_light_counts.z; i++) {
+        SpotLight light = u_spot_lights[i];
+        vec3 to_light = light.position - v_position;
+        float light_distance = length(to_light);
+        vec3 light_dir = to_light / light_distance;
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-light_dir, light.direction));
+        vec3 radiance = light.color * light.intensity * cone * range_attenuation(light_distance, light.range);
+        color += cook_torrance(surface, light_dir, radiance);
+    }
+
+    float ambient_occlusion = mix(1.0, occlusion, u_occlusion_strength);
+    color += u_ambient_color * u_ambient_strength * surface.albedo * ambient_occlusion;
+    color += emissive;
+
+    // Reinhard tonemapping so bright highlights roll off instead of clipping
+    color = color / (color + vec3(1.0));
+
+    f_color = vec4(color, base_color.a);
+}
@@ -0,0 +1,46 @@
+#version 450
+
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec2 a_tex_coords;
+layout(location=2) in vec3 a_normal;
+layout(location=3) in vec4 a_tangent;
+
+layout(location=5) in vec4 model_matrix_0;
+layout(location=6) in vec4 model_matrix_1;
+layout(location=7) in vec4 model_matrix_2;
+layout(location=8) in vec4 model_matrix_3;
+layout(location=9) in vec3 normal_matrix_0;
+layout(location=10) in vec3 normal_matrix_1;
+layout(location=11) in vec3 normal_matrix_2;
+
+layout(location=0) out vec2 v_tex_coords;
+layout(location=1) out vec3 v_position;
+layout(location=2) out vec3 v_normal;
+layout(location=3) out vec4 v_tangent;
+
+layout(set=1, binding=0) uniform Camera {
+    vec4 u_view_position;
+    mat4 u_view_proj;
+};
+
+void main() {
+    mat4 model_matrix = mat4(
+        model_matrix_0,
+        model_matrix_1,
+        model_matrix_2,
+        model_matrix_3
+    );
+    mat3 normal_matrix = mat3(
+        normal_matrix_0,
+        normal_matrix_1,
+        normal_matrix_2
+    );
+
+    vec4 world_position = model_matrix * vec4(a_position, 1.0);
+    v_tex_coords = a_tex_coords;
+    v_position = world_position.xyz;
+    v_normal = normal_matrix * a_normal;
+    // Tangents lie in the surface, so they follow the model matrix rather than the normal matrix
+    v_tangent = vec4(mat3(model_matrix) * a_tangent.xyz, a_tangent.w);
+    gl_Position = u_view_proj * world_position;
+}
@@ -0,0 +1,513 @@
+use std::iter;
+use std::time::Instant;
+
+use cgmath::prelude::*;
+use framework::{
+    camera::{Camera, CameraBinding, CameraController},
+    instance::{Instance, InstanceRaw},
+    light::{DirectionalLight, LightBinding, LightGizmos, Lights, PointLight},
+    material::{DefaultTextures, Material, MaterialFactors, MaterialMaps},
+    mesh::{GpuMesh, Mesh, ModelVertex},
+    texture,
+};
+use wgpu::util::DeviceExt;
+use winit::{event::*, window::Window};
+
+const GRID_SIZE: usize = 5;
+const GRID_SPACING: f32 = 1.2;
+
+struct SceneObject {
+    mesh: GpuMesh,
+    instances: Vec<Instance>,
+    instance_buffer: wgpu::Buffer,
+}
+
+impl SceneObject {
+    fn new(device: &wgpu::Device, mesh: Mesh, instances: Vec<Instance>) -> Self {
+        let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Instance Buffer", mesh.name)),
+            contents: bytemuck::cast_slice(&instance_data),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+
+        Self {
+            mesh: mesh.upload(device),
+            instances,
+            instance_buffer,
+        }
+    }
+
+    fn write_instances(&self, queue: &wgpu::Queue) {
+        let instance_data = self
+            .instances
+            .iter()
+            .map(Instance::to_raw)
+            .collect::<Vec<_>>();
+        queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&instance_data),
+        );
+    }
+}
+
+/// Alternating polished metal and rough plastic squares, laid out the way glTF expects:
+/// roughness in green and metallic in blue.
+fn checker_metallic_roughness() -> image::DynamicImage {
+    let img = image::RgbaImage::from_fn(64, 64, |x, y| {
+        if (x / 16 + y / 16) % 2 == 0 {
+            image::Rgba([0, 40, 255, 255])
+        } else {
+            image::Rgba([0, 200, 0, 255])
+        }
+    });
+    image::DynamicImage::ImageRgba8(img)
+}
+
+pub struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline: wgpu::RenderPipeline,
+    depth_texture: texture::Texture,
+
+    camera: Camera,
+    camera_controller: CameraController,
+    camera_binding: CameraBinding,
+
+    lights: Lights,
+    light_binding: LightBinding,
+    light_gizmos: LightGizmos,
+    show_gizmos: bool,
+
+    ground: SceneObject,
+    ground_material: Material,
+    /// One instance per sphere, drawn one at a time since each has its own material.
+    spheres: SceneObject,
+    sphere_materials: Vec<Material>,
+    pentagon: SceneObject,
+    pentagon_material: Material,
+    lamp: SceneObject,
+    lamp_material: Material,
+
+    start_time: Instant,
+    last_update: Instant,
+}
+
+impl State {
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: adapter.get_swap_chain_preferred_format(&surface),
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let depth_texture =
+            texture::Texture::create_depth_texture(&device, &sc_desc, "depth_texture");
+
+        let material_bind_group_layout = Material::bind_group_layout(&device);
+        let default_textures = DefaultTextures::new(&device, &queue);
+
+        // Metallic grows down the rows and roughness across the columns.
+        let mut sphere_materials = Vec::with_capacity(GRID_SIZE * GRID_SIZE);
+        for row in 0..GRID_SIZE {
+            for column in 0..GRID_SIZE {
+                let metallic = row as f32 / (GRID_SIZE - 1) as f32;
+                let roughness = column as f32 / (GRID_SIZE - 1) as f32;
+                sphere_materials.push(Material::new(
+                    &device,
+                    &material_bind_group_layout,
+                    &default_textures,
+                    &format!("sphere_{}_{}", row, column),
+                    MaterialFactors {
+                        base_color: [0.9, 0.3, 0.2, 1.0],
+                        metallic,
+                        roughness,
+                        ..Default::default()
+                    },
+                    MaterialMaps::default(),
+                ));
+            }
+        }
+
+        let ground_material = Material::new(
+            &device,
+            &material_bind_group_layout,
+            &default_textures,
+            "ground",
+            MaterialFactors {
+                base_color: [0.5, 0.5, 0.5, 1.0],
+                metallic: 0.0,
+                roughness: 0.8,
+                ..Default::default()
+            },
+            MaterialMaps::default(),
+        );
+
+        let diffuse_bytes = include_bytes!("happy-tree.png");
+        let pentagon_material = Material::new(
+            &device,
+            &material_bind_group_layout,
+            &default_textures,
+            "pentagon",
+            // The map supplies metallic and roughness directly.
+            MaterialFactors::default(),
+            MaterialMaps {
+                base_color: Some(
+                    texture::Texture::from_bytes(
+                        &device,
+                        &queue,
+                        diffuse_bytes,
+                        "happy-tree.png",
+                        false,
+                    )
+                    .unwrap(),
+                ),
+                metallic_roughness: Some(
+                    texture::Texture::from_image(
+                        &device,
+                        &queue,
+                        &checker_metallic_roughness(),
+                        Some("checker_metallic_roughness"),
+                        true,
+                    )
+                    .unwrap(),
+                ),
+                ..Default::default()
+            },
+        );
+
+        let lamp_material = Material::new(
+            &device,
+            &material_bind_group_layout,
+            &default_textures,
+            "lamp",
+            MaterialFactors {
+                base_color: [0.0, 0.0, 0.0, 1.0],
+                metallic: 0.0,
+                roughness: 0.5,
+                emissive: [1.0, 0.8, 0.5],
+                ..Default::default()
+            },
+            MaterialMaps::default(),
+        );
+
+        let camera = Camera::new(
+            (0.0, 1.5, 7.0).into(),
+            (0.0, 0.0, 0.0).into(),
+            sc_desc.width as f32 / sc_desc.height as f32,
+        );
+        let camera_controller = CameraController::new(4.0);
+        let camera_binding = CameraBinding::new(&device, &camera);
+
+        let mut lights = Lights::new();
+        lights.point_lights.push(PointLight {
+            position: (0.0, 2.0, 3.0).into(),
+            color: (1.0, 0.8, 0.5).into(),
+            intensity: 20.0,
+            range: 15.0,
+        });
+        lights.point_lights.push(PointLight {
+            position: (-3.0, 3.0, 2.0).into(),
+            color: (0.6, 0.7, 1.0).into(),
+            intensity: 15.0,
+            range: 15.0,
+        });
+        lights.directional_lights.push(DirectionalLight {
+            direction: (0.3, -1.0, -0.5).into(),
+            color: (1.0, 1.0, 1.0).into(),
+            intensity: 1.5,
+        });
+        let light_binding = LightBinding::new(&device, &lights);
+        let light_gizmos = LightGizmos::new(
+            &device,
+            &camera_binding.bind_group_layout,
+            sc_desc.format,
+            Some(texture::Texture::DEPTH_FORMAT),
+            1,
+        );
+
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!("shader.vert.spv"));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!("shader.frag.spv"));
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &material_bind_group_layout,
+                    &camera_binding.bind_group_layout,
+                    &light_binding.bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &vs_module,
+                entry_point: "main",
+                buffers: &[ModelVertex::desc(), InstanceRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fs_module,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format: sc_desc.format,
+                    alpha_blend: wgpu::BlendState::REPLACE,
+                    color_blend: wgpu::BlendState::REPLACE,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+                clamp_depth: false,
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        let ground = SceneObject::new(
+            &device,
+            Mesh::plane(16.0, 1.0),
+            vec![Instance::new((0.0, -2.0, 0.0).into())],
+        );
+
+        let offset = (GRID_SIZE - 1) as f32 * GRID_SPACING * 0.5;
+        let spheres = SceneObject::new(
+            &device,
+            Mesh::uv_sphere(0.5, 48, 24),
+            (0..GRID_SIZE * GRID_SIZE)
+                .map(|i| {
+                    let row = (i / GRID_SIZE) as f32;
+                    let column = (i % GRID_SIZE) as f32;
+                    Instance::new(
+                        (
+                            column * GRID_SPACING - offset,
+                            offset - row * GRID_SPACING,
+                            -1.0,
+                        )
+                            .into(),
+                    )
+                })
+                .collect(),
+        );
+
+        let pentagon = SceneObject::new(
+            &device,
+            Mesh::pentagon_prism(0.2),
+            vec![Instance::new((offset + 2.0, 0.0, 0.0).into())],
+        );
+
+        let mut lamp_instance = Instance::new((0.0, 2.0, 3.0).into());
+        lamp_instance.scale = (0.15, 0.15, 0.15).into();
+        let lamp = SceneObject::new(&device, Mesh::uv_sphere(1.0, 16, 8), vec![lamp_instance]);
+
+        let now = Instant::now();
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline,
+            depth_texture,
+            camera,
+            camera_controller,
+            camera_binding,
+            lights,
+            light_binding,
+            light_gizmos,
+            show_gizmos: false,
+            ground,
+            ground_material,
+            spheres,
+            sphere_materials,
+            pentagon,
+            pentagon_material,
+            lamp,
+            lamp_material,
+            start_time: now,
+            last_update: now,
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.depth_texture =
+            texture::Texture::create_depth_texture(&self.device, &self.sc_desc, "depth_texture");
+        self.camera.aspect = self.sc_desc.width as f32 / self.sc_desc.height as f32;
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        if self.camera_controller.process_events(event) {
+            return true;
+        }
+
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::G),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_gizmos = !self.show_gizmos;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = now - self.last_update;
+        self.last_update = now;
+        let time = (now - self.start_time).as_secs_f32();
+
+        self.camera_controller.update_camera(&mut self.camera, dt);
+        self.camera_binding.update(&self.queue, &self.camera);
+
+        // Swing the warm light in front of the grid so the highlights slide across it,
+        // with the emissive lamp sphere marking where it is.
+        let angle = time * 0.6;
+        let position = cgmath::Vector3::new(3.0 * angle.sin(), 2.0, 3.0 * angle.cos().abs());
+        self.lights.point_lights[0].position = position;
+        self.lamp.instances[0].position = position;
+        self.lamp.write_instances(&self.queue);
+
+        self.pentagon.instances[0].rotation =
+            cgmath::Quaternion::from_angle_y(cgmath::Rad(time * 0.5));
+        self.pentagon.write_instances(&self.queue);
+
+        self.light_binding.update(&self.queue, &self.lights);
+        self.light_gizmos.update(&self.queue, &self.lights);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
+        let frame = self.swap_chain.get_current_frame()?.output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                    attachment: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(1, &self.camera_binding.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.light_binding.bind_group, &[]);
+
+            for (object, material) in [
+                (&self.ground, &self.ground_material),
+                (&self.pentagon, &self.pentagon_material),
+                (&self.lamp, &self.lamp_material),
+            ]
+            .iter()
+            {
+                render_pass.set_bind_group(0, &material.bind_group, &[]);
+                render_pass.set_vertex_buffer(1, object.instance_buffer.slice(..));
+                object
+                    .mesh
+                    .draw(&mut render_pass, 0..object.instances.len() as u32);
+            }
+
+            render_pass.set_vertex_buffer(1, self.spheres.instance_buffer.slice(..));
+            for (i, material) in self.sphere_materials.iter().enumerate() {
+                let i = i as u32;
+                render_pass.set_bind_group(0, &material.bind_group, &[]);
+                self.spheres.mesh.draw(&mut render_pass, i..i + 1);
+            }
+
+            if self.show_gizmos {
+                self.light_gizmos
+                    .draw(&mut render_pass, &self.camera_binding.bind_group);
+            }
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
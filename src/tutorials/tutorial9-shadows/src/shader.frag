@@ -0,0 +1,162 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec3 v_position;
+layout(location=2) in vec3 v_normal;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_diffuse;
+layout(set=0, binding=1) uniform sampler s_diffuse;
+
+layout(set=1, binding=0) uniform Camera {
+    vec4 u_view_position;
+    mat4 u_view_proj;
+};
+
+struct PointLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    float intensity;
+};
+
+struct DirectionalLight {
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float _padding;
+};
+
+struct SpotLight {
+    vec3 position;
+    float range;
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+// Must match the MAX_* constants in framework::light
+const uint MAX_POINT_LIGHTS = 8;
+const uint MAX_DIRECTIONAL_LIGHTS = 4;
+const uint MAX_SPOT_LIGHTS = 8;
+
+layout(set=2, binding=0) uniform Lights {
+    PointLight u_point_lights[MAX_POINT_LIGHTS];
+    DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+    SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+    vec3 u_ambient_color;
+    float u_ambient_strength;
+    uvec4 u_light_counts;
+};
+
+// Must match MAX_SHADOW_MAPS in framework::shadow
+const uint MAX_SHADOW_MAPS = 4;
+
+layout(set=3, binding=0) uniform Shadows {
+    mat4 u_light_view_proj[MAX_SHADOW_MAPS];
+    float u_shadow_texel_size;
+    int u_pcf_radius;
+    // Directional lights take the first layers, spot lights follow
+    uint u_shadow_directional_count;
+    uint u_shadow_count;
+};
+layout(set=3, binding=1) uniform texture2DArray t_shadow;
+layout(set=3, binding=2) uniform samplerShadow s_shadow;
+
+const float SHININESS = 32.0;
+
+struct Lighting {
+    vec3 diffuse;
+    vec3 specular;
+};
+
+// light_dir points from the surface towards the light
+void blinn_phong(inout Lighting lighting, vec3 light_dir, vec3 radiance, vec3 normal, vec3 view_dir) {
+    float diffuse_strength = max(dot(normal, light_dir), 0.0);
+    vec3 half_dir = normalize(view_dir + light_dir);
+    float specular_strength = pow(max(dot(normal, half_dir), 0.0), SHININESS);
+    // Surfaces facing away from the light shouldn't pick up a highlight
+    if (diffuse_strength <= 0.0) {
+        specular_strength = 0.0;
+    }
+
+    lighting.diffuse += radiance * diffuse_strength;
+    lighting.specular += radiance * specular_strength;
+}
+
+// Inverse square falloff that reaches exactly zero at the light's range
+float range_attenuation(float light_distance, float range) {
+    float ratio = light_distance / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (light_distance * light_distance + 1.0);
+}
+
+// 1.0 when fully lit, 0.0 when fully in shadow
+float shadow_factor(uint layer) {
+    if (layer >= u_shadow_count) {
+        return 1.0;
+    }
+
+    vec4 light_clip = u_light_view_proj[layer] * vec4(v_position, 1.0);
+    // Behind a spot light
+    if (light_clip.w <= 0.0) {
+        return 1.0;
+    }
+    vec3 light_ndc = light_clip.xyz / light_clip.w;
+    // Texture space has y pointing down
+    vec2 uv = light_ndc.xy * vec2(0.5, -0.5) + 0.5;
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 || light_ndc.z > 1.0) {
+        return 1.0;
+    }
+
+    float lit = 0.0;
+    for (int y = -u_pcf_radius; y <= u_pcf_radius; y++) {
+        for (int x = -u_pcf_radius; x <= u_pcf_radius; x++) {
+            vec2 offset = vec2(x, y) * u_shadow_texel_size;
+            lit += texture(sampler2DArrayShadow(t_shadow, s_shadow), vec4(uv + offset, float(layer), light_ndc.z));
+        }
+    }
+    float taps = float(2 * u_pcf_radius + 1);
+    return lit / (taps * taps);
+}
+
+void main() {
+    vec4 object_color = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords);
+    vec3 normal = normalize(v_normal);
+    vec3 view_dir = normalize(u_view_position.xyz - v_position);
+
+    Lighting lighting = Lighting(vec3(0.0), vec3(0.0));
+
+    for (uint i = 0; i < u_light_counts.x; i++) {
+        PointLight light = u_point_lights[i];
+        vec3 to_light = light.position - v_position;
+        float light_distance = length(to_light);
+        vec3 radiance = light.color * light.intensity * range_attenuation(light_distance, light.range);
+        blinn_phong(lighting, to_light / light_distance, radiance, normal, view_dir);
+    }
+
+    for (uint i = 0; i < u_light_counts.y; i++) {
+        DirectionalLight light = u_directional_lights[i];
+        vec3 radiance = light.color * light.intensity * shadow_factor(i);
+        blinn_phong(lighting, -light.direction, radiance, normal, view_dir);
+    }
+
+    for (uint i = 0; i < u_light_counts.z; i++) {
+        SpotLight light = u_spot_lights[i];
+        vec3 to_light = light.position - v_position;
+        float light_distance = length(to_light);
+        vec3 light_dir = to_light / light_distance;
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-light_dir, light.direction));
+        float shadow = shadow_factor(u_shadow_directional_count + i);
+        vec3 radiance = light.color * light.intensity * cone * shadow * range_attenuation(light_distance, light.range);
+        blinn_phong(lighting, light_dir, radiance, normal, view_dir);
+    }
+
+    vec3 ambient = u_ambient_color * u_ambient_strength;
+    vec3 result = (ambient + lighting.diffuse) * object_color.rgb + lighting.specular;
+
+    f_color = vec4(result, object_color.a);
+}